@@ -0,0 +1,53 @@
+use crate::core::simdata::SimData;
+use crate::core::verlet_lists::create_verlet_lists;
+
+pub mod bond_order;
+pub mod dynamics;
+pub mod flow;
+pub mod forces;
+pub mod structure;
+pub mod voronoi;
+
+pub use bond_order::{global_psi, local_psi};
+pub use dynamics::{
+    cage_relative_msd, d2min, displacement_field, lindemann_parameter, localization_length, participation_ratio,
+};
+pub use flow::{enstrophy, flow_fields, radial_velocity_profile, velocity_correlation};
+pub use forces::{per_particle_stress, potential_profile, radial_pressure_profile, stress_tensor};
+pub use structure::{
+    bond_angle_distribution, correlation_length, fabric_tensor, free_volume_map, overlap_histogram, pair_entropy,
+    partial_rdf, rattler_fraction, rdf,
+};
+pub use voronoi::{count_defects, voronoi_areas, voronoi_coordination};
+
+/// Least squares fit of a line to a set of (x, y) points, returning (slope, intercept). The result is NaN if there are
+/// fewer than two distinct x values.
+pub fn linear_fit(points: &[(f64, f64)]) -> (f64, f64) {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x) * (x - mean_x)).sum();
+    let slope = covariance / variance;
+    (slope, mean_y - slope * mean_x)
+}
+
+/// Find the neighbors of each particle, the particles whose centers are closer than `factor` times the sum of the
+/// two particles' radii.
+pub fn neighbors_within(sim_data: &SimData, factor: f64) -> Vec<Vec<usize>> {
+    let mut neighbors = vec![vec![]; sim_data.num_particles()];
+    if sim_data.is_empty() {
+        return neighbors;
+    }
+
+    let max_radius = sim_data.radii.iter().copied().fold(f64::NAN, f64::max);
+    let verlet_lists = create_verlet_lists(sim_data, f64::max(factor - 1.0, 0.0) * 2.0 * max_radius);
+    for (id1, id2) in &verlet_lists {
+        let range = factor * (sim_data.radii[id1] + sim_data.radii[id2]);
+        if sim_data.distance_sqr_between(id1, id2) < range * range {
+            neighbors[id1].push(id2);
+            neighbors[id2].push(id1);
+        }
+    }
+    neighbors
+}
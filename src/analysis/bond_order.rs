@@ -0,0 +1,74 @@
+use crate::analysis::neighbors_within;
+use crate::core::simdata::SimData;
+
+/// Particles are bonded neighbors if their centers are closer than this factor times the sum of their radii.
+const BOND_FACTOR: f64 = 1.2;
+
+/// Compute the n-fold bond orientational order parameter of each particle,
+///     psi_n(j) = (1 / N_j) sum_k exp(i n theta_jk),
+/// where the sum is over the N_j bonded neighbors of particle j, and theta_jk is the angle of the bond between j
+/// and k. Returns the (real, imaginary) parts. Particles without neighbors have psi_n = 0.
+pub fn local_psi(sim_data: &SimData, n: u32) -> Vec<(f64, f64)> {
+    let neighbors = neighbors_within(sim_data, BOND_FACTOR);
+
+    let mut psi = Vec::with_capacity(sim_data.num_particles());
+    for (id, neighbors) in neighbors.iter().enumerate() {
+        if neighbors.is_empty() {
+            psi.push((0.0, 0.0));
+            continue;
+        }
+        let (mut re, mut im) = (0.0, 0.0);
+        for &other in neighbors {
+            let bond = sim_data.displacement_between(id, other);
+            let theta = f64::atan2(bond.y, bond.x);
+            re += f64::cos(n as f64 * theta);
+            im += f64::sin(n as f64 * theta);
+        }
+        psi.push((re / neighbors.len() as f64, im / neighbors.len() as f64));
+    }
+    psi
+}
+
+/// Compute the magnitude of the system averaged n-fold bond orientational order parameter. This is near one when the
+/// system has long range n-fold order, e.g. n = 6 for hexagonal order and n = 4 for square order.
+pub fn global_psi(sim_data: &SimData, n: u32) -> f64 {
+    if sim_data.is_empty() {
+        return 0.0;
+    }
+    let psi = local_psi(sim_data, n);
+    let re: f64 = psi.iter().map(|p| p.0).sum();
+    let im: f64 = psi.iter().map(|p| p.1).sum();
+    f64::sqrt(re * re + im * im) / psi.len() as f64
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::simdata::Bounds;
+    use crate::utils::init::{hexagonal_lattice, square_lattice};
+
+    #[test]
+    fn test_global_psi_square_lattice() {
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let sim_data = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.5));
+
+        assert_close!(global_psi(&sim_data, 4), 1.0, 1.0e-9);
+        assert!(global_psi(&sim_data, 6) < 0.1);
+    }
+
+    #[test]
+    fn test_global_psi_hexagonal_lattice() {
+        let height = 6.0 * f64::sqrt(3.0) / 2.0;
+        let bounds = Bounds::from((0.0, 6.0, 0.0, height));
+        let sim_data = SimData::new_with_particles(bounds, &hexagonal_lattice(6, 6, bounds, 0.5));
+
+        assert_close!(global_psi(&sim_data, 6), 1.0, 1.0e-9);
+        assert!(global_psi(&sim_data, 4) < 0.1);
+    }
+}
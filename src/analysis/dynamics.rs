@@ -0,0 +1,297 @@
+use crate::analysis::neighbors_within;
+use crate::core::monitor::msd::MsdMonitor;
+use crate::core::simdata::{Bounds, SimData};
+use crate::core::vector::{Position, Vector};
+use crate::core::verlet_lists::create_verlet_lists;
+
+/// The largest log-log slope of the MSD, d ln(MSD) / d ln(t), for which the MSD still counts as a plateau. Ballistic
+/// motion has a slope of 2 and diffusive motion a slope of 1.
+const PLATEAU_SLOPE: f64 = 0.3;
+
+/// Find the localization (caging) length from an MSD series of (time, msd) pairs, such as `MsdMonitor::series`.
+///
+/// Caged particles show a plateau in the MSD, where the log-log slope of the MSD drops well below the diffusive
+/// value of 1. The localization length is the square root of the MSD where the slope is smallest. Returns None if
+/// the MSD has no plateau, as for a liquid. Points with non-positive times or MSDs are ignored.
+pub fn localization_length(msd_series: &[(f64, f64)]) -> Option<f64> {
+    let points: Vec<(f64, f64)> = msd_series.iter().copied().filter(|(t, msd)| 0.0 < *t && 0.0 < *msd).collect();
+
+    let mut plateau: Option<(f64, f64)> = None; // (slope, msd)
+    for pair in points.windows(2) {
+        let ((t1, msd1), (t2, msd2)) = (pair[0], pair[1]);
+        if t2 <= t1 {
+            continue;
+        }
+        let slope = (msd2.ln() - msd1.ln()) / (t2.ln() - t1.ln());
+        if plateau.is_none_or(|(min_slope, _)| slope < min_slope) {
+            plateau = Some((slope, (msd1 * msd2).sqrt()));
+        }
+    }
+
+    match plateau {
+        Some((slope, msd)) if slope < PLATEAU_SLOPE => Some(msd.sqrt()),
+        _ => None,
+    }
+}
+
+/// The Lindemann parameter, the root mean square displacement of the particles relative to the lattice spacing, using
+/// the latest MSD recorded by the monitor. Solids are commonly taken to melt once this exceeds about 0.1. Returns NaN
+/// if the monitor has not recorded anything yet.
+pub fn lindemann_parameter(msd_monitor: &MsdMonitor, lattice_spacing: f64) -> f64 {
+    msd_monitor.msd.last().map_or(f64::NAN, |msd| msd.sqrt() / lattice_spacing)
+}
+
+/// Compute the cage-relative MSD from the unwrapped displacements recorded by an MSD monitor, as (lag time, msd)
+/// pairs. Each particle's displacement is taken relative to the mean displacement of its cage, its neighbors in the
+/// reference configuration, which should be the configuration when the monitor first ran. Neighbors are particles
+/// closer than `neighbor_factor` times the sum of their radii. Collective motion, like the drift of the whole system
+/// or long wavelength fluctuations, moves a particle along with its cage and drops out, leaving the local motion.
+/// Particles without neighbors are left out, and if no particle has neighbors, nothing is returned.
+pub fn cage_relative_msd(msd_monitor: &MsdMonitor, reference: &SimData, neighbor_factor: f64) -> Vec<(f64, f64)> {
+    let neighbors = neighbors_within(reference, neighbor_factor);
+    let num_caged = neighbors.iter().filter(|n| !n.is_empty()).count();
+    let Some(&start) = msd_monitor.times.first() else {
+        return vec![];
+    };
+    if num_caged == 0 {
+        return vec![];
+    }
+
+    msd_monitor
+        .times
+        .iter()
+        .zip(msd_monitor.displacements.iter())
+        .map(|(t, displacements)| {
+            let total: f64 = neighbors
+                .iter()
+                .enumerate()
+                .filter(|(_, cage)| !cage.is_empty())
+                .map(|(i, cage)| {
+                    let cage_displacement = cage.iter().fold(Vector::zero(), |sum, &j| sum + displacements[j]);
+                    (displacements[i] - cage_displacement / cage.len() as f64).length_sqr()
+                })
+                .sum();
+            (t - start, total / num_caged as f64)
+        })
+        .collect()
+}
+
+/// Find the displacement of each particle between two frames, using the minimum image in the periodic bounds, so a
+/// particle that crossed the boundary has a short displacement. The frames must list the particles in the same order,
+/// and no particle may move more than half the box between them.
+pub fn displacement_field(frame_a: &[Position], frame_b: &[Position], bounds: &Bounds) -> Vec<Vector> {
+    frame_a.iter().zip(frame_b.iter()).map(|(&a, &b)| bounds.minimum_image(b - a)).collect()
+}
+
+/// The participation ratio of a displacement field, (sum |d|^2)^2 / (N sum |d|^4). It is 1 when every particle moves
+/// the same distance, and 1/N when only a single particle moves, so it measures the fraction of the particles taking
+/// part in a rearrangement. Returns NaN if nothing moves.
+pub fn participation_ratio(displacements: &[Vector]) -> f64 {
+    let second: f64 = displacements.iter().map(|d| d.length_sqr()).sum();
+    let fourth: f64 = displacements.iter().map(|d| d.length_sqr().powi(2)).sum();
+    second * second / (displacements.len() as f64 * fourth)
+}
+
+/// Compute the Falk-Langer non-affine deformation D2min of each particle between two frames. The neighbors of a
+/// particle are the particles within `neighbor_radius` of it in the first frame. The local affine transformation J
+/// that best maps the neighbors' relative positions in the first frame onto those in the second frame is found by
+/// least squares, and D2min is the remaining squared error, sum_j |r_ij(b) - J r_ij(a)|^2. It is zero for an affine
+/// deformation, and large where particles rearrange, e.g. in shear transformation zones.
+///
+/// The SimData supplies the bounds, with relative positions found by the minimum image. Particles whose neighbors do
+/// not span both dimensions have no well defined J, and get NaN.
+pub fn d2min(frame_a: &[Position], frame_b: &[Position], sim_data: &SimData, neighbor_radius: f64) -> Vec<f64> {
+    // Find the neighbors in the first frame. With zero radii, the verlet lists cutoff is the neighbor radius.
+    let mut reference = sim_data.clone();
+    reference.positions = frame_a.to_vec();
+    reference.radii = vec![0.0; frame_a.len()];
+    let mut neighbors = vec![vec![]; frame_a.len()];
+    if !frame_a.is_empty() {
+        for (id1, id2) in &create_verlet_lists(&reference, neighbor_radius) {
+            if reference.distance_sqr_between(id1, id2) < neighbor_radius * neighbor_radius {
+                neighbors[id1].push(id2);
+                neighbors[id2].push(id1);
+            }
+        }
+    }
+
+    let bounds = sim_data.bounds;
+    (0..frame_a.len())
+        .map(|i| {
+            let pairs: Vec<(Vector, Vector)> = neighbors[i]
+                .iter()
+                .map(|&j| {
+                    (
+                        bounds.minimum_image(frame_a[j] - frame_a[i]),
+                        bounds.minimum_image(frame_b[j] - frame_b[i]),
+                    )
+                })
+                .collect();
+
+            // X = sum r(b) r(a)^T and Y = sum r(a) r(a)^T, with J = X Y^-1.
+            let (mut x, mut y) = ([[0.0; 2]; 2], [[0.0; 2]; 2]);
+            for (a, b) in pairs.iter() {
+                let (a, b) = ([a.x, a.y], [b.x, b.y]);
+                for m in 0..2 {
+                    for n in 0..2 {
+                        x[m][n] += b[m] * a[n];
+                        y[m][n] += a[m] * a[n];
+                    }
+                }
+            }
+            let det = y[0][0] * y[1][1] - y[0][1] * y[1][0];
+            if det.abs() <= 1.0e-12 * (y[0][0] * y[1][1]).abs() {
+                return f64::NAN;
+            }
+            let y_inv = [[y[1][1] / det, -y[0][1] / det], [-y[1][0] / det, y[0][0] / det]];
+            let j = [
+                [x[0][0] * y_inv[0][0] + x[0][1] * y_inv[1][0], x[0][0] * y_inv[0][1] + x[0][1] * y_inv[1][1]],
+                [x[1][0] * y_inv[0][0] + x[1][1] * y_inv[1][0], x[1][0] * y_inv[0][1] + x[1][1] * y_inv[1][1]],
+            ];
+
+            pairs
+                .iter()
+                .map(|(a, b)| {
+                    let affine = Vector::new(j[0][0] * a.x + j[0][1] * a.y, j[1][0] * a.x + j[1][1] * a.y);
+                    (*b - affine).length_sqr()
+                })
+                .sum()
+        })
+        .collect()
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::utils::init::square_lattice;
+
+    /// Logarithmically spaced times from 10^-3 to 10^3.
+    fn log_times() -> Vec<f64> {
+        (0..=60).map(|i| 10f64.powf(-3.0 + 0.1 * i as f64)).collect()
+    }
+
+    #[test]
+    fn test_localization_length_of_caged_particles() {
+        // Particles rattle in cages of size 0.1, and only escape very slowly.
+        let (cage, tau, d) = (0.1, 0.1, 1.0e-7);
+        let series: Vec<(f64, f64)> = log_times()
+            .into_iter()
+            .map(|t| (t, cage * cage * (1.0 - f64::exp(-t / tau)) + 4.0 * d * t))
+            .collect();
+        assert_close!(localization_length(&series).unwrap(), cage, 0.005);
+    }
+
+    #[test]
+    fn test_cage_relative_msd() {
+        // A square lattice, where each particle's cage is its four nearest neighbors.
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let reference = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.5));
+        let mut monitor = MsdMonitor::new(0.1);
+        monitor.times = vec![0.0, 1.0, 2.0];
+
+        // The whole system drifts, so the particles never leave their cages.
+        monitor.displacements = (0..3).map(|i| vec![Vector::new(0.4 * i as f64, -0.1 * i as f64); 36]).collect();
+        for &(_, msd) in cage_relative_msd(&monitor, &reference, 1.2).iter() {
+            assert_close!(msd, 0.0, 1.0e-12);
+        }
+
+        // One particle hops in its cage, which shifts the cages of its four neighbors by a quarter as much.
+        let mut hopped = vec![Vector::zero(); 36];
+        hopped[0] = Vector::new(0.3, 0.0);
+        monitor.displacements = vec![vec![Vector::zero(); 36], vec![Vector::zero(); 36], hopped];
+        let msd = cage_relative_msd(&monitor, &reference, 1.2);
+        assert_eq!(msd.len(), 3);
+        assert_eq!(msd[1], (1.0, 0.0));
+        assert_close!(msd[2].1, (0.09 + 4.0 * 0.075 * 0.075) / 36.0, 1.0e-12);
+
+        // Without any neighbors, there are no cages.
+        assert!(cage_relative_msd(&monitor, &reference, 0.5).is_empty());
+    }
+
+    #[test]
+    fn test_lindemann_parameter() {
+        let mut monitor = MsdMonitor::new(0.1);
+        assert!(lindemann_parameter(&monitor, 1.0).is_nan());
+
+        monitor.times = vec![0.0, 1.0, 2.0];
+        monitor.msd = vec![0.0, 0.0016, 0.0025];
+        assert_close!(lindemann_parameter(&monitor, 0.5), 0.1, 1e-12);
+    }
+
+    #[test]
+    fn test_no_localization_length_for_liquid() {
+        // Ballistic, then diffusive, with no plateau in between.
+        let (d, tau) = (0.5, 0.1);
+        let series: Vec<(f64, f64)> = log_times()
+            .into_iter()
+            .map(|t| (t, 4.0 * d * (t - tau * (1.0 - f64::exp(-t / tau)))))
+            .collect();
+        assert!(localization_length(&series).is_none());
+        assert!(localization_length(&[]).is_none());
+    }
+
+    #[test]
+    fn test_displacement_field() {
+        let bounds = Bounds::from((0.0, 10.0, 0.0, 10.0));
+        let frame_a = vec![Position::new(2.0, 3.0), Position::new(5.0, 5.0), Position::new(9.8, 0.1)];
+        // Every particle moves by (0.5, -0.3), and the last particle wraps across both boundaries.
+        let shift = Vector::new(0.5, -0.3);
+        let frame_b = vec![Position::new(2.5, 2.7), Position::new(5.5, 4.7), Position::new(0.3, 9.8)];
+
+        let field = displacement_field(&frame_a, &frame_b, &bounds);
+        assert_eq!(field.len(), 3);
+        for d in field {
+            assert_close!(d.x, shift.x, 1.0e-12);
+            assert_close!(d.y, shift.y, 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_d2min() {
+        // A square lattice in the middle of a large box, so the deformation does not reach the boundaries.
+        let bounds = Bounds::from((0.0, 100.0, 0.0, 100.0));
+        let lattice = square_lattice(6, 6, Bounds::from((47.0, 53.0, 47.0, 53.0)), 0.4);
+        let sim_data = SimData::new_with_particles(bounds, &lattice);
+        let frame_a = sim_data.positions.clone();
+
+        // A shear combined with an expansion is affine.
+        let frame_b: Vec<Position> = frame_a
+            .iter()
+            .map(|p| Position::new(p.x + 0.05 * (p.y - 50.0), p.y + 0.02 * (p.y - 50.0)))
+            .collect();
+        for d in d2min(&frame_a, &frame_b, &sim_data, 1.5) {
+            assert_close!(d, 0.0, 1.0e-20);
+        }
+
+        // One particle rearranging is non-affine in its neighborhood, but not far away from it.
+        let mut rearranged = frame_b.clone();
+        rearranged[14].x += 0.3;
+        let d = d2min(&frame_a, &rearranged, &sim_data, 1.5);
+        assert!(0.01 < d[14]);
+        assert!(0.001 < d[15]);
+        assert_close!(d[0], 0.0, 1.0e-20);
+        assert_close!(d[35], 0.0, 1.0e-20);
+    }
+
+    #[test]
+    fn test_participation_ratio() {
+        // Every particle moves the same distance, in different directions.
+        let uniform = vec![
+            Vector::new(0.1, 0.0), Vector::new(0.0, -0.1), Vector::new(0.06, 0.08), Vector::new(-0.1, 0.0),
+        ];
+        assert_close!(participation_ratio(&uniform), 1.0, 1.0e-12);
+
+        // Only one particle moves.
+        let mut localized = vec![Vector::zero(); 10];
+        localized[3] = Vector::new(0.5, 0.2);
+        assert_close!(participation_ratio(&localized), 0.1, 1.0e-12);
+
+        assert!(participation_ratio(&[Vector::zero(); 3]).is_nan());
+    }
+}
@@ -0,0 +1,263 @@
+use crate::core::simdata::SimData;
+use crate::core::vector::{Position, Vector};
+use crate::core::verlet_lists::create_verlet_lists;
+
+/// Bin the particles by their distance from a center, up to `max_r`, and find the mean radial component of their
+/// velocities in each shell. Each entry is the center of a shell and the mean radial velocity, which is positive for
+/// particles moving away from the center. Distances use the minimum image. Shells without any particles are left
+/// out.
+///
+/// Panics if the bin width is not positive.
+pub fn radial_velocity_profile(sim_data: &SimData, center: Position, bin_width: f64, max_r: f64) -> Vec<(f64, f64)> {
+    if bin_width.is_nan() || bin_width <= 0.0 {
+        panic!("bin width must be positive, found {}", bin_width);
+    }
+    let num_bins = f64::ceil(max_r / bin_width) as usize;
+    let mut sums = vec![0.0; num_bins];
+    let mut counts = vec![0usize; num_bins];
+    for i in 0..sim_data.num_particles() {
+        let displacement = sim_data.minimum_image(sim_data.positions[i] - center);
+        let r = displacement.length();
+        if max_r <= r || r == 0.0 {
+            continue;
+        }
+        let v = sim_data.velocities[i];
+        let bin = usize::min((r / bin_width) as usize, num_bins - 1);
+        sums[bin] += (v.x * displacement.x + v.y * displacement.y) / r;
+        counts[bin] += 1;
+    }
+
+    (0..num_bins)
+        .filter(|&bin| 0 < counts[bin])
+        .map(|bin| ((bin as f64 + 0.5) * bin_width, sums[bin] / counts[bin] as f64))
+        .collect()
+}
+
+/// Find the two point velocity correlation, <v_i . v_j> for pairs of particles binned by their separation, up to
+/// `max_r`, normalized by the mean square speed. Each entry is the center of a bin and the correlation. Coherent
+/// flow shows up as positive correlation at short range. Bins without any pairs are left out. If there are no
+/// particles, or they are all at rest, the correlation is undefined, and nothing is returned.
+///
+/// Panics if the bin width is not positive.
+pub fn velocity_correlation(sim_data: &SimData, bin_width: f64, max_r: f64) -> Vec<(f64, f64)> {
+    if bin_width.is_nan() || bin_width <= 0.0 {
+        panic!("bin width must be positive, found {}", bin_width);
+    }
+    if sim_data.is_empty() {
+        return vec![];
+    }
+    let mean_square_speed = sim_data.velocities.iter().map(|v| v.length_sqr()).sum::<f64>()
+        / sim_data.num_particles() as f64;
+    if mean_square_speed == 0.0 {
+        return vec![];
+    }
+
+    // The verlet lists contain all pairs closer than the sum of their radii plus max_r, a superset of the pairs
+    // closer than max_r.
+    let num_bins = f64::ceil(max_r / bin_width) as usize;
+    let mut sums = vec![0.0; num_bins];
+    let mut counts = vec![0usize; num_bins];
+    for (id1, id2) in &create_verlet_lists(sim_data, max_r) {
+        let r = sim_data.distance_sqr_between(id1, id2).sqrt();
+        if max_r <= r {
+            continue;
+        }
+        let (v1, v2) = (sim_data.velocities[id1], sim_data.velocities[id2]);
+        let bin = usize::min((r / bin_width) as usize, num_bins - 1);
+        sums[bin] += v1.x * v2.x + v1.y * v2.y;
+        counts[bin] += 1;
+    }
+
+    (0..num_bins)
+        .filter(|&bin| 0 < counts[bin])
+        .map(|bin| ((bin as f64 + 0.5) * bin_width, sums[bin] / counts[bin] as f64 / mean_square_speed))
+        .collect()
+}
+
+/// Coarse grain the velocity field onto an nx by ny grid covering the box, and compute its (vorticity, divergence),
+/// each indexed as [ix][iy]. The velocity of a grid cell is the mean velocity of the particles in it, or zero if it is
+/// empty. Derivatives are central differences between neighboring cells, and one sided differences at the edges of
+/// the grid, so a flow that is not periodic, like a rotation, is differentiated correctly everywhere.
+///
+/// Panics if the grid has no cells.
+pub fn flow_fields(sim_data: &SimData, nx: usize, ny: usize) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    if nx == 0 || ny == 0 {
+        panic!("the grid must have at least one cell in each direction, found {} x {}", nx, ny);
+    }
+    let b = sim_data.bounds;
+    let (dx, dy) = (sim_data.width() / nx as f64, sim_data.height() / ny as f64);
+
+    let mut velocity = vec![vec![Vector::zero(); ny]; nx];
+    let mut counts = vec![vec![0usize; ny]; nx];
+    for (p, v) in sim_data.positions.iter().zip(sim_data.velocities.iter()) {
+        let ix = usize::min(((p.x - b.xlo) / dx).floor().max(0.0) as usize, nx - 1);
+        let iy = usize::min(((p.y - b.ylo) / dy).floor().max(0.0) as usize, ny - 1);
+        velocity[ix][iy] += *v;
+        counts[ix][iy] += 1;
+    }
+    for ix in 0..nx {
+        for iy in 0..ny {
+            if 0 < counts[ix][iy] {
+                velocity[ix][iy] = velocity[ix][iy] / counts[ix][iy] as f64;
+            }
+        }
+    }
+
+    // The neighboring cells to difference between, and the distance between their centers. With a single cell in a
+    // direction, there is nothing to difference, and the derivative is zero.
+    let stencil = |i: usize, n: usize, spacing: f64| -> (usize, usize, f64) {
+        let (lo, hi) = (i.saturating_sub(1), usize::min(i + 1, n - 1));
+        (lo, hi, f64::max((hi - lo) as f64 * spacing, f64::MIN_POSITIVE))
+    };
+
+    let mut vorticity = vec![vec![0.0; ny]; nx];
+    let mut divergence = vec![vec![0.0; ny]; nx];
+    for ix in 0..nx {
+        for iy in 0..ny {
+            let (xlo, xhi, x_distance) = stencil(ix, nx, dx);
+            let (ylo, yhi, y_distance) = stencil(iy, ny, dy);
+            let d_dx = (velocity[xhi][iy] - velocity[xlo][iy]) / x_distance;
+            let d_dy = (velocity[ix][yhi] - velocity[ix][ylo]) / y_distance;
+            vorticity[ix][iy] = d_dx.y - d_dy.x;
+            divergence[ix][iy] = d_dx.x + d_dy.y;
+        }
+    }
+    (vorticity, divergence)
+}
+
+/// The enstrophy of the coarse grained velocity field, the integral of the squared vorticity over the box, using the
+/// vorticity from `flow_fields` on an nx by ny grid. It measures how much rotational motion the flow has, so its decay
+/// tracks the breakup of vortices.
+///
+/// Panics if the grid has no cells.
+pub fn enstrophy(sim_data: &SimData, nx: usize, ny: usize) -> f64 {
+    let cell_area = sim_data.width() * sim_data.height() / (nx * ny) as f64;
+    let (vorticity, _) = flow_fields(sim_data, nx, ny);
+    vorticity.iter().flatten().map(|w| w * w * cell_area).sum()
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::vector::Vector;
+    use crate::utils::init::square_lattice;
+
+    #[test]
+    fn test_radial_velocity_profile() {
+        let center = Position::new(5.0, 5.0);
+        let particles: Vec<Particle> = (0..12)
+            .map(|i| {
+                let angle = i as f64 * 0.5;
+                let r = 0.5 + 0.3 * i as f64;
+                let direction = Vector::new(angle.cos(), angle.sin());
+                Particle::new()
+                    .with_position(center + direction * r)
+                    .with_velocity(direction * 1.5)
+                    .with_radius(0.1)
+                    .to_owned()
+            })
+            .collect();
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+
+        let profile = radial_velocity_profile(&sim_data, center, 1.0, 5.0);
+        assert_eq!(profile.len(), 4);
+        assert_close!(profile[0].0, 0.5, 1.0e-12);
+        for (_, v) in profile {
+            assert_close!(v, 1.5, 1.0e-9);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "bin width must be positive")]
+    fn test_radial_velocity_profile_rejects_empty_bins() {
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let sim_data = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.5));
+        radial_velocity_profile(&sim_data, Position::new(3.0, 3.0), 0.0, 2.0);
+    }
+
+    #[test]
+    fn test_velocity_correlation_of_uniform_flow() {
+        let bounds = Bounds::from((0.0, 10.0, 0.0, 10.0));
+        let mut particles = square_lattice(10, 10, bounds, 0.3);
+        for p in particles.iter_mut() {
+            p.with_velocity_components(1.0, 0.5);
+        }
+        let sim_data = SimData::new_with_particles(bounds, &particles);
+
+        let correlation = velocity_correlation(&sim_data, 0.5, 4.0);
+        // Lattice spacings of 1, sqrt(2), 2, ... fall in several of the bins.
+        assert!(4 <= correlation.len());
+        for (_, c) in correlation {
+            assert_close!(c, 1.0, 1.0e-12);
+        }
+
+        // Particles at rest have no correlation.
+        let at_rest = SimData::new_with_particles(bounds, &square_lattice(10, 10, bounds, 0.3));
+        assert!(velocity_correlation(&at_rest, 0.5, 4.0).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "bin width must be positive")]
+    fn test_velocity_correlation_rejects_empty_bins() {
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let sim_data = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.5));
+        velocity_correlation(&sim_data, 0.0, 2.0);
+    }
+
+    #[test]
+    fn test_flow_fields_of_rigid_rotation() {
+        // One particle at the center of every cell of a 10 by 8 grid, rotating rigidly with angular velocity 0.7.
+        let bounds = Bounds::from((0.0, 10.0, 0.0, 8.0));
+        let mut sim_data = SimData::new_with_particles(bounds, &square_lattice(10, 8, bounds, 0.1));
+        sim_data.set_affine_velocity([[0.0, -0.7], [0.7, 0.0]]);
+
+        let (vorticity, divergence) = flow_fields(&sim_data, 10, 8);
+        assert_eq!((vorticity.len(), vorticity[0].len()), (10, 8));
+        for ix in 0..10 {
+            for iy in 0..8 {
+                assert_close!(vorticity[ix][iy], 1.4, 1.0e-9);
+                assert_close!(divergence[ix][iy], 0.0, 1.0e-9);
+            }
+        }
+
+        // A uniform expansion has no vorticity, and a divergence of twice the expansion rate.
+        sim_data.set_affine_velocity([[0.3, 0.0], [0.0, 0.3]]);
+        let (vorticity, divergence) = flow_fields(&sim_data, 10, 8);
+        assert_close!(vorticity[4][5], 0.0, 1.0e-9);
+        assert_close!(divergence[0][7], 0.6, 1.0e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "the grid must have at least one cell in each direction")]
+    fn test_flow_fields_reject_empty_grid() {
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let sim_data = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.5));
+        flow_fields(&sim_data, 4, 0);
+    }
+
+    #[test]
+    fn test_enstrophy_of_rigid_rotation() {
+        // A rigid rotation with angular velocity omega has a uniform vorticity of 2 omega.
+        let bounds = Bounds::from((0.0, 10.0, 0.0, 8.0));
+        let mut sim_data = SimData::new_with_particles(bounds, &square_lattice(10, 8, bounds, 0.1));
+        sim_data.set_affine_velocity([[0.0, -0.7], [0.7, 0.0]]);
+        let slow = enstrophy(&sim_data, 10, 8);
+        assert_close!(slow, 1.4 * 1.4 * 80.0, 1.0e-9);
+
+        // Doubling the rotation rate quadruples the enstrophy.
+        sim_data.set_affine_velocity([[0.0, -1.4], [1.4, 0.0]]);
+        assert_close!(enstrophy(&sim_data, 10, 8), 4.0 * slow, 1.0e-9);
+
+        // A uniform expansion has none.
+        sim_data.set_affine_velocity([[0.3, 0.0], [0.0, 0.3]]);
+        assert_close!(enstrophy(&sim_data, 10, 8), 0.0, 1.0e-9);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::core::force::Force;
+use crate::core::particle::Particle;
+use crate::core::simdata::{Bounds, SimData};
+use crate::core::vector::Position;
+use crate::core::verlet_lists::{create_verlet_lists, VerletLists};
+use std::f64::consts::PI;
+
+/// Place two particles with unit radius and mass at `steps` evenly spaced separations from r_min to r_max, and record
+/// the pair potential at each separation as (r, potential). Useful for sanity checking the shape of a force.
+pub fn potential_profile(force: &dyn Force, r_min: f64, r_max: f64, steps: usize) -> Vec<(f64, f64)> {
+    // Make the box large enough that periodic images of the particles never interact.
+    let half_width = f64::max(2.0 * r_max, 5.0);
+    let bounds = Bounds::from((-half_width, half_width, -half_width, half_width));
+    let mut sim_data = SimData::new_with_particles(bounds, &[Particle::new(), Particle::new()]);
+
+    let mut profile = Vec::with_capacity(steps);
+    for i in 0..steps {
+        let r = if steps == 1 { r_min } else { r_min + (r_max - r_min) * i as f64 / (steps - 1) as f64 };
+        sim_data.positions[0].x = -0.5 * r;
+        sim_data.positions[1].x = 0.5 * r;
+        profile.push((r, force.pair_potential(&sim_data, 0, 1)));
+    }
+    profile
+}
+
+/// Compute a scalar stress for each particle, the trace of its local virial, (1/2) sum_j r_ij . F_ij, summed over the
+/// pairs in the verlet lists that it is part of. Each pair's virial is split evenly between its two particles. The
+/// stress is positive for repulsive contacts, and is not divided by any area. Coloring particles by their stress shows
+/// the force chains in a granular packing.
+pub fn per_particle_stress(sim_data: &SimData, force: &dyn Force, verlet_lists: &VerletLists) -> Vec<f64> {
+    let mut stress = vec![0.0; sim_data.num_particles()];
+    for (id1, id2) in verlet_lists {
+        let r = sim_data.displacement_between(id1, id2);
+        let f = force.pair_force(sim_data, id1, id2);
+        let virial = r.x * f.x + r.y * f.y;
+        stress[id1] += 0.5 * virial;
+        stress[id2] += 0.5 * virial;
+    }
+    stress
+}
+
+/// Compute the pressure tensor of the configuration, P_ab = (sum_i m_i v_ia v_ib + sum_{i<j} r_ij,a F_ij,b) / A, from
+/// the particles' momenta and the pair forces, where r_ij = r_i - r_j and F_ij is the force on i due to j. Half its
+/// trace is the virial pressure. The off-diagonal element is the shear stress whose autocorrelation gives the shear
+/// viscosity. Pairs are found assuming the force acts only between touching particles, or within its extra reach, see
+/// `Force::extra_reach`.
+pub fn stress_tensor(sim_data: &SimData, force: &dyn Force) -> [[f64; 2]; 2] {
+    let mut stress = [[0.0; 2]; 2];
+    for (m, v) in sim_data.masses.iter().zip(sim_data.velocities.iter()) {
+        let v = [v.x, v.y];
+        for (a, row) in stress.iter_mut().enumerate() {
+            for (b, entry) in row.iter_mut().enumerate() {
+                *entry += m * v[a] * v[b];
+            }
+        }
+    }
+    for (id1, id2) in &create_verlet_lists(sim_data, force.extra_reach(sim_data)) {
+        let r = sim_data.displacement_between(id1, id2);
+        let f = force.pair_force(sim_data, id1, id2);
+        let (r, f) = ([r.x, r.y], [f.x, f.y]);
+        for (a, row) in stress.iter_mut().enumerate() {
+            for (b, entry) in row.iter_mut().enumerate() {
+                *entry += r[a] * f[b];
+            }
+        }
+    }
+    let area = sim_data.width() * sim_data.height();
+    stress.iter_mut().flatten().for_each(|entry| *entry /= area);
+    stress
+}
+
+/// Compute the local pressure in radial shells of width `bin_width` about a center, e.g. to find the pressure jump
+/// across the surface of a droplet. Each particle contributes its kinetic energy and half of its share of the pair
+/// virial, as in `per_particle_stress`, to the shell it is in, and the sum is divided by the area of the shell. Summed
+/// over the whole box, this is the virial pressure. Each entry is the center of a shell and its pressure. Only shells
+/// that fit inside the box are included, and pairs are found assuming the force acts only between touching particles,
+/// or within its extra reach, see `Force::extra_reach`.
+///
+/// Panics if the bin width is not positive.
+pub fn radial_pressure_profile(
+    sim_data: &SimData,
+    force: &dyn Force,
+    center: Position,
+    bin_width: f64,
+) -> Vec<(f64, f64)> {
+    if bin_width.is_nan() || bin_width <= 0.0 {
+        panic!("bin width must be positive, found {}", bin_width);
+    }
+    let max_r = 0.5 * f64::min(sim_data.width(), sim_data.height());
+    let num_bins = f64::floor(max_r / bin_width) as usize;
+    let stress = per_particle_stress(sim_data, force, &create_verlet_lists(sim_data, force.extra_reach(sim_data)));
+
+    let mut sums = vec![0.0; num_bins];
+    for (i, particle_stress) in stress.into_iter().enumerate() {
+        let bin = (sim_data.minimum_image(sim_data.positions[i] - center).length() / bin_width) as usize;
+        if bin < num_bins {
+            sums[bin] += 0.5 * sim_data.masses[i] * sim_data.velocities[i].length_sqr() + 0.5 * particle_stress;
+        }
+    }
+
+    sums.into_iter()
+        .enumerate()
+        .map(|(bin, sum)| {
+            let (r_in, r_out) = (bin as f64 * bin_width, (bin + 1) as f64 * bin_width);
+            ((bin as f64 + 0.5) * bin_width, sum / (PI * (r_out * r_out - r_in * r_in)))
+        })
+        .collect()
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::force::HardSphereForce;
+    use crate::core::universe::Universe;
+    use crate::core::vector::Velocity;
+    use crate::utils::init::square_lattice;
+    use crate::core::verlet_lists::create_verlet_lists;
+
+    #[test]
+    fn test_hard_sphere_potential_profile() {
+        let force = HardSphereForce::new(10.0);
+        let profile = potential_profile(&force, 1.0, 3.0, 21);
+        assert_eq!(profile.len(), 21);
+        assert_close!(profile[0].0, 1.0, 1.0e-12);
+        assert_close!(profile[20].0, 3.0, 1.0e-12);
+
+        // Contact is at r = 2, beyond which the potential is zero.
+        for &(r, potential) in profile.iter() {
+            if 2.0 <= r {
+                assert_eq!(potential, 0.0);
+            } else {
+                assert_close!(potential, 0.5 * 10.0 * (2.0 - r) * (2.0 - r), 1.0e-9);
+            }
+        }
+
+        // The potential rises as the overlap increases.
+        for window in profile.windows(2) {
+            if window[0].0 < 2.0 {
+                assert!(window[1].1 < window[0].1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_per_particle_stress() {
+        // A compressed chain, where every contact has an overlap of 0.1. The ends have one contact, and the interior
+        // particles have two.
+        let particles: Vec<Particle> = (0..5)
+            .map(|i| Particle::new().with_coords(3.0 + 0.9 * i as f64, 5.0).with_radius(0.5).to_owned())
+            .collect();
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let force = HardSphereForce::new(10.0);
+        let stress = per_particle_stress(&sim_data, &force, &create_verlet_lists(&sim_data, 0.1));
+
+        // Each contact has a virial of 0.9 * 10 * 0.1, split between its two particles.
+        let contact = 0.5 * 0.9 * 10.0 * 0.1;
+        assert_close!(stress[0], contact, 1.0e-9);
+        assert_close!(stress[4], contact, 1.0e-9);
+        for i in 1..4 {
+            assert_close!(stress[i], 2.0 * contact, 1.0e-9);
+            assert!(stress[0] < stress[i]);
+        }
+    }
+
+    #[test]
+    fn test_radial_pressure_profile_of_uniform_system() {
+        // A uniformly compressed lattice, with small random velocities.
+        let bounds = Bounds::from((0.0, 27.0, 0.0, 27.0));
+        let mut universe = Universe::new_seeded(bounds, 3);
+        let mut particles = square_lattice(30, 30, bounds, 0.5);
+        for p in particles.iter_mut() {
+            p.with_velocity_components(universe.rng().gaussian(0.0, 0.1), universe.rng().gaussian(0.0, 0.1));
+        }
+        universe.sim_data.add_particles(&particles);
+        universe.with_forces(Box::new(HardSphereForce::new(10.0)));
+
+        let force = HardSphereForce::new(10.0);
+        let profile = radial_pressure_profile(&universe.sim_data, &force, Position::new(13.5, 13.5), 3.0);
+        assert_eq!(profile.len(), 4);
+        let pressure = universe.pressure();
+        // Every shell has roughly the same pressure as the whole box.
+        for &(_, p) in profile.iter() {
+            assert_close!(p, pressure, 0.1 * pressure);
+        }
+    }
+
+    #[test]
+    fn test_radial_pressure_profile_of_anisotropic_contact() {
+        // The particles are 1.2 apart along x, further than the sum of their radii, but the elliptical contact distance
+        // along x is 1.5. Both are in the innermost shell, which holds the whole virial, 1.2 * 30 / 2.
+        let particles = vec![
+            Particle::new().with_coords(4.4, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.6, 5.0).with_radius(0.5).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let force = HardSphereForce::new(100.0).with_anisotropy(1.5, 0.0).to_owned();
+        let profile = radial_pressure_profile(&sim_data, &force, Position::new(5.0, 5.0), 1.0);
+        assert_close!(profile[0].1, 0.5 * 1.2 * 30.0 / PI, 1.0e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "bin width must be positive")]
+    fn test_radial_pressure_profile_rejects_empty_bins() {
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let sim_data = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.5));
+        radial_pressure_profile(&sim_data, &HardSphereForce::new(10.0), Position::new(3.0, 3.0), 0.0);
+    }
+
+    #[test]
+    fn test_stress_tensor() {
+        // A compressed chain along x, whose contacts only push along x.
+        let particles: Vec<Particle> = (0..5)
+            .map(|i| Particle::new().with_coords(3.0 + 0.9 * i as f64, 5.0).with_radius(0.5).to_owned())
+            .collect();
+        let mut universe = Universe::new(Bounds::from((0.0, 10.0, 0.0, 10.0)));
+        universe.sim_data.add_particles(&particles);
+        let force = HardSphereForce::new(10.0);
+        let stress = stress_tensor(&universe.sim_data, &force);
+        assert_close!(stress[0][0], 4.0 * 0.9 * 10.0 * 0.1 / 100.0, 1.0e-9);
+        assert_close!(stress[1][1], 0.0, 1.0e-12);
+        assert_close!(stress[0][1], 0.0, 1.0e-12);
+
+        // Moving particles carry momentum. A particle moving diagonally contributes to the shear stress.
+        universe.sim_data.velocities[0] = Velocity::new(1.0, 2.0);
+        let stress = stress_tensor(&universe.sim_data, &force);
+        assert_close!(stress[0][1], 2.0 / 100.0, 1.0e-12);
+        assert_close!(stress[1][0], 2.0 / 100.0, 1.0e-12);
+
+        // Half the trace is the virial pressure.
+        universe.with_forces(Box::new(HardSphereForce::new(10.0)));
+        assert_close!(0.5 * (stress[0][0] + stress[1][1]), universe.pressure(), 1.0e-12);
+    }
+
+    #[test]
+    fn test_stress_tensor_of_anisotropic_contact() {
+        // The particles are 1.2 apart along x, further than the sum of their radii, but the elliptical contact distance
+        // along x is 1.5.
+        let particles = vec![
+            Particle::new().with_coords(4.4, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.6, 5.0).with_radius(0.5).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let force = HardSphereForce::new(100.0).with_anisotropy(1.5, 0.0).to_owned();
+        let stress = stress_tensor(&sim_data, &force);
+        assert_close!(stress[0][0], 1.2 * 100.0 * 0.3 / 100.0, 1.0e-9);
+        assert_close!(stress[1][1], 0.0, 1.0e-12);
+    }
+}
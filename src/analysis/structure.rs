@@ -0,0 +1,522 @@
+use crate::analysis::linear_fit;
+use crate::core::force::Force;
+use crate::core::linked_cells::LinkedCells;
+use crate::core::simdata::SimData;
+use crate::core::vector::Position;
+use crate::core::verlet_lists::{create_verlet_lists, VerletLists};
+
+/// Find the correlation length of the structure from a radial distribution function, given as (r, g(r)) pairs.
+///
+/// The peaks of |g(r) - 1| trace out the envelope of the structural oscillations, which decays as exp(-r / xi). The
+/// correlation length xi is found from a least squares fit of ln|g(r) - 1| at the peaks. Returns NaN if there are
+/// fewer than two peaks to fit.
+pub fn correlation_length(rdf: &[(f64, f64)]) -> f64 {
+    let deviation: Vec<f64> = rdf.iter().map(|(_, g)| (g - 1.0).abs()).collect();
+
+    // A peak must rise strictly above the previous point, so flat regions, like the excluded core where g(r) = 0,
+    // do not count.
+    let peaks: Vec<(f64, f64)> = (1..rdf.len().saturating_sub(1))
+        .filter(|&i| deviation[i - 1] < deviation[i] && deviation[i + 1] <= deviation[i])
+        .map(|i| (rdf[i].0, deviation[i].ln()))
+        .collect();
+    if peaks.len() < 2 {
+        return f64::NAN;
+    }
+
+    let (slope, _) = linear_fit(&peaks);
+    -1.0 / slope
+}
+
+/// Compute the partial radial distribution function g_AB(r) between particles of `type_a` and particles of
+/// `type_b`, as (bin center, g) pairs for bins out to `max_r`. Only pairs with one particle of each type count, and
+/// the normalization is by the density of such pairs, so g_AB(r) tends to one for an uncorrelated mixture. If the two
+/// types are the same, this is the RDF of that species alone.
+///
+/// Panics if the bin width is not positive.
+pub fn partial_rdf(sim_data: &SimData, type_a: usize, type_b: usize, bin_width: f64, max_r: f64) -> Vec<(f64, f64)> {
+    // The number of distinct A-B pairs in the system.
+    let n_a = sim_data.types.iter().filter(|&&t| t == type_a).count() as f64;
+    let n_b = sim_data.types.iter().filter(|&&t| t == type_b).count() as f64;
+    let num_pairs = if type_a == type_b { 0.5 * n_a * (n_a - 1.0) } else { n_a * n_b };
+
+    let is_ab_pair = |id1: usize, id2: usize| {
+        let (t1, t2) = (sim_data.types[id1], sim_data.types[id2]);
+        (t1 == type_a && t2 == type_b) || (t1 == type_b && t2 == type_a)
+    };
+    pair_distribution(sim_data, is_ab_pair, num_pairs, bin_width, max_r)
+}
+
+/// Compute the radial distribution function g(r) of all the particles, as (bin center, g) pairs for bins out to
+/// `max_r`.
+///
+/// Panics if the bin width is not positive.
+pub fn rdf(sim_data: &SimData, bin_width: f64, max_r: f64) -> Vec<(f64, f64)> {
+    let n = sim_data.num_particles() as f64;
+    pair_distribution(sim_data, |_, _| true, 0.5 * n * (n - 1.0), bin_width, max_r)
+}
+
+/// Histogram the distances between the pairs of particles that are included, and normalize each bin by the number of
+/// such pairs expected in it if the included pairs, `num_pairs` of them, were spread out uniformly.
+fn pair_distribution(
+    sim_data: &SimData,
+    include: impl Fn(usize, usize) -> bool,
+    num_pairs: f64,
+    bin_width: f64,
+    max_r: f64,
+) -> Vec<(f64, f64)> {
+    if bin_width.is_nan() || bin_width <= 0.0 {
+        panic!("bin width must be positive, found {}", bin_width);
+    }
+    let num_bins = (max_r / bin_width).ceil() as usize;
+    let mut counts = vec![0usize; num_bins];
+    if !sim_data.is_empty() {
+        for (id1, id2) in &create_verlet_lists(sim_data, max_r) {
+            if !include(id1, id2) {
+                continue;
+            }
+            let r = sim_data.distance_sqr_between(id1, id2).sqrt();
+            let bin = (r / bin_width) as usize;
+            if bin < num_bins {
+                counts[bin] += 1;
+            }
+        }
+    }
+    let area = sim_data.width() * sim_data.height();
+
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(bin, count)| {
+            let (r_in, r_out) = (bin as f64 * bin_width, (bin + 1) as f64 * bin_width);
+            let shell_area = std::f64::consts::PI * (r_out * r_out - r_in * r_in);
+            (r_in + 0.5 * bin_width, count as f64 / (num_pairs * shell_area / area))
+        })
+        .collect()
+}
+
+/// Estimate the two body excess entropy per particle, s_2 = -pi rho integral_0^cutoff (g ln g - g + 1) r dr, from the
+/// radial distribution function. It is zero for an ideal gas, and more negative the more structured the system is.
+pub fn pair_entropy(sim_data: &SimData, cutoff: f64) -> f64 {
+    const NUM_BINS: usize = 200;
+
+    let bin_width = cutoff / NUM_BINS as f64;
+    let density = sim_data.num_particles() as f64 / (sim_data.width() * sim_data.height());
+    let integral: f64 = rdf(sim_data, bin_width, cutoff)
+        .into_iter()
+        .map(|(r, g)| {
+            // g ln g goes to zero as g does.
+            let g_ln_g = if 0.0 < g { g * g.ln() } else { 0.0 };
+            (g_ln_g - g + 1.0) * r * bin_width
+        })
+        .sum();
+    -std::f64::consts::PI * density * integral
+}
+
+/// Find the fraction of particles that are rattlers, particles with fewer than three force-bearing contacts, which is
+/// too few for them to be mechanically constrained in 2D. A pair is in contact if the force between them is nonzero,
+/// which may reach beyond the sum of their radii, see `Force::extra_reach`.
+pub fn rattler_fraction(sim_data: &SimData, force: &dyn Force) -> f64 {
+    const MIN_CONSTRAINED_CONTACTS: usize = 3;
+
+    if sim_data.is_empty() {
+        return 0.0;
+    }
+    let mut contacts = vec![0usize; sim_data.num_particles()];
+    for (id1, id2) in &create_verlet_lists(sim_data, force.extra_reach(sim_data)) {
+        if 0.0 < force.pair_force(sim_data, id1, id2).length() {
+            contacts[id1] += 1;
+            contacts[id2] += 1;
+        }
+    }
+    let rattlers = contacts.iter().filter(|&&c| c < MIN_CONSTRAINED_CONTACTS).count();
+    rattlers as f64 / sim_data.num_particles() as f64
+}
+
+/// Histogram the overlaps, r_i + r_j - distance, of all pairs of particles in contact. Each entry is the center of a
+/// bin and the number of overlaps that fall in it. Bins run from zero up to the bin of the largest overlap. A well
+/// relaxed packing only has overlaps in the first few bins.
+///
+/// Panics if the bin width is not positive.
+pub fn overlap_histogram(sim_data: &SimData, bin_width: f64) -> Vec<(f64, usize)> {
+    if bin_width.is_nan() || bin_width <= 0.0 {
+        panic!("bin width must be positive, found {}", bin_width);
+    }
+    let mut counts: Vec<usize> = vec![];
+    for (id1, id2) in &create_verlet_lists(sim_data, 0.0) {
+        let overlap = sim_data.radii[id1] + sim_data.radii[id2] - sim_data.distance_sqr_between(id1, id2).sqrt();
+        if overlap <= 0.0 {
+            continue;
+        }
+        let bin = (overlap / bin_width) as usize;
+        if counts.len() <= bin {
+            counts.resize(bin + 1, 0);
+        }
+        counts[bin] += 1;
+    }
+    counts.into_iter().enumerate().map(|(bin, count)| ((bin as f64 + 0.5) * bin_width, count)).collect()
+}
+
+/// Compute the fabric tensor of the contact network, F_ab = (1/N_c) sum_c n_a n_b, where n is the unit normal of
+/// contact c. Pairs in the verlet lists are in contact if the particles overlap. The tensor has unit trace, and is
+/// 0.5 I for an isotropic network, so its anisotropy measures how much the contacts line up, e.g. along the direction
+/// of compression in a sheared granular packing. Returns zero if there are no contacts.
+pub fn fabric_tensor(sim_data: &SimData, verlet_lists: &VerletLists) -> [[f64; 2]; 2] {
+    let mut fabric = [[0.0; 2]; 2];
+    let mut num_contacts = 0;
+    for (id1, id2) in verlet_lists {
+        let r = sim_data.displacement_between(id1, id2);
+        let distance = r.length();
+        if distance == 0.0 || sim_data.radii[id1] + sim_data.radii[id2] <= distance {
+            continue;
+        }
+        let n = [r.x / distance, r.y / distance];
+        for (a, row) in fabric.iter_mut().enumerate() {
+            for (b, entry) in row.iter_mut().enumerate() {
+                *entry += n[a] * n[b];
+            }
+        }
+        num_contacts += 1;
+    }
+    if 0 < num_contacts {
+        fabric.iter_mut().flatten().for_each(|entry| *entry /= num_contacts as f64);
+    }
+    fabric
+}
+
+/// Estimate the local free volume on an nx by ny grid over the box, as the distance from the center of each grid cell
+/// to the surface of the nearest particle, which is zero inside a particle. Voids show up as peaks. The result is
+/// indexed as `[ix][iy]`, and is infinite everywhere if there are no particles.
+///
+/// The nearest particle is found by searching rings of linked cells outwards from the grid point, until no particle
+/// in the next ring could be any closer.
+pub fn free_volume_map(sim_data: &SimData, nx: usize, ny: usize) -> Vec<Vec<f64>> {
+    let b = sim_data.bounds;
+    let (dx, dy) = (sim_data.width() / nx as f64, sim_data.height() / ny as f64);
+    let mut free_volume = vec![vec![f64::INFINITY; ny]; nx];
+    if sim_data.is_empty() {
+        return free_volume;
+    }
+
+    // Aim for about one particle per cell, but make the cells at least as large as the particles.
+    let max_radius = sim_data.radii.iter().copied().fold(0.0, f64::max);
+    let spacing = f64::sqrt(sim_data.width() * sim_data.height() / sim_data.num_particles() as f64);
+    let mut linked_cells = LinkedCells::new_for_simdata(sim_data, f64::max(spacing, 2.0 * max_radius));
+    for (id, position) in sim_data.positions.iter().enumerate() {
+        linked_cells.add_particle(position, id);
+    }
+    let (num_x, num_y) = (linked_cells.get_num_x() as i64, linked_cells.get_num_y() as i64);
+    let cell_size = f64::min(linked_cells.get_cell_width(), linked_cells.get_cell_height());
+
+    for (ix, column) in free_volume.iter_mut().enumerate() {
+        for (iy, entry) in column.iter_mut().enumerate() {
+            let point = Position::new(b.xlo + (ix as f64 + 0.5) * dx, b.ylo + (iy as f64 + 0.5) * dy);
+            let (cx, cy) = linked_cells.get_cell_indices(point.x, point.y);
+            let (cx, cy) = (i64::min(cx as i64, num_x - 1), i64::min(cy as i64, num_y - 1));
+
+            // Particles in ring k + 1 are at least k cell sizes away from the point.
+            for k in 0..=i64::max(num_x, num_y) {
+                if *entry + max_radius <= (k - 1) as f64 * cell_size {
+                    break;
+                }
+                for ox in -k..=k {
+                    for oy in -k..=k {
+                        if i64::max(ox.abs(), oy.abs()) != k {
+                            continue;
+                        }
+                        let cell_x = (cx + ox).rem_euclid(num_x) as usize;
+                        let cell_y = (cy + oy).rem_euclid(num_y) as usize;
+                        for &id in &linked_cells.get_cell(cell_x, cell_y).unwrap().particle_ids {
+                            let distance = sim_data.minimum_image(sim_data.positions[id] - point).length();
+                            *entry = f64::min(*entry, f64::max(distance - sim_data.radii[id], 0.0));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    free_volume
+}
+
+/// Histogram the bond angles, the angles in degrees between the bonds from each particle to two of its neighbors,
+/// where neighbors are particles whose centers are closer than `neighbor_cutoff`. Each entry is the center of a bin
+/// and the probability density of the angle, normalized so that it integrates to one over the bins. A square lattice
+/// has peaks at 90 and 180 degrees, and a hexagonal lattice at 60, 120, and 180 degrees.
+///
+/// Panics if the bin width is not positive.
+pub fn bond_angle_distribution(sim_data: &SimData, neighbor_cutoff: f64, bin_width: f64) -> Vec<(f64, f64)> {
+    if bin_width.is_nan() || bin_width <= 0.0 {
+        panic!("bin width must be positive, found {}", bin_width);
+    }
+    let num_bins = f64::ceil(180.0 / bin_width) as usize;
+    let mut counts = vec![0usize; num_bins];
+
+    // Find the neighbors. With zero radii, the verlet lists cutoff is the neighbor cutoff.
+    let mut points = sim_data.clone();
+    points.radii = vec![0.0; sim_data.num_particles()];
+    let mut neighbors = vec![vec![]; sim_data.num_particles()];
+    if !sim_data.is_empty() {
+        for (id1, id2) in &create_verlet_lists(&points, neighbor_cutoff) {
+            if sim_data.distance_sqr_between(id1, id2) < neighbor_cutoff * neighbor_cutoff {
+                neighbors[id1].push(id2);
+                neighbors[id2].push(id1);
+            }
+        }
+    }
+
+    for (center, bonded) in neighbors.iter().enumerate() {
+        let bonds: Vec<_> = bonded.iter().map(|&other| sim_data.displacement_between(center, other)).collect();
+        for (k, a) in bonds.iter().enumerate() {
+            for b in bonds.iter().skip(k + 1) {
+                let cos = (a.x * b.x + a.y * b.y) / (a.length() * b.length());
+                let angle = cos.clamp(-1.0, 1.0).acos().to_degrees();
+                counts[usize::min((angle / bin_width) as usize, num_bins - 1)] += 1;
+            }
+        }
+    }
+
+    let total = counts.iter().sum::<usize>().max(1) as f64;
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(bin, count)| ((bin as f64 + 0.5) * bin_width, count as f64 / (total * bin_width)))
+        .collect()
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::force::HardSphereForce;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::vector::Vector;
+    use crate::utils::init::{hexagonal_lattice, square_lattice};
+    use crate::utils::rng::SimRng;
+
+    #[test]
+    fn test_correlation_length() {
+        // No particles closer than 1, then oscillations with an exponentially decaying envelope.
+        let xi = 1.5;
+        let rdf: Vec<(f64, f64)> = (0..1000)
+            .map(|i| {
+                let r = 0.01 * i as f64;
+                let g = if r < 1.0 {
+                    0.0
+                } else {
+                    1.0 + 2.0 * f64::exp(-r / xi) * f64::cos(2.0 * std::f64::consts::PI * (r - 1.0))
+                };
+                (r, g)
+            })
+            .collect();
+        assert_close!(correlation_length(&rdf), xi, 1.0e-3);
+
+        // An ideal gas has no structure to fit.
+        assert!(correlation_length(&[(1.0, 1.0), (2.0, 1.0), (3.0, 1.0)]).is_nan());
+    }
+
+    #[test]
+    fn test_overlap_histogram() {
+        let particles = vec![
+            // Overlap of 0.05.
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(1.95, 1.0).with_radius(0.5).to_owned(),
+            // Overlap of 0.25.
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.0, 5.75).with_radius(0.5).to_owned(),
+            // Close, but not touching.
+            Particle::new().with_coords(8.0, 8.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(9.02, 8.0).with_radius(0.5).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let histogram = overlap_histogram(&sim_data, 0.1);
+
+        assert_eq!(histogram.len(), 3);
+        assert_close!(histogram[0].0, 0.05, 1.0e-12);
+        assert_eq!(histogram.iter().map(|h| h.1).collect::<Vec<usize>>(), vec![1, 0, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "bin width must be positive")]
+    fn test_overlap_histogram_rejects_empty_bins() {
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let sim_data = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.55));
+        overlap_histogram(&sim_data, -0.1);
+    }
+
+    #[test]
+    fn test_partial_rdf() {
+        // A checkerboard of two species on a square lattice with spacing 1. Unlike particles are nearest neighbors, at
+        // a distance of 1, and like particles are next nearest neighbors, at sqrt(2).
+        let bounds = Bounds::from((0.0, 8.0, 0.0, 8.0));
+        let particles: Vec<Particle> = square_lattice(8, 8, bounds, 0.4)
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| p.to_owned().with_type((i % 8 + i / 8) % 2).to_owned())
+            .collect();
+        let sim_data = SimData::new_with_particles(bounds, &particles);
+
+        let bin_width = 0.08;
+        let bin_of = |r: f64| (r / bin_width) as usize;
+        let ab = partial_rdf(&sim_data, 0, 1, bin_width, 2.0);
+        let aa = partial_rdf(&sim_data, 0, 0, bin_width, 2.0);
+        assert_eq!(ab.len(), 25);
+
+        // The A-B partial RDF peaks at the A-B spacing, and has no contributions from A-A pairs.
+        let peak = ab.iter().enumerate().max_by(|a, b| a.1.1.total_cmp(&b.1.1)).unwrap().0;
+        assert_eq!(peak, bin_of(1.0));
+        assert_eq!(ab[bin_of(f64::sqrt(2.0))].1, 0.0);
+        assert!(ab[..bin_of(1.0)].iter().all(|&(_, g)| g == 0.0));
+
+        // The A-A partial RDF is the other way around.
+        assert_eq!(aa[bin_of(1.0)].1, 0.0);
+        assert!(0.0 < aa[bin_of(f64::sqrt(2.0))].1);
+
+        // Each A particle has 4 B neighbors at r = 1: 32 * 4 pairs, out of 32 * 32, with a density of 1 / 64.
+        let shell_area = std::f64::consts::PI * bin_width * bin_width * (2.0 * bin_of(1.0) as f64 + 1.0);
+        assert_close!(ab[bin_of(1.0)].1, 128.0 / (1024.0 * shell_area / 64.0), 1.0e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "bin width must be positive")]
+    fn test_partial_rdf_rejects_empty_bins() {
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let sim_data = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.5));
+        partial_rdf(&sim_data, 0, 0, 0.0, 2.0);
+    }
+
+    #[test]
+    fn test_rattler_fraction() {
+        // A compressed square lattice, where every particle has 4 contacts, except for one small particle sitting
+        // loose in a vacancy. Its former neighbors keep 3 contacts, so they are not rattlers.
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let mut particles = square_lattice(6, 6, bounds, 0.55);
+        particles[14].radius = 0.2;
+        let sim_data = SimData::new_with_particles(bounds, &particles);
+        let force = HardSphereForce::new(100.0);
+        assert_close!(rattler_fraction(&sim_data, &force), 1.0 / 36.0, 1.0e-12);
+
+        // Without the compression, nothing touches, so everything rattles.
+        let loose = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.45));
+        assert_eq!(rattler_fraction(&loose, &force), 1.0);
+
+        // Diagonal ellipses reach their lattice neighbors, so nothing rattles.
+        let ellipses = HardSphereForce::new(100.0).with_anisotropy(1.5, 0.25 * std::f64::consts::PI).to_owned();
+        assert_eq!(rattler_fraction(&loose, &ellipses), 0.0);
+    }
+
+    #[test]
+    fn test_pair_entropy() {
+        // Point-like particles placed uniformly at random, an ideal gas.
+        let bounds = Bounds::from((0.0, 40.0, 0.0, 40.0));
+        let mut rng = SimRng::new(21);
+        let particles: Vec<Particle> = (0..2000)
+            .map(|_| Particle::new()
+                .with_coords(rng.uniform(0.0, 40.0), rng.uniform(0.0, 40.0))
+                .with_radius(0.01)
+                .to_owned())
+            .collect();
+        let gas = SimData::new_with_particles(bounds, &particles);
+        let gas_entropy = pair_entropy(&gas, 3.0);
+        assert!(gas_entropy <= 0.0);
+        assert_close!(gas_entropy, 0.0, 0.2);
+
+        // The same density, in a crystal.
+        let height = 40.0 * f64::sqrt(3.0) / 2.0;
+        let crystal_bounds = Bounds::from((0.0, 40.0, 0.0, height));
+        let crystal = SimData::new_with_particles(crystal_bounds, &hexagonal_lattice(40, 40, crystal_bounds, 0.01));
+        assert!(pair_entropy(&crystal, 3.0) < gas_entropy - 1.0);
+    }
+
+    #[test]
+    fn test_fabric_tensor() {
+        // A compressed hexagonal lattice, whose contacts point equally in six directions.
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 3.0 * f64::sqrt(3.0)));
+        let sim_data = SimData::new_with_particles(bounds, &hexagonal_lattice(6, 6, bounds, 0.55));
+        let fabric = fabric_tensor(&sim_data, &create_verlet_lists(&sim_data, 0.1));
+        assert_close!(fabric[0][0], 0.5, 1.0e-9);
+        assert_close!(fabric[1][1], 0.5, 1.0e-9);
+        assert_close!(fabric[0][1], 0.0, 1.0e-9);
+        assert_close!(fabric[1][0], 0.0, 1.0e-9);
+
+        // Rows of particles that only touch their neighbors along x.
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let particles: Vec<Particle> = (0..6)
+            .flat_map(|i| (0..3).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                Particle::new().with_coords(0.5 + i as f64, 1.0 + 2.0 * j as f64).with_radius(0.55).to_owned()
+            })
+            .collect();
+        let sim_data = SimData::new_with_particles(bounds, &particles);
+        let fabric = fabric_tensor(&sim_data, &create_verlet_lists(&sim_data, 0.1));
+        assert_close!(fabric[0][0], 1.0, 1.0e-9);
+        assert_close!(fabric[1][1], 0.0, 1.0e-9);
+
+        // Nothing touches.
+        let loose = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.45));
+        assert_eq!(fabric_tensor(&loose, &create_verlet_lists(&loose, 0.1)), [[0.0; 2]; 2]);
+    }
+
+    #[test]
+    fn test_free_volume_map() {
+        // A single particle, in the corner of four grid cells.
+        let bounds = Bounds::from((0.0, 10.0, 0.0, 10.0));
+        let particle = Particle::new().with_coords(5.0, 5.0).with_radius(0.5).to_owned();
+        let sim_data = SimData::new_with_particles(bounds, &[particle]);
+        let free_volume = free_volume_map(&sim_data, 10, 10);
+        assert_eq!(free_volume.len(), 10);
+        assert_eq!(free_volume[0].len(), 10);
+
+        for (ix, column) in free_volume.iter().enumerate() {
+            for (iy, &entry) in column.iter().enumerate() {
+                let offset = sim_data.minimum_image(Vector::new(ix as f64 + 0.5 - 5.0, iy as f64 + 0.5 - 5.0));
+                assert_close!(entry, offset.length() - 0.5, 1.0e-12);
+            }
+        }
+        // Small next to the particle, and growing with the distance from it.
+        assert_close!(free_volume[4][4], f64::sqrt(0.5) - 0.5, 1.0e-12);
+        for ix in 5..9 {
+            assert!(free_volume[ix][5] < free_volume[ix + 1][5]);
+        }
+
+        let empty = SimData::new_with_particles(bounds, &[]);
+        assert!(free_volume_map(&empty, 2, 2).iter().flatten().all(|v| v.is_infinite()));
+    }
+
+    #[test]
+    fn test_bond_angle_distribution_of_hexagonal_lattice() {
+        // Bins of 7 degrees, so that no peak falls on the edge of a bin.
+        let bounds = Bounds::from((0.0, 8.0, 0.0, 4.0 * f64::sqrt(3.0)));
+        let sim_data = SimData::new_with_particles(bounds, &hexagonal_lattice(8, 8, bounds, 0.5));
+        let distribution = bond_angle_distribution(&sim_data, 1.2, 7.0);
+        assert_eq!(distribution.len(), 26);
+        assert_close!(distribution.iter().map(|(_, p)| p * 7.0).sum::<f64>(), 1.0, 1.0e-9);
+
+        // Of the 15 angles between the 6 bonds of each particle, 6 are 60 degrees, 6 are 120, and 3 are 180.
+        let probability = |distribution: &[(f64, f64)], angle: f64| distribution[(angle / 7.0) as usize].1 * 7.0;
+        assert_close!(probability(&distribution, 60.0), 0.4, 1.0e-9);
+        assert_close!(probability(&distribution, 120.0), 0.4, 1.0e-9);
+        assert_close!(probability(&distribution, 180.0), 0.2, 1.0e-9);
+        assert_close!(probability(&distribution, 90.0), 0.0, 1.0e-9);
+
+        // A square lattice has its peaks at 90 and 180 degrees instead.
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let sim_data = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.5));
+        let distribution = bond_angle_distribution(&sim_data, 1.2, 7.0);
+        assert_close!(probability(&distribution, 90.0), 4.0 / 6.0, 1.0e-9);
+        assert_close!(probability(&distribution, 180.0), 2.0 / 6.0, 1.0e-9);
+        assert_close!(probability(&distribution, 60.0), 0.0, 1.0e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "bin width must be positive")]
+    fn test_bond_angle_distribution_rejects_empty_bins() {
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let sim_data = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.5));
+        bond_angle_distribution(&sim_data, 1.2, 0.0);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::core::simdata::SimData;
+use crate::core::vector::Vector;
+
+/// Clip a convex polygon by a half plane, keeping the part of the polygon where `point · normal <= offset`.
+fn clip_polygon(polygon: &[Vector], normal: Vector, offset: f64) -> Vec<Vector> {
+    let inside = |p: Vector| p.x * normal.x + p.y * normal.y <= offset;
+
+    let mut clipped = Vec::new();
+    for i in 0..polygon.len() {
+        let current = polygon[i];
+        let next = polygon[(i + 1) % polygon.len()];
+        if inside(current) {
+            clipped.push(current);
+        }
+        if inside(current) != inside(next) {
+            // Add the point where the edge crosses the boundary of the half plane.
+            let d_current = current.x * normal.x + current.y * normal.y - offset;
+            let d_next = next.x * normal.x + next.y * normal.y - offset;
+            let t = d_current / (d_current - d_next);
+            clipped.push(current + (next - current) * t);
+        }
+    }
+    clipped
+}
+
+/// The area of a polygon, from the shoelace formula.
+fn polygon_area(polygon: &[Vector]) -> f64 {
+    let mut twice_area = 0.0;
+    for i in 0..polygon.len() {
+        let p = polygon[i];
+        let q = polygon[(i + 1) % polygon.len()];
+        twice_area += p.x * q.y - q.x * p.y;
+    }
+    0.5 * f64::abs(twice_area)
+}
+
+/// Compute the vertices of a particle's Voronoi cell, relative to the particle's position, with periodic
+/// boundary conditions.
+///
+/// The cell starts as the box centered on the particle, which is its cell with respect to its own periodic images,
+/// and is then clipped by the perpendicular bisector of every image of every other particle in the neighboring
+/// copies of the box. This is O(N) per particle.
+pub fn voronoi_cell(sim_data: &SimData, id: usize) -> Vec<Vector> {
+    let (width, height) = (sim_data.width(), sim_data.height());
+    let (hw, hh) = (0.5 * width, 0.5 * height);
+    let mut cell = vec![
+        Vector::new(-hw, -hh),
+        Vector::new(hw, -hh),
+        Vector::new(hw, hh),
+        Vector::new(-hw, hh),
+    ];
+
+    for other in 0..sim_data.num_particles() {
+        if other == id {
+            continue;
+        }
+        let displacement = sim_data.displacement_between(id, other);
+        for ix in -1..=1 {
+            for iy in -1..=1 {
+                let image = displacement + Vector::new(ix as f64 * width, iy as f64 * height);
+                cell = clip_polygon(&cell, image, 0.5 * image.length_sqr());
+            }
+        }
+    }
+    cell
+}
+
+/// Compute the area of each particle's Voronoi cell, with periodic boundary conditions.
+pub fn voronoi_areas(sim_data: &SimData) -> Vec<f64> {
+    (0..sim_data.num_particles())
+        .map(|id| polygon_area(&voronoi_cell(sim_data, id)))
+        .collect()
+}
+
+/// Compute the Voronoi coordination number of each particle, the number of edges of its Voronoi cell, i.e. the number
+/// of neighbors it shares a cell edge with, with periodic boundary conditions. Unlike counting the particles within a
+/// cutoff, this needs no parameters. Edges shorter than a tiny fraction of the cell's size, which come from several
+/// cells meeting at a point, like the diagonal neighbors in a square lattice, are not counted.
+pub fn voronoi_coordination(sim_data: &SimData) -> Vec<usize> {
+    const EDGE_TOLERANCE: f64 = 1.0e-6;
+
+    (0..sim_data.num_particles())
+        .map(|id| {
+            let cell = voronoi_cell(sim_data, id);
+            let min_length = EDGE_TOLERANCE * polygon_area(&cell).sqrt();
+            (0..cell.len()).filter(|&i| min_length < (cell[(i + 1) % cell.len()] - cell[i]).length()).count()
+        })
+        .collect()
+}
+
+/// Count the disclinations in a 2D crystal, the particles whose Voronoi coordination is 5 or 7 instead of the 6 of a
+/// hexagonal lattice. Returns (number of 5-fold particles, number of 7-fold particles). Dislocations appear as bound
+/// 5-7 pairs, and free disclinations as unpaired ones, which drive 2D melting.
+pub fn count_defects(sim_data: &SimData) -> (usize, usize) {
+    let coordination = voronoi_coordination(sim_data);
+    let count = |n: usize| coordination.iter().filter(|&&c| c == n).count();
+    (count(5), count(7))
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::utils::init::{hexagonal_lattice, square_lattice};
+
+    #[test]
+    fn test_voronoi_areas_square_lattice() {
+        let spacing = 0.8;
+        let mut particles = Vec::new();
+        for ix in 0..5 {
+            for iy in 0..5 {
+                particles.push(Particle::new()
+                    .with_coords((ix as f64 + 0.5) * spacing, (iy as f64 + 0.5) * spacing)
+                    .with_radius(0.1)
+                    .to_owned());
+            }
+        }
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 4.0, 0.0, 4.0)), &particles);
+
+        let areas = voronoi_areas(&sim_data);
+        assert_eq!(areas.len(), 25);
+        for area in areas {
+            assert_close!(area, spacing * spacing, 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn test_voronoi_areas_sum_to_box_area() {
+        let particles = vec![
+            Particle::new().with_coords(0.5, 0.7).to_owned(),
+            Particle::new().with_coords(3.1, 1.2).to_owned(),
+            Particle::new().with_coords(1.9, 3.3).to_owned(),
+            Particle::new().with_coords(2.2, 2.0).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 4.0, 0.0, 4.0)), &particles);
+
+        let total: f64 = voronoi_areas(&sim_data).iter().sum();
+        assert_close!(total, 16.0, 1.0e-9);
+    }
+
+    #[test]
+    fn test_voronoi_coordination_of_lattices() {
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 6.0));
+        let square = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.5));
+        assert_eq!(voronoi_coordination(&square), vec![4; 36]);
+
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 3.0 * f64::sqrt(3.0)));
+        let hexagonal = SimData::new_with_particles(bounds, &hexagonal_lattice(6, 6, bounds, 0.5));
+        assert_eq!(voronoi_coordination(&hexagonal), vec![6; 36]);
+
+        // A square lattice that is stretched along x is still a square lattice to the Voronoi construction, while a
+        // cutoff based count would lose the neighbors along x.
+        let bounds = Bounds::from((0.0, 9.0, 0.0, 6.0));
+        let stretched = SimData::new_with_particles(bounds, &square_lattice(6, 6, bounds, 0.5));
+        assert_eq!(voronoi_coordination(&stretched), vec![4; 36]);
+    }
+
+    #[test]
+    fn test_count_defects() {
+        let bounds = Bounds::from((0.0, 8.0, 0.0, 4.0 * f64::sqrt(3.0)));
+        let mut particles = hexagonal_lattice(8, 8, bounds, 0.5);
+        let sim_data = SimData::new_with_particles(bounds, &particles);
+        assert!(voronoi_coordination(&sim_data).iter().all(|&c| c == 6));
+        assert_eq!(count_defects(&sim_data), (0, 0));
+
+        // Insert a particle at the center of a triangle of neighbors. Its Voronoi cell is a triangle that cuts away
+        // the edges between the three, so each of them goes from 6 to 5 neighbors. The interstitial itself only has 3.
+        let (p, q, r) = (particles[27].position, particles[28].position, particles[36].position);
+        let center = (p + q + r) / 3.0;
+        particles.push(Particle::new().with_coords(center.x, center.y).with_radius(0.5).to_owned());
+        let sim_data = SimData::new_with_particles(bounds, &particles);
+        let coordination = voronoi_coordination(&sim_data);
+        assert_eq!((coordination[27], coordination[28], coordination[36], coordination[64]), (5, 5, 5, 3));
+        assert_eq!(count_defects(&sim_data), (3, 0));
+    }
+}
@@ -1,5 +1,7 @@
+pub mod kd_tree;
 pub mod linked_cells;
 pub mod particle;
+pub mod protocol;
 pub mod simdata;
 pub mod vector;
 pub mod verlet_lists;
@@ -1,13 +1,112 @@
 use crate::core::simdata::SimData;
 use crate::core::vector::Vector;
 
+// ======================================================
+//  Define modules.
+// ======================================================
+
+pub mod bonds;
+
 /// An object with the force trait needs to be able to calculate the force between two particles in a SimData.
 pub trait Force {
-    fn calculate_forces(&self, sim_data: &mut SimData, id1: usize, id2: usize);
+    /// Add the forces between two particles to the force buffer of the SimData.
+    fn calculate_forces(&self, sim_data: &mut SimData, id1: usize, id2: usize) {
+        let force = self.pair_force(sim_data, id1, id2);
+        sim_data.forces[id1] -= force;
+        sim_data.forces[id2] += force;
+    }
+
+    /// The force that the first particle exerts on the second particle.
+    fn pair_force(&self, sim_data: &SimData, id1: usize, id2: usize) -> Vector;
+
+    /// The potential energy of the interaction between two particles.
+    fn pair_potential(&self, sim_data: &SimData, id1: usize, id2: usize) -> f64;
+
+    /// How much farther apart than the sum of their radii two particles in a SimData can be and still interact. The
+    /// verlet lists' cutoff is widened by this. By default, particles only interact when they overlap.
+    fn extra_reach(&self, _sim_data: &SimData) -> f64 {
+        0.0
+    }
 }
 
+/// A force that is not a sum of pairwise interactions between nearby particles, like bonds between particular
+/// particles, or three-body forces. Body forces are applied after the pairwise forces.
+pub trait BodyForce {
+    /// Add the body forces to the force buffer of the SimData.
+    fn apply_body_forces(&self, sim_data: &mut SimData);
+
+    /// The total potential energy of the body force.
+    fn potential_energy(&self, sim_data: &SimData) -> f64;
+}
+
+#[derive(Clone)]
 pub struct HardSphereForce {
     pub(crate) repulsion: f64,
+
+    /// If set, the (aspect ratio, orientation) of an elliptical contact condition. The contact distance is stretched
+    /// by the aspect ratio along the direction at angle `orientation` to the x axis.
+    anisotropy: Option<(f64, f64)>,
+}
+
+impl HardSphereForce {
+    pub fn new(repulsion: f64) -> HardSphereForce {
+        HardSphereForce { repulsion, anisotropy: None }
+    }
+
+    /// Use an elliptical contact condition, with the contact distance stretched by `aspect_ratio` along the direction
+    /// at angle `orientation` to the x axis. Allows for chaining.
+    ///
+    /// NOTE: Particles can then interact at up to `aspect_ratio` times the sum of their radii, which `extra_reach`
+    /// reports so that the verlet lists are built with a large enough cutoff to find these pairs.
+    pub fn with_anisotropy(&mut self, aspect_ratio: f64, orientation: f64) -> &mut Self {
+        self.anisotropy = Some((aspect_ratio, orientation));
+        self
+    }
+
+    /// The distance at which two particles, whose radii sum to `sum_radii`, come into contact along the direction
+    /// of a unit vector.
+    fn contact_distance(&self, sum_radii: f64, unit: Vector) -> f64 {
+        match self.anisotropy {
+            None => sum_radii,
+            Some((aspect_ratio, orientation)) => {
+                let along = unit.x * orientation.cos() + unit.y * orientation.sin();
+                let across = -unit.x * orientation.sin() + unit.y * orientation.cos();
+                sum_radii / f64::sqrt(along * along / (aspect_ratio * aspect_ratio) + across * across)
+            }
+        }
+    }
+
+    /// If the particles are in contact, get their overlap and the unit vector pointing from the first to the second.
+    fn overlap(&self, sim_data: &SimData, id1: usize, id2: usize) -> Option<(f64, Vector)> {
+        let displacement = sim_data.displacement_between(id1, id2);
+        let distance = displacement.length();
+        if distance == 0.0 {
+            return None;
+        }
+        let unit = displacement / distance;
+        let overlap = self.contact_distance(sim_data.radii[id1] + sim_data.radii[id2], unit) - distance;
+        if 0.0 < overlap { Some((overlap, unit)) } else { None }
+    }
+}
+
+/// A force whose strength is scaled by a constant factor relative to some other force.
+pub struct ScaledForce<'a> {
+    pub inner: &'a dyn Force,
+    pub scale: f64,
+}
+
+impl<'a> Force for ScaledForce<'a> {
+    fn pair_force(&self, sim_data: &SimData, id1: usize, id2: usize) -> Vector {
+        self.inner.pair_force(sim_data, id1, id2) * self.scale
+    }
+
+    fn pair_potential(&self, sim_data: &SimData, id1: usize, id2: usize) -> f64 {
+        self.inner.pair_potential(sim_data, id1, id2) * self.scale
+    }
+
+    fn extra_reach(&self, sim_data: &SimData) -> f64 {
+        self.inner.extra_reach(sim_data)
+    }
 }
 
 pub fn force_loop<Iterable>(force: &dyn Force, sim_data: &mut SimData, iterable: Iterable)
@@ -24,19 +123,135 @@ pub fn force_loop<Iterable>(force: &dyn Force, sim_data: &mut SimData, iterable:
     }
 }
 
+/// Like `force_loop`, but accumulate the forces into a caller supplied buffer instead of the SimData's forces, which
+/// are left untouched. The buffer must have an entry for every particle. Since the SimData is not modified, the
+/// forces are found with `pair_force`.
+pub fn force_loop_into<Iterable>(force: &dyn Force, sim_data: &SimData, iterable: Iterable, out: &mut [Vector])
+    where Iterable: IntoIterator<Item=(usize, usize)>
+{
+    // Clear the buffer of forces.
+    for f in out.iter_mut() {
+        *f = Vector::zero();
+    }
+
+    for (id1, id2) in iterable.into_iter() {
+        let f = force.pair_force(sim_data, id1, id2);
+        out[id1] -= f;
+        out[id2] += f;
+    }
+}
+
 impl Force for HardSphereForce {
-    fn calculate_forces(&self, sim_data: &mut SimData, id1: usize, id2: usize) {
-        let rsqr = sim_data.distance_sqr_between(id1, id2);
-        let sum_radii = sim_data.radii[id1] + sim_data.radii[id2];
-        if rsqr < sum_radii * sum_radii {
-            // Calculate the magnitude of the force.
-            let overlap = sum_radii - f64::sqrt(sum_radii);
+    fn pair_force(&self, sim_data: &SimData, id1: usize, id2: usize) -> Vector {
+        match self.overlap(sim_data, id1, id2) {
+            Some((overlap, unit)) => unit * self.repulsion * overlap,
+            None => Vector::zero(),
+        }
+    }
+
+    /// The hard sphere force is a harmonic repulsion, so the potential is (k/2) * overlap^2.
+    fn pair_potential(&self, sim_data: &SimData, id1: usize, id2: usize) -> f64 {
+        match self.overlap(sim_data, id1, id2) {
+            Some((overlap, _)) => 0.5 * self.repulsion * overlap * overlap,
+            None => 0.0,
+        }
+    }
+
+    /// With an elliptical contact condition, the contact distance of the largest particles is stretched by up to the
+    /// aspect ratio.
+    fn extra_reach(&self, sim_data: &SimData) -> f64 {
+        match self.anisotropy {
+            None => 0.0,
+            Some((aspect_ratio, _)) => {
+                let max_radius = sim_data.radii.iter().copied().fold(0.0, f64::max);
+                f64::max(aspect_ratio - 1.0, 0.0) * 2.0 * max_radius
+            }
+        }
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+
+    #[test]
+    fn test_hard_sphere_force() {
+        // The particles overlap by 0.2 across the periodic boundary.
+        let particles = vec![
+            Particle::new().with_coords(0.3, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(9.5, 5.0).with_radius(0.5).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let force = HardSphereForce::new(100.0);
+        force_loop(&force, &mut sim_data, vec![(0, 1)]);
+
+        assert_close!(sim_data.forces[0].x, 20.0, 1.0e-9);
+        assert_close!(sim_data.forces[0].y, 0.0, 1.0e-9);
+        assert_close!(sim_data.forces[1].x, -20.0, 1.0e-9);
+        assert_close!(force.pair_potential(&sim_data, 0, 1), 2.0, 1.0e-9);
+
+        let scaled = ScaledForce { inner: &force, scale: 0.25 };
+        assert_close!(scaled.pair_force(&sim_data, 0, 1).x, -5.0, 1.0e-9);
+        assert_close!(scaled.pair_potential(&sim_data, 0, 1), 0.5, 1.0e-9);
+    }
+
+    #[test]
+    fn test_force_loop_into() {
+        let particles = vec![
+            Particle::new().with_coords(4.2, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.0, 5.3).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.8, 5.0).with_radius(0.5).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let force = HardSphereForce::new(100.0);
+        let pairs = vec![(0, 1), (1, 2), (0, 2)];
 
-            let displacement = sim_data.positions[id2] - sim_data.positions[id1];
-            let unit = Vector::normalize(displacement);
+        let mut out = vec![Vector::new(1.0, 1.0); 3];
+        force_loop_into(&force, &sim_data, pairs.clone(), &mut out);
+        // The SimData's forces are untouched.
+        assert!(sim_data.forces.iter().all(|f| f.x == 0.0 && f.y == 0.0));
 
-            sim_data.forces[id1] -= unit * self.repulsion * overlap;
-            sim_data.forces[id2] += unit * self.repulsion * overlap;
+        force_loop(&force, &mut sim_data, pairs);
+        for (buffered, in_place) in out.iter().zip(sim_data.forces.iter()) {
+            assert_eq!(buffered.x, in_place.x);
+            assert_eq!(buffered.y, in_place.y);
         }
+        assert!(0.0 < out[1].y);
+    }
+
+    #[test]
+    fn test_anisotropic_hard_sphere_force() {
+        // The particles are 1.2 apart along the x axis, so circles of radius 0.5 would not be in contact.
+        let particles = vec![
+            Particle::new().with_coords(4.4, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.6, 5.0).with_radius(0.5).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let circles = HardSphereForce::new(100.0);
+        assert_eq!(circles.pair_force(&sim_data, 0, 1).length(), 0.0);
+
+        // Elongated along the x axis, the contact distance is 1.5, so the overlap is 0.3.
+        let ellipses = HardSphereForce::new(100.0).with_anisotropy(1.5, 0.0).to_owned();
+        let force = ellipses.pair_force(&sim_data, 0, 1);
+        assert_close!(force.x, 30.0, 1.0e-9);
+        assert_close!(force.y, 0.0, 1.0e-9);
+
+        // Along the short axis, the contact distance is unchanged.
+        sim_data.positions[1] = sim_data.positions[0] + Vector::new(0.0, 1.2);
+        assert_eq!(ellipses.pair_force(&sim_data, 0, 1).length(), 0.0);
+        let rotated = HardSphereForce::new(100.0).with_anisotropy(1.5, 0.5 * std::f64::consts::PI).to_owned();
+        assert_close!(rotated.pair_force(&sim_data, 0, 1).y, 30.0, 1.0e-9);
+
+        // The contact distance of two of the particles reaches up to 1.5, 0.5 more than for circles.
+        assert_eq!(circles.extra_reach(&sim_data), 0.0);
+        assert_close!(ellipses.extra_reach(&sim_data), 0.5, 1.0e-9);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,279 @@
+use crate::core::force::BodyForce;
+use crate::core::simdata::SimData;
+use crate::core::vector::Vector;
+
+/// Harmonic springs between pairs of particles, which pull each pair towards its rest length.
+///
+/// Bonds refer to particles by their unique ids, see `SimData::uid_of`, so they stay attached to the same particles
+/// when other particles are removed and the indices shift. Bonds to particles that have been removed are ignored.
+#[derive(Clone)]
+pub struct HarmonicBonds {
+    /// Each bond, as the unique ids of the two particles and the rest length.
+    pub bonds: Vec<(u64, u64, f64)>,
+
+    /// The spring constant of the bonds.
+    pub stiffness: f64,
+}
+
+impl HarmonicBonds {
+    pub fn new(stiffness: f64) -> HarmonicBonds {
+        HarmonicBonds { bonds: vec![], stiffness }
+    }
+
+    /// Add a bond between the particles with unique ids uid1 and uid2, with some rest length. Particles get the
+    /// unique ids 0, 1, 2, ... in the order that they are added to a SimData. Allows for chaining.
+    pub fn add_bond(&mut self, uid1: u64, uid2: u64, rest_length: f64) -> &mut Self {
+        self.bonds.push((uid1, uid2, rest_length));
+        self
+    }
+
+    /// The current indices of the particles of each bond, and its rest length, for the bonds whose particles both
+    /// still exist.
+    pub fn bond_indices<'a>(&'a self, sim_data: &'a SimData) -> impl Iterator<Item = (usize, usize, f64)> + 'a {
+        self.bonds.iter().filter_map(|&(uid1, uid2, rest_length)| {
+            Some((sim_data.index_of(uid1)?, sim_data.index_of(uid2)?, rest_length))
+        })
+    }
+}
+
+impl BodyForce for HarmonicBonds {
+    fn apply_body_forces(&self, sim_data: &mut SimData) {
+        for (id1, id2, rest_length) in self.bond_indices(sim_data).collect::<Vec<_>>() {
+            let displacement = sim_data.displacement_between(id1, id2);
+            let length = displacement.length();
+            if length == 0.0 {
+                continue;
+            }
+            // Positive tension pulls the particles together.
+            let force = displacement * (self.stiffness * (length - rest_length) / length);
+            sim_data.forces[id1] += force;
+            sim_data.forces[id2] -= force;
+        }
+    }
+
+    fn potential_energy(&self, sim_data: &SimData) -> f64 {
+        self.bond_indices(sim_data)
+            .map(|(id1, id2, rest_length)| {
+                let stretch = sim_data.distance_sqr_between(id1, id2).sqrt() - rest_length;
+                0.5 * self.stiffness * stretch * stretch
+            })
+            .sum()
+    }
+}
+
+/// Harmonic penalties on the angles of triples of particles, U = (1/2) k (theta - theta0)^2, where theta, in [0, pi],
+/// is the angle at the central particle between the bonds to the other two. Gives polymers a bending stiffness.
+///
+/// Like `HarmonicBonds`, angles refer to particles by their unique ids, and angles involving a removed particle are
+/// ignored.
+#[derive(Clone)]
+pub struct AngleBonds {
+    /// Each angle, as the unique ids of the outer, central, and other outer particle, and the rest angle.
+    pub angles: Vec<(u64, u64, u64, f64)>,
+
+    /// The bending stiffness of the angles.
+    pub stiffness: f64,
+}
+
+impl AngleBonds {
+    pub fn new(stiffness: f64) -> AngleBonds {
+        AngleBonds { angles: vec![], stiffness }
+    }
+
+    /// Add an angle between the particles with unique ids uid1, uid2, and uid3, centered on uid2, with some rest
+    /// angle. Allows for chaining.
+    pub fn add_angle(&mut self, uid1: u64, uid2: u64, uid3: u64, rest_angle: f64) -> &mut Self {
+        self.angles.push((uid1, uid2, uid3, rest_angle));
+        self
+    }
+
+    /// The current indices of the particles of each angle, and its rest angle, for the angles whose particles all
+    /// still exist.
+    pub fn angle_indices<'a>(&'a self, sim_data: &'a SimData) -> impl Iterator<Item = (usize, usize, usize, f64)> + 'a {
+        self.angles.iter().filter_map(|&(uid1, uid2, uid3, rest_angle)| {
+            Some((sim_data.index_of(uid1)?, sim_data.index_of(uid2)?, sim_data.index_of(uid3)?, rest_angle))
+        })
+    }
+
+    /// The signed angle, in (-pi, pi], that rotates the bond from j to i onto the bond from j to k, and the bonds.
+    fn signed_angle(sim_data: &SimData, i: usize, j: usize, k: usize) -> (f64, Vector, Vector) {
+        let a = sim_data.displacement_between(j, i);
+        let b = sim_data.displacement_between(j, k);
+        (f64::atan2(a.x * b.y - a.y * b.x, a.x * b.x + a.y * b.y), a, b)
+    }
+}
+
+impl BodyForce for AngleBonds {
+    fn apply_body_forces(&self, sim_data: &mut SimData) {
+        for (i, j, k, rest_angle) in self.angle_indices(sim_data).collect::<Vec<_>>() {
+            let (phi, a, b) = AngleBonds::signed_angle(sim_data, i, j, k);
+            if a.length_sqr() == 0.0 || b.length_sqr() == 0.0 {
+                continue;
+            }
+            // The signed angle is the direction of b minus the direction of a, and the gradient of the direction of a
+            // vector is perpendicular to it, with magnitude one over its length. Since theta = |phi|, dU/dphi picks
+            // up the sign of phi.
+            let du_dphi = self.stiffness * (phi.abs() - rest_angle) * phi.signum();
+            let force_i = Vector::new(-a.y, a.x) * (du_dphi / a.length_sqr());
+            let force_k = Vector::new(b.y, -b.x) * (du_dphi / b.length_sqr());
+            sim_data.forces[i] += force_i;
+            sim_data.forces[k] += force_k;
+            // The central particle balances the forces, so there is no net force on the triple.
+            sim_data.forces[j] -= force_i + force_k;
+        }
+    }
+
+    fn potential_energy(&self, sim_data: &SimData) -> f64 {
+        self.angle_indices(sim_data)
+            .map(|(i, j, k, rest_angle)| {
+                let (phi, _, _) = AngleBonds::signed_angle(sim_data, i, j, k);
+                0.5 * self.stiffness * (phi.abs() - rest_angle).powi(2)
+            })
+            .sum()
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+
+    #[test]
+    fn test_harmonic_bonds() {
+        // A bond stretched by 0.5 across the periodic boundary.
+        let particles = vec![
+            Particle::new().with_coords(0.5, 5.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(9.0, 5.0).with_radius(0.1).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let bonds = HarmonicBonds::new(10.0).add_bond(0, 1, 1.0).to_owned();
+        bonds.apply_body_forces(&mut sim_data);
+
+        assert_close!(sim_data.forces[0].x, -5.0, 1.0e-9);
+        assert_close!(sim_data.forces[1].x, 5.0, 1.0e-9);
+        assert_close!(bonds.potential_energy(&sim_data), 1.25, 1.0e-9);
+    }
+
+    #[test]
+    fn test_bonds_follow_particles_when_others_are_removed() {
+        let particles = vec![
+            Particle::new().with_coords(1.0, 5.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(4.0, 5.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(5.5, 5.0).with_radius(0.1).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let bonds = HarmonicBonds::new(10.0).add_bond(1, 2, 1.0).to_owned();
+
+        // Removing the first particle moves the last one into its place, but the bond stays between the same two.
+        sim_data.remove_particle(0);
+        sim_data.forces = vec![Vector::zero(); 2];
+        bonds.apply_body_forces(&mut sim_data);
+        let (i, j) = (sim_data.index_of(1).unwrap(), sim_data.index_of(2).unwrap());
+        assert_close!(sim_data.forces[i].x, 5.0, 1.0e-9);
+        assert_close!(sim_data.forces[j].x, -5.0, 1.0e-9);
+        assert_close!(bonds.potential_energy(&sim_data), 1.25, 1.0e-9);
+
+        // Once one of its particles is gone, the bond does nothing.
+        sim_data.remove_particle(j);
+        sim_data.forces = vec![Vector::zero(); 1];
+        bonds.apply_body_forces(&mut sim_data);
+        assert_eq!(sim_data.forces[0].x, 0.0);
+        assert_eq!(bonds.potential_energy(&sim_data), 0.0);
+    }
+
+    #[test]
+    fn test_straight_angle_is_force_free() {
+        let particles = vec![
+            Particle::new().with_coords(4.0, 5.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(6.0, 5.0).with_radius(0.1).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let angles = AngleBonds::new(10.0).add_angle(0, 1, 2, std::f64::consts::PI).to_owned();
+        angles.apply_body_forces(&mut sim_data);
+
+        for f in sim_data.forces.iter() {
+            assert_close!(f.length(), 0.0, 1.0e-12);
+        }
+        assert_close!(angles.potential_energy(&sim_data), 0.0, 1.0e-12);
+    }
+
+    #[test]
+    fn test_angles_follow_particles_when_others_are_removed() {
+        // A right angle, after a particle that is not part of it.
+        let particles = vec![
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(4.0, 5.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(5.0, 6.0).with_radius(0.1).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let angles = AngleBonds::new(10.0).add_angle(1, 2, 3, std::f64::consts::PI).to_owned();
+        angles.apply_body_forces(&mut sim_data);
+        let forces = sim_data.forces.clone();
+        let energy = angles.potential_energy(&sim_data);
+
+        // Removing the first particle moves the last one into its place, but the angle stays between the same three,
+        // rather than reaching past the end of the particles.
+        sim_data.remove_particle(0);
+        sim_data.forces = vec![Vector::zero(); 3];
+        angles.apply_body_forces(&mut sim_data);
+        for uid in 1..4 {
+            let force = sim_data.forces[sim_data.index_of(uid).unwrap()];
+            assert_close!(force.x, forces[uid as usize].x, 1.0e-12);
+            assert_close!(force.y, forces[uid as usize].y, 1.0e-12);
+        }
+        assert_close!(angles.potential_energy(&sim_data), energy, 1.0e-12);
+
+        // Once one of its particles is gone, the angle does nothing.
+        sim_data.remove_particle(sim_data.index_of(3).unwrap());
+        sim_data.forces = vec![Vector::zero(); 2];
+        angles.apply_body_forces(&mut sim_data);
+        assert!(sim_data.forces.iter().all(|f| f.length() == 0.0));
+        assert_eq!(angles.potential_energy(&sim_data), 0.0);
+    }
+
+    #[test]
+    fn test_bent_angle_is_restored() {
+        // The last particle is bent up by 45 degrees.
+        let particles = vec![
+            Particle::new().with_coords(4.0, 5.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(6.0, 6.0).with_radius(0.1).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let angles = AngleBonds::new(10.0).add_angle(0, 1, 2, std::f64::consts::PI).to_owned();
+        angles.apply_body_forces(&mut sim_data);
+        let forces = sim_data.forces.clone();
+
+        // The outer particles are pushed down, back towards the line, and the central particle up.
+        assert!(forces[0].y < 0.0 && forces[2].y < 0.0 && 0.0 < forces[1].y);
+        // The forces are perpendicular to the bonds, and balance.
+        assert_close!(forces[0].x, 0.0, 1.0e-12);
+        assert_close!(forces[2].x + forces[2].y, 0.0, 1.0e-12);
+        let net = forces[0] + forces[1] + forces[2];
+        assert_close!(net.length(), 0.0, 1.0e-12);
+
+        // The forces are minus the gradient of the potential energy.
+        let h = 1.0e-6;
+        for (id, force) in forces.iter().enumerate() {
+            for (dx, dy) in [(h, 0.0), (0.0, h)] {
+                let position = sim_data.positions[id];
+                sim_data.positions[id] = position + Vector::new(dx, dy);
+                let forward = angles.potential_energy(&sim_data);
+                sim_data.positions[id] = position - Vector::new(dx, dy);
+                let backward = angles.potential_energy(&sim_data);
+                sim_data.positions[id] = position;
+                let f = force.x * dx / h + force.y * dy / h;
+                assert_close!(f, -(forward - backward) / (2.0 * h), 1.0e-6);
+            }
+        }
+    }
+}
@@ -1,6 +1,4 @@
 use crate::core::simdata::SimData;
-use crate::core::universe::Universe;
-use crate::core::vector::Velocity;
 
 // ======================================================
 //  Define modules.
@@ -8,6 +6,7 @@ use crate::core::vector::Velocity;
 
 pub mod velocity_verlet;
 pub mod overdamped;
+pub mod rattle;
 
 
 /// The integrator trait represents objects that can integrate the particles in a sim data, potentially including
@@ -15,9 +14,16 @@ pub mod overdamped;
 ///
 pub trait Integrator {
     fn get_timestep(&self) -> f64;
+    fn set_timestep(&mut self, dt: f64);
     fn pre_forces(&mut self, sim_data: &mut SimData);
     fn post_forces(&mut self, sim_data: &mut SimData);
     fn post_step(&mut self, sim_data: &mut SimData);
+
+    /// Whether the integrator keeps the particle velocities up to date. If not, the velocities in the sim data are
+    /// stale once the integrator has run.
+    fn updates_velocities(&self) -> bool {
+        true
+    }
 }
 
 
@@ -27,8 +33,6 @@ pub trait Integrator {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn test_update_positions() {}
 }
\ No newline at end of file
@@ -1,9 +1,17 @@
 use crate::core::integrator::Integrator;
 use crate::core::simdata::SimData;
+use crate::core::vector::Position;
+
+/// A mobility that depends on where a particle is, e.g. for particles in a medium with a viscosity gradient.
+pub type MobilityField = Box<dyn Fn(Position) -> f64>;
 
 pub struct OverdampedIntegrator {
     pub dt: f64,
-    pub damping_constant: f64
+    pub damping_constant: f64,
+
+    /// If set, the mobility at each point in space. A particle's displacement in a timestep is then the local mobility
+    /// times the force on it times the timestep, instead of using its inverse mass as the mobility.
+    pub mobility_field: Option<MobilityField>,
 }
 
 impl Integrator for OverdampedIntegrator {
@@ -11,13 +19,23 @@ impl Integrator for OverdampedIntegrator {
         self.dt
     }
 
-    fn pre_forces(&mut self, sim_data: &mut SimData) {}
+    fn set_timestep(&mut self, dt: f64) {
+        self.dt = dt;
+    }
+
+    fn pre_forces(&mut self, _sim_data: &mut SimData) {}
 
     fn post_forces(&mut self, sim_data: &mut SimData) {
         for i in 0..sim_data.num_particles() {
-            let im = 1.0 / sim_data.masses[i];
-            sim_data.positions[i].x += sim_data.forces[i].x * self.dt * im;
-            sim_data.positions[i].y += sim_data.forces[i].y * self.dt * im;
+            if sim_data.frozen[i] {
+                continue;
+            }
+            let mobility = match &self.mobility_field {
+                Some(field) => field(sim_data.positions[i]),
+                None => 1.0 / sim_data.masses[i],
+            };
+            sim_data.positions[i].x += sim_data.forces[i].x * self.dt * mobility;
+            sim_data.positions[i].y += sim_data.forces[i].y * self.dt * mobility;
         }
 
         // Make sure particles stay in their canonical positions.
@@ -27,10 +45,55 @@ impl Integrator for OverdampedIntegrator {
     fn post_step(&mut self, sim_data: &mut SimData) {
         sim_data.simulation_time += self.dt;
     }
+
+    fn updates_velocities(&self) -> bool {
+        false
+    }
 }
 
 impl OverdampedIntegrator {
     pub fn new(dt: f64, damping_constant: f64) -> OverdampedIntegrator {
-        OverdampedIntegrator { dt, damping_constant }
+        OverdampedIntegrator { dt, damping_constant, mobility_field: None }
+    }
+
+    /// Use a position dependent mobility for the particles. Allows for chaining.
+    pub fn with_mobility_field(&mut self, mobility_field: MobilityField) -> &mut Self {
+        self.mobility_field = Some(mobility_field);
+        self
     }
-}
\ No newline at end of file
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::vector::Vector;
+
+    #[test]
+    fn test_mobility_field() {
+        // The mobility steps up from 1 in the left half of the box to 4 in the right half.
+        let mut integrator = OverdampedIntegrator::new(0.01, 1.0);
+        integrator.with_mobility_field(Box::new(|p| if p.x < 5.0 { 1.0 } else { 4.0 }));
+
+        let particles = [
+            Particle::new().with_coords(2.0, 5.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(7.0, 5.0).with_radius(0.1).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        for _ in 0..10 {
+            sim_data.forces = vec![Vector::new(0.0, 1.0); 2];
+            integrator.post_forces(&mut sim_data);
+        }
+
+        // Under the same force, the particle in the high mobility region moves four times as far.
+        assert_close!(sim_data.positions[0].y - 5.0, 0.1, 1.0e-12);
+        assert_close!(sim_data.positions[1].y - 5.0, 0.4, 1.0e-12);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::core::integrator::Integrator;
+use crate::core::simdata::SimData;
+use crate::core::vector::Vector;
+
+/// The constraints are solved once every constrained distance is within this fraction of its target, and every
+/// constrained pair's relative velocity along its bond is within this fraction of the bond length per unit time.
+const RATTLE_TOLERANCE: f64 = 1.0e-10;
+
+/// The most sweeps over the constraints that the solver makes in a step.
+const RATTLE_MAX_ITERATIONS: usize = 500;
+
+/// Velocity verlet integrator with RATTLE constraints, which hold pairs of particles at fixed distances, e.g. to make
+/// rigid dumbbells or molecules. Unlike stiff springs, the constraints do not limit the timestep.
+///
+/// After the drift, SHAKE iterations move each constrained pair along its bond from the start of the step, by the
+/// Lagrange multiplier that restores its length, and the velocities are corrected to match. After the second half
+/// kick, the relative velocity of each pair along its bond is removed in the same way, so that the constraints are
+/// also satisfied by the velocities. Since the constraints are coupled, both are iterated until every constraint is
+/// satisfied. Frozen particles act as if they have infinite mass, and constraints between two frozen particles are
+/// skipped, since neither particle can move.
+pub struct RattleIntegrator {
+    pub dt: f64,
+
+    /// The constrained pairs of particles, and the distance between each pair.
+    pub constraints: Vec<(usize, usize, f64)>,
+
+    /// The number of times the positions or the velocities could not be made to satisfy the constraints within
+    /// `RATTLE_MAX_ITERATIONS` sweeps, e.g. because the constraints contradict each other, or the timestep is too
+    /// large.
+    pub unconverged_solves: u64,
+
+    /// The bond vectors of the constraints at the start of the step.
+    old_bonds: Vec<Vector>,
+}
+
+impl Integrator for RattleIntegrator {
+    fn get_timestep(&self) -> f64 {
+        self.dt
+    }
+
+    fn set_timestep(&mut self, dt: f64) {
+        self.dt = dt;
+    }
+
+    fn pre_forces(&mut self, sim_data: &mut SimData) {
+        self.old_bonds = self.constraints.iter().map(|&(i, j, _)| sim_data.displacement_between(i, j)).collect();
+
+        // First half kick, and drift.
+        self.half_kick(sim_data);
+        for i in 0..sim_data.num_particles() {
+            if !sim_data.frozen[i] {
+                sim_data.positions[i] += sim_data.velocities[i] * self.dt;
+            }
+        }
+        if !self.constrain_positions(sim_data) {
+            self.unconverged_solves += 1;
+        }
+
+        // Make sure particles stay in their canonical positions.
+        sim_data.canonical_positions();
+    }
+
+    fn post_forces(&mut self, sim_data: &mut SimData) {
+        // Second half kick.
+        self.half_kick(sim_data);
+        if !self.constrain_velocities(sim_data) {
+            self.unconverged_solves += 1;
+        }
+    }
+
+    fn post_step(&mut self, sim_data: &mut SimData) {
+        sim_data.simulation_time += self.dt;
+    }
+}
+
+impl RattleIntegrator {
+    pub fn new(dt: f64) -> RattleIntegrator {
+        RattleIntegrator { dt, constraints: vec![], unconverged_solves: 0, old_bonds: vec![] }
+    }
+
+    /// Hold two particles at a fixed distance from each other. Allows for chaining.
+    pub fn with_constraint(&mut self, id1: usize, id2: usize, distance: f64) -> &mut Self {
+        self.constraints.push((id1, id2, distance));
+        self
+    }
+
+    /// Constrain every pair of particles at their current distance, e.g. to make the particles of a molecule move
+    /// as a rigid body. Allows for chaining.
+    pub fn with_constraints_from(&mut self, sim_data: &SimData, pairs: &[(usize, usize)]) -> &mut Self {
+        for &(id1, id2) in pairs {
+            self.constraints.push((id1, id2, sim_data.displacement_between(id1, id2).length()));
+        }
+        self
+    }
+
+    fn inverse_mass(sim_data: &SimData, id: usize) -> f64 {
+        if sim_data.frozen[id] { 0.0 } else { 1.0 / sim_data.masses[id] }
+    }
+
+    fn half_kick(&self, sim_data: &mut SimData) {
+        let hdt = self.dt / 2.0;
+        for i in 0..sim_data.num_particles() {
+            let im = RattleIntegrator::inverse_mass(sim_data, i);
+            sim_data.velocities[i] += sim_data.forces[i] * (hdt * im);
+        }
+    }
+
+    /// SHAKE: move the constrained pairs along their old bonds until every constrained distance is restored. Returns
+    /// whether this converged.
+    fn constrain_positions(&self, sim_data: &mut SimData) -> bool {
+        for _ in 0..RATTLE_MAX_ITERATIONS {
+            let mut converged = true;
+            for (&(i, j, distance), &old_bond) in self.constraints.iter().zip(self.old_bonds.iter()) {
+                let im_i = RattleIntegrator::inverse_mass(sim_data, i);
+                let im_j = RattleIntegrator::inverse_mass(sim_data, j);
+                if im_i + im_j == 0.0 {
+                    continue;
+                }
+                let bond = sim_data.displacement_between(i, j);
+                let error = distance * distance - bond.length_sqr();
+                if error.abs() <= 2.0 * RATTLE_TOLERANCE * distance * distance {
+                    continue;
+                }
+                converged = false;
+
+                let g = error / (2.0 * (bond.x * old_bond.x + bond.y * old_bond.y) * (im_i + im_j));
+                sim_data.positions[i] -= old_bond * (g * im_i);
+                sim_data.positions[j] += old_bond * (g * im_j);
+                sim_data.velocities[i] -= old_bond * (g * im_i / self.dt);
+                sim_data.velocities[j] += old_bond * (g * im_j / self.dt);
+            }
+            if converged {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// RATTLE: remove the relative velocity of each constrained pair along its bond, until none is left. Returns
+    /// whether this converged.
+    fn constrain_velocities(&self, sim_data: &mut SimData) -> bool {
+        for _ in 0..RATTLE_MAX_ITERATIONS {
+            let mut converged = true;
+            for &(i, j, distance) in self.constraints.iter() {
+                let im_i = RattleIntegrator::inverse_mass(sim_data, i);
+                let im_j = RattleIntegrator::inverse_mass(sim_data, j);
+                if im_i + im_j == 0.0 {
+                    continue;
+                }
+                let bond = sim_data.displacement_between(i, j);
+                let relative = sim_data.velocities[j] - sim_data.velocities[i];
+                let bond_velocity = bond.x * relative.x + bond.y * relative.y;
+                if bond_velocity.abs() <= RATTLE_TOLERANCE * distance * distance {
+                    continue;
+                }
+                converged = false;
+
+                let k = bond_velocity / (bond.length_sqr() * (im_i + im_j));
+                sim_data.velocities[i] += bond * (k * im_i);
+                sim_data.velocities[j] -= bond * (k * im_j);
+            }
+            if converged {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+
+    #[test]
+    fn test_constrained_pair_keeps_its_separation() {
+        // A spinning dumbbell of unequal masses, with no net momentum, pulled by different forces on its two ends.
+        let particles = [
+            Particle::new().with_coords(4.5, 5.0).with_radius(0.1).with_velocity_components(0.0, -2.0).to_owned(),
+            Particle::new().with_coords(5.5, 5.0).with_velocity_components(0.0, 1.0).with_mass(2.0).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let mut integrator = RattleIntegrator::new(0.01);
+        integrator.with_constraint(0, 1, 1.0);
+
+        let forces = [Vector::new(1.0, 0.5), Vector::new(-0.3, 2.0)];
+        for _ in 0..1000 {
+            sim_data.forces = forces.to_vec();
+            integrator.pre_forces(&mut sim_data);
+            sim_data.forces = forces.to_vec();
+            integrator.post_forces(&mut sim_data);
+            integrator.post_step(&mut sim_data);
+
+            let bond = sim_data.displacement_between(0, 1);
+            assert_close!(bond.length(), 1.0, 1.0e-8);
+            let relative = sim_data.velocities[1] - sim_data.velocities[0];
+            assert_close!(bond.x * relative.x + bond.y * relative.y, 0.0, 1.0e-8);
+        }
+        assert_close!(sim_data.simulation_time, 10.0, 1.0e-9);
+
+        // The constraint forces are internal, so the momentum only changes by the impulse of the applied forces.
+        let momentum = sim_data.velocities[0] + sim_data.velocities[1] * 2.0;
+        assert_close!(momentum.x, 0.7 * 10.0, 1.0e-6);
+        assert_close!(momentum.y, 2.5 * 10.0, 1.0e-6);
+        assert_eq!(integrator.unconverged_solves, 0);
+    }
+
+    fn rattle_step(integrator: &mut RattleIntegrator, sim_data: &mut SimData) {
+        integrator.pre_forces(sim_data);
+        integrator.post_forces(sim_data);
+        integrator.post_step(sim_data);
+    }
+
+    #[test]
+    fn test_constraint_between_frozen_particles_is_skipped() {
+        let particles = [
+            Particle::new().with_coords(4.5, 5.0).to_owned(),
+            Particle::new().with_coords(5.5, 5.0).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        sim_data.frozen = vec![true, true];
+        let mut integrator = RattleIntegrator::new(0.01);
+        integrator.with_constraint(0, 1, 2.0);
+
+        rattle_step(&mut integrator, &mut sim_data);
+        assert_eq!(sim_data.positions[0].x, 4.5);
+        assert_eq!(sim_data.positions[1].x, 5.5);
+        assert_eq!(integrator.unconverged_solves, 0);
+    }
+
+    #[test]
+    fn test_contradictory_constraints_are_reported() {
+        // Three particles in a row can not be 1 apart from their neighbors, and 3 apart from each other.
+        let particles = [
+            Particle::new().with_coords(4.0, 5.0).to_owned(),
+            Particle::new().with_coords(5.0, 5.0).to_owned(),
+            Particle::new().with_coords(6.0, 5.0).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let mut integrator = RattleIntegrator::new(0.01);
+        integrator.with_constraint(0, 1, 1.0).with_constraint(1, 2, 1.0).with_constraint(0, 2, 3.0);
+
+        rattle_step(&mut integrator, &mut sim_data);
+        assert!(0 < integrator.unconverged_solves);
+    }
+}
@@ -1,8 +1,28 @@
 use crate::core::integrator::Integrator;
 use crate::core::simdata::SimData;
 
+/// Velocity verlet integrator.
+///
+/// Particles can optionally be assigned integration tiers, for multiple time stepping. A particle in tier t is
+/// integrated with a timestep of dt / 2^t, so fast particles can take several substeps during each step of the slow
+/// particles. Each iteration of the simulation then advances time by the substep of the fastest tier. Every particle
+/// drifts by the substep each iteration, so the forces of each substep are found from the positions of all the
+/// particles at the same time, but a particle only kicks at the start and end of its own, longer, step.
+#[derive(Clone)]
 pub struct VelocityVerlet {
-    pub dt: f64
+    pub dt: f64,
+
+    /// The integration tier of each particle. Particles without an entry are in tier 0.
+    pub tiers: Vec<u8>,
+
+    /// If set, the speed of every particle is capped at this value, as a safety valve against runaway particles.
+    pub max_speed: Option<f64>,
+
+    /// Which substep of the outermost step the integrator is on.
+    substep: u64,
+
+    /// The number of (sub)steps each particle has started.
+    steps_taken: Vec<u64>,
 }
 
 impl Integrator for VelocityVerlet {
@@ -10,39 +30,195 @@ impl Integrator for VelocityVerlet {
         self.dt
     }
 
+    fn set_timestep(&mut self, dt: f64) {
+        self.dt = dt;
+    }
+
     fn pre_forces(&mut self, sim_data: &mut SimData) {
+        self.steps_taken.resize(sim_data.num_particles(), 0);
+
         // First half kick.
-        self.update_velocities(sim_data);
+        self.update_velocities(sim_data, |substep, period| substep.is_multiple_of(period));
         self.update_positions(sim_data);
     }
 
     fn post_forces(&mut self, sim_data: &mut SimData) {
         // Second half kick.
-        self.update_velocities(sim_data);
+        self.update_velocities(sim_data, |substep, period| (substep + 1).is_multiple_of(period));
     }
 
     fn post_step(&mut self, sim_data: &mut SimData) {
-        sim_data.simulation_time += self.dt;
+        let num_substeps = 1 << self.max_tier();
+        sim_data.simulation_time += self.dt / num_substeps as f64;
+        self.substep = (self.substep + 1) % num_substeps;
     }
 }
 
 impl VelocityVerlet {
+    pub fn new(dt: f64) -> VelocityVerlet {
+        VelocityVerlet { dt, tiers: vec![], max_speed: None, substep: 0, steps_taken: vec![] }
+    }
+
+    /// Set the integration tier of each particle. Allows for chaining.
+    pub fn with_tiers(&mut self, tiers: Vec<u8>) -> &mut Self {
+        self.tiers = tiers;
+        self.substep = 0;
+        self
+    }
+
+    /// Cap the speed of every particle at a maximum, preserving the direction of its velocity. Allows for chaining.
+    pub fn with_max_speed(&mut self, max_speed: f64) -> &mut Self {
+        self.max_speed = Some(max_speed);
+        self
+    }
+
+    /// The number of (sub)steps that a particle has started.
+    pub fn steps_taken(&self, id: usize) -> u64 {
+        self.steps_taken.get(id).copied().unwrap_or(0)
+    }
+
+    fn max_tier(&self) -> u8 {
+        self.tiers.iter().copied().max().unwrap_or(0)
+    }
+
+    fn tier(&self, id: usize) -> u8 {
+        self.tiers.get(id).copied().unwrap_or(0)
+    }
+
+    /// The number of iterations between the updates of a particle in some tier.
+    fn period(&self, tier: u8) -> u64 {
+        1 << (self.max_tier() - tier)
+    }
+
+    /// The timestep of a particle in some tier.
+    fn tier_timestep(&self, tier: u8) -> f64 {
+        self.dt / (1u64 << tier) as f64
+    }
+
+    /// Drift every particle by the substep of the fastest tier. A particle in a slower tier is not kicked between the
+    /// start and end of its step, so it drifts as far over its substeps as it would in one step.
     fn update_positions(&mut self, sim_data: &mut SimData) {
+        let dt = self.tier_timestep(self.max_tier());
         for i in 0..sim_data.num_particles() {
-            sim_data.positions[i].x += sim_data.velocities[i].x * self.dt;
-            sim_data.positions[i].y += sim_data.velocities[i].y * self.dt;
+            if sim_data.frozen[i] {
+                continue;
+            }
+            if self.substep.is_multiple_of(self.period(self.tier(i))) {
+                self.steps_taken[i] += 1;
+            }
+            sim_data.positions[i].x += sim_data.velocities[i].x * dt;
+            sim_data.positions[i].y += sim_data.velocities[i].y * dt;
         }
 
         // Make sure particles stay in their canonical positions.
         sim_data.canonical_positions();
     }
 
-    fn update_velocities(&mut self, sim_data: &mut SimData) {
-        let hdt = self.dt / 2.0;
+    /// Half kick every particle for which the predicate, evaluated on the current substep and the particle's
+    /// period, is true.
+    fn update_velocities<P>(&mut self, sim_data: &mut SimData, should_kick: P)
+        where P: Fn(u64, u64) -> bool
+    {
         for i in 0..sim_data.num_particles() {
+            let tier = self.tier(i);
+            if sim_data.frozen[i] || !should_kick(self.substep, self.period(tier)) {
+                continue;
+            }
+            let hdt = self.tier_timestep(tier) / 2.0;
             let im = 1.0 / sim_data.masses[i];
             sim_data.velocities[i].x += sim_data.forces[i].x * hdt * im;
             sim_data.velocities[i].y += sim_data.forces[i].y * hdt * im;
+
+            if let Some(max_speed) = self.max_speed {
+                let speed = sim_data.velocities[i].length();
+                if max_speed < speed {
+                    sim_data.velocities[i] = sim_data.velocities[i] * (max_speed / speed);
+                }
+            }
+        }
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::force::{force_loop, Force, HardSphereForce};
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::verlet_lists::create_verlet_lists;
+
+    fn step(integrator: &mut VelocityVerlet, force: &dyn Force, sim_data: &mut SimData) {
+        integrator.pre_forces(sim_data);
+        let verlet_lists = create_verlet_lists(sim_data, 0.1);
+        force_loop(force, sim_data, &verlet_lists);
+        integrator.post_forces(sim_data);
+        integrator.post_step(sim_data);
+    }
+
+    fn total_energy(force: &dyn Force, sim_data: &SimData) -> f64 {
+        let kinetic: f64 = (0..sim_data.num_particles())
+            .map(|i| 0.5 * sim_data.masses[i] * sim_data.velocities[i].length_sqr())
+            .sum();
+        kinetic + force.pair_potential(sim_data, 0, 1)
+    }
+
+    #[test]
+    fn test_multiple_time_stepping() {
+        // Two particles that collide head on. The first particle is in the fast tier.
+        let particles = vec![
+            Particle::new().with_coords(4.2, 5.0).with_radius(0.5).with_velocity_components(1.0, 0.0).to_owned(),
+            Particle::new().with_coords(5.8, 5.0).with_radius(0.5).with_velocity_components(-1.0, 0.0).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let force = HardSphereForce::new(100.0);
+        let mut integrator = VelocityVerlet::new(0.005).with_tiers(vec![1, 0]).to_owned();
+
+        // One outer step. The slow particle drifts with the substeps, so it is always at the same time as the fast one.
+        step(&mut integrator, &force, &mut sim_data);
+        assert_close!(sim_data.positions[0].x, 4.2025, 1.0e-9);
+        assert_close!(sim_data.positions[1].x, 5.7975, 1.0e-9);
+        step(&mut integrator, &force, &mut sim_data);
+        assert_close!(sim_data.positions[1].x, 5.795, 1.0e-9);
+        assert_eq!(integrator.steps_taken(0), 2);
+        assert_eq!(integrator.steps_taken(1), 1);
+        assert_close!(sim_data.simulation_time, 0.005, 1.0e-9);
+
+        let initial_energy = total_energy(&force, &sim_data);
+        for _ in 0..398 {
+            step(&mut integrator, &force, &mut sim_data);
         }
+        assert_eq!(integrator.steps_taken(0), 400);
+        assert_eq!(integrator.steps_taken(1), 200);
+
+        // The particles have collided and are moving apart.
+        assert!(sim_data.velocities[0].x < 0.0);
+        assert!(0.0 < sim_data.velocities[1].x);
+        assert_close!(total_energy(&force, &sim_data), initial_energy, 0.02 * initial_energy);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_max_speed() {
+        let particles = vec![
+            Particle::new().with_coords(2.0, 2.0).with_radius(0.5).with_velocity_components(600.0, 800.0).to_owned(),
+            Particle::new().with_coords(7.0, 7.0).with_radius(0.5).with_velocity_components(0.5, 0.0).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let force = HardSphereForce::new(100.0);
+        let mut integrator = VelocityVerlet::new(0.001).with_max_speed(10.0).to_owned();
+        step(&mut integrator, &force, &mut sim_data);
+
+        // The fast particle is clamped, keeping its direction.
+        assert_close!(sim_data.velocities[0].x, 6.0, 1.0e-9);
+        assert_close!(sim_data.velocities[0].y, 8.0, 1.0e-9);
+        assert_close!(sim_data.positions[0].x, 2.006, 1.0e-9);
+        // The slow particle is unaffected.
+        assert_eq!(sim_data.velocities[1].x, 0.5);
+        assert_eq!(sim_data.velocities[1].y, 0.0);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::core::vector::Position;
+
+/// A static two dimensional KD-tree of particle positions, for finding the particles within some distance of a
+/// point. Unlike linked cells, the cost of a search does not depend on how uniformly the particles are spread out, so
+/// it suits very dilute or clustered systems, where most cells would be empty.
+///
+/// The tree is stored implicitly: the node of a range of the points is its median along the splitting axis, with the
+/// points to the left of it at or below the median, and the points to the right of it at or above it. The splitting
+/// axis alternates between x and y with depth.
+#[derive(Debug, Clone)]
+pub struct KdTree {
+    nodes: Vec<(Position, usize)>,
+}
+
+impl KdTree {
+    /// Build a KD-tree from (position, id) pairs.
+    pub fn new(points: Vec<(Position, usize)>) -> KdTree {
+        let mut nodes = points;
+        KdTree::build(&mut nodes, 0);
+        KdTree { nodes }
+    }
+
+    /// The number of points in the tree.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Check whether the tree has no points.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Find the ids of all the points within `radius` of a point, in no particular order. Distances are not periodic.
+    pub fn within(&self, center: Position, radius: f64) -> Vec<usize> {
+        let mut found = Vec::new();
+        KdTree::search(&self.nodes, 0, center, radius, &mut found);
+        found
+    }
+
+    fn coordinate(position: Position, depth: usize) -> f64 {
+        if depth.is_multiple_of(2) { position.x } else { position.y }
+    }
+
+    fn build(nodes: &mut [(Position, usize)], depth: usize) {
+        if nodes.len() <= 1 {
+            return;
+        }
+        let mid = nodes.len() / 2;
+        nodes.select_nth_unstable_by(mid, |a, b| {
+            KdTree::coordinate(a.0, depth).total_cmp(&KdTree::coordinate(b.0, depth))
+        });
+        let (left, right) = nodes.split_at_mut(mid);
+        KdTree::build(left, depth + 1);
+        KdTree::build(&mut right[1..], depth + 1);
+    }
+
+    fn search(nodes: &[(Position, usize)], depth: usize, center: Position, radius: f64, found: &mut Vec<usize>) {
+        if nodes.is_empty() {
+            return;
+        }
+        let mid = nodes.len() / 2;
+        let (position, id) = nodes[mid];
+        if (position - center).length_sqr() <= radius * radius {
+            found.push(id);
+        }
+
+        // Only search the sides of the splitting line that the search circle reaches.
+        let offset = KdTree::coordinate(center, depth) - KdTree::coordinate(position, depth);
+        if offset <= radius {
+            KdTree::search(&nodes[..mid], depth + 1, center, radius, found);
+        }
+        if -radius <= offset {
+            KdTree::search(&nodes[mid + 1..], depth + 1, center, radius, found);
+        }
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::utils::rng::SimRng;
+
+    #[test]
+    fn test_within_matches_brute_force() {
+        let mut rng = SimRng::new(5);
+        let points: Vec<(Position, usize)> = (0..500)
+            .map(|id| (Position::new(rng.uniform(0.0, 50.0), rng.uniform(0.0, 20.0)), id))
+            .collect();
+        let tree = KdTree::new(points.clone());
+        assert_eq!(tree.len(), 500);
+
+        for _ in 0..20 {
+            let center = Position::new(rng.uniform(0.0, 50.0), rng.uniform(0.0, 20.0));
+            let radius = rng.uniform(0.5, 5.0);
+            let mut found = tree.within(center, radius);
+            found.sort();
+            let expected: Vec<usize> = points
+                .iter()
+                .filter(|(p, _)| (*p - center).length_sqr() <= radius * radius)
+                .map(|&(_, id)| id)
+                .collect();
+            assert_eq!(found, expected);
+        }
+
+        assert!(KdTree::new(vec![]).within(Position::new(0.0, 0.0), 1.0).is_empty());
+    }
+}
@@ -18,6 +18,10 @@ pub struct LinkedCells {
 
     cell_width: f64,
     cell_height: f64,
+
+    /// The number of layers of ghost cells around the cells, which mirror the cells on the opposite side of the
+    /// bounds.
+    ghost_width: usize,
 }
 
 impl LinkedCells {
@@ -29,8 +33,47 @@ impl LinkedCells {
         self.num_y
     }
 
+    /// The width of a cell.
+    pub fn get_cell_width(&self) -> f64 {
+        self.cell_width
+    }
+
+    /// The height of a cell.
+    pub fn get_cell_height(&self) -> f64 {
+        self.cell_height
+    }
+
+    /// Whether the linked cells have a layer of ghost cells.
+    pub fn has_ghost_layer(&self) -> bool {
+        0 < self.ghost_width
+    }
+
+    /// Whether there are enough cells, 2 * ghost_width + 1 in each dimension, for the ghost cells to hold the periodic
+    /// images of the cells on the opposite edge. See `populate_ghost_cells`.
+    pub fn ghost_cells_wrap(&self) -> bool {
+        let min_cells = 2 * self.ghost_width + 1;
+        self.has_ghost_layer() && min_cells <= self.num_x && min_cells <= self.num_y
+    }
+
     /// Create a new set of linked cells object.
     pub fn new(bounds: Bounds, target_size: f64) -> Self {
+        LinkedCells::new_impl(bounds, target_size, 0)
+    }
+
+    /// Create a new set of linked cells object, surrounded by a layer of ghost cells. After particles are added,
+    /// call `populate_ghost_cells` to fill the ghost cells with the periodic images of the particles on the
+    /// opposite edge.
+    pub fn new_with_ghost_layer(bounds: Bounds, target_size: f64) -> Self {
+        LinkedCells::new_impl(bounds, target_size, 1)
+    }
+
+    /// Create a new set of linked cells object, surrounded by `ghost_width` layers of ghost cells. This is needed
+    /// when neighbors are searched for more than one cell away.
+    pub fn new_with_ghost_layers(bounds: Bounds, target_size: f64, ghost_width: usize) -> Self {
+        LinkedCells::new_impl(bounds, target_size, ghost_width)
+    }
+
+    fn new_impl(bounds: Bounds, target_size: f64, ghost_width: usize) -> Self {
         // Calculate the number of x and y cells
         if target_size <= 0. {
             panic!("target size cannot be less than or equal to zero");
@@ -38,7 +81,8 @@ impl LinkedCells {
 
         let num_x = max(1, f64::floor(bounds.width() / target_size) as usize);
         let num_y = max(1, f64::floor(bounds.height() / target_size) as usize);
-        let num_cells = num_x * num_y;
+        let num_ghost = 2 * ghost_width;
+        let num_cells = (num_x + num_ghost) * (num_y + num_ghost);
 
         let cell_width = bounds.width() / (num_x as f64);
         let cell_height = bounds.height() / (num_y as f64);
@@ -50,11 +94,12 @@ impl LinkedCells {
                 Cell {
                     particle_ids: vec![]
                 };
-                num_cells as usize
+                num_cells
             ],
             bounds,
             cell_width,
             cell_height,
+            ghost_width,
         }
     }
 
@@ -63,27 +108,31 @@ impl LinkedCells {
         LinkedCells::new(sim_data.bounds, target_size)
     }
 
+    /// Get the index into the cells vector of a cell. Ghost cells have an x index below 0 or at least num_x, or a
+    /// y index below 0 or at least num_y.
+    fn cell_index(&self, x: i64, y: i64) -> Option<usize> {
+        let g = self.ghost_width as i64;
+        let (num_x, num_y) = (self.num_x as i64, self.num_y as i64);
+        if x < -g || num_x + g <= x || y < -g || num_y + g <= y {
+            return None;
+        }
+        Some(((y + g) * (num_x + 2 * g) + (x + g)) as usize)
+    }
+
     /// Get a cell given the x and y indices of the cell.
     pub fn get_cell(&self, x: usize, y: usize) -> Option<&Cell> {
         if self.num_x <= x || self.num_y <= y {
             return None;
         }
-        let index = self.num_x * y + x;
-        Some(self.cells.get(index as usize).expect("Could not get cell"))
+        let index = self.cell_index(x as i64, y as i64).unwrap();
+        Some(self.cells.get(index).expect("Could not get cell"))
     }
 
+    /// Get the cell that is offset by (dx, dy) from the cell with indices (x, y). If there is a ghost layer,
+    /// this can be a ghost cell.
     pub fn get_adjusted_cell(&self, x: usize, y: usize, dx: i32, dy: i32) -> Option<&Cell> {
-        if (dx < 0 && x < -dx as usize)
-            || (dy < 0 && y < -dy as usize)
-            || (0 < dx && self.num_x < x + dx as usize)
-            || (0 < dy && self.num_y < y + dy as usize)
-        {
-            return None;
-        }
-
-        let adjx = (x as i32) + dx;
-        let adjy = (y as i32) + dy;
-        self.get_cell(adjx as usize, adjy as usize)
+        let index = self.cell_index(x as i64 + dx as i64, y as i64 + dy as i64)?;
+        Some(self.cells.get(index).expect("Could not get cell"))
     }
 
     /// Get a cell given the x and y indices of the cell.
@@ -91,14 +140,72 @@ impl LinkedCells {
         if self.num_x <= x || self.num_y <= y {
             return None;
         }
-        let index = self.num_x * y + x;
+        let index = self.cell_index(x as i64, y as i64).unwrap();
         Some(
             self.cells
-                .get_mut(index as usize)
+                .get_mut(index)
                 .expect("Could not get cell"),
         )
     }
 
+    /// Fill the ghost cells with the particles of the cells on the opposite side of the bounds, i.e. their
+    /// periodic images. Does nothing if there is no ghost layer.
+    ///
+    /// A dimension with fewer than 2 * ghost_width + 1 cells is not wrapped, since a cell's neighbors on either
+    /// side would then include images of the same cell, and pairs would be counted twice.
+    pub fn populate_ghost_cells(&mut self) {
+        self.populate_sheared_ghost_cells(0.0);
+    }
+
+    /// Fill the ghost cells like `populate_ghost_cells`, but with the images above the bounds shifted by `offset` in
+    /// x, and the images below the bounds shifted by `-offset`, as for Lees-Edwards boundary conditions. Since the
+    /// offset is generally not a whole number of cells, a ghost cell above or below the bounds holds the particles of
+    /// both cells that its image overlaps, so the same particle can be in two neighboring ghost cells.
+    pub fn populate_sheared_ghost_cells(&mut self, offset: f64) {
+        if !self.has_ghost_layer() {
+            return;
+        }
+
+        let g = self.ghost_width as i64;
+        let (num_x, num_y) = (self.num_x as i64, self.num_y as i64);
+        let wrap = |i: i64, n: i64| -> Option<i64> {
+            if 0 <= i && i < n {
+                Some(i)
+            } else if n < 2 * g + 1 {
+                None
+            } else {
+                Some(i.rem_euclid(n))
+            }
+        };
+
+        for y in -g..num_y + g {
+            for x in -g..num_x + g {
+                if 0 <= x && x < num_x && 0 <= y && y < num_y {
+                    continue;
+                }
+                let ghost_index = self.cell_index(x, y).unwrap();
+                // The range of x indices of the cells whose images overlap this ghost cell.
+                let shift = if num_y <= y {
+                    offset / self.cell_width
+                } else if y < 0 {
+                    -offset / self.cell_width
+                } else {
+                    0.0
+                };
+                let first = f64::floor(x as f64 - shift) as i64;
+                let last = f64::ceil(x as f64 + 1.0 - shift) as i64 - 1;
+
+                let mut particle_ids = vec![];
+                if let Some(sy) = wrap(y, num_y) {
+                    for sx in (first..=last).filter_map(|sx| wrap(sx, num_x)) {
+                        particle_ids.extend_from_slice(&self.cells[self.cell_index(sx, sy).unwrap()].particle_ids);
+                    }
+                }
+                self.cells[ghost_index].particle_ids = particle_ids;
+            }
+        }
+    }
+
     /// Get what cell a position falls inside.
     pub fn get_cell_indices(&self, x: f64, y: f64) -> (usize, usize) {
         let ix = ((x - self.bounds.xlo) / self.cell_width) as usize;
@@ -124,3 +231,39 @@ impl LinkedCells {
 // =================================================================================================
 //  Unit Tests.
 // =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn test_ghost_cells_mirror_opposite_edge() {
+        let mut linked_cells = LinkedCells::new_with_ghost_layer(Bounds::from((0.0, 10.0, 0.0, 10.0)), 1.0);
+        assert!(linked_cells.has_ghost_layer());
+        linked_cells.add_particle(&Position::new(9.8, 0.5), 0);
+        linked_cells.add_particle(&Position::new(9.8, 9.5), 1);
+        linked_cells.populate_ghost_cells();
+
+        // The cell to the left of the bottom left cell is the image of the bottom right cell.
+        let ghost = linked_cells.get_adjusted_cell(0, 0, -1, 0).unwrap();
+        assert_eq!(ghost.particle_ids, vec![0]);
+
+        // The corner ghost cell is the image of the top right cell.
+        let ghost = linked_cells.get_adjusted_cell(0, 0, -1, -1).unwrap();
+        assert_eq!(ghost.particle_ids, vec![1]);
+
+        // The top ghost cell above the top right cell is the image of the bottom right cell.
+        let ghost = linked_cells.get_adjusted_cell(9, 9, 0, 1).unwrap();
+        assert_eq!(ghost.particle_ids, vec![0]);
+    }
+
+    #[test]
+    fn test_no_ghost_cells() {
+        let linked_cells = LinkedCells::new(Bounds::from((0.0, 10.0, 0.0, 10.0)), 1.0);
+        assert!(!linked_cells.has_ghost_layer());
+        assert!(linked_cells.get_adjusted_cell(0, 0, -1, 0).is_none());
+        assert!(linked_cells.get_adjusted_cell(9, 9, 1, 0).is_none());
+        assert!(linked_cells.get_adjusted_cell(9, 9, -1, 0).is_some());
+    }
+}
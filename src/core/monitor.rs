@@ -1,14 +1,29 @@
 use std::any::Any;
+use crate::core::force::Force;
 use crate::core::simdata::SimData;
-use crate::core::vector::Position;
+use crate::core::vector::{Force as ForceVector, Position, Vector, Velocity};
+use crate::core::verlet_lists::create_verlet_lists;
+
+// ======================================================
+//  Define modules.
+// ======================================================
+
+pub mod bonds;
+pub mod mobility;
+pub mod msd;
+pub mod scattering;
+pub mod susceptibility;
+pub mod temperature;
+pub mod trajectory;
+pub mod viscosity;
 
 /// Defines an implementation of an object that periodically gathers data from a SimData for the purpose
 /// of gathering statistics about the simulation.
 pub trait Monitor {
-    fn pre_step(&mut self, sim_data: &SimData) {}
-    fn pre_forces(&mut self, sim_data: &SimData) {}
-    fn post_forces(&mut self, sim_data: &SimData) {}
-    fn post_step(&mut self, sim_data: &SimData) {}
+    fn pre_step(&mut self, _sim_data: &SimData) {}
+    fn pre_forces(&mut self, _sim_data: &SimData) {}
+    fn post_forces(&mut self, _sim_data: &SimData) {}
+    fn post_step(&mut self, _sim_data: &SimData) {}
 
     fn as_any(&self) -> &dyn Any;
 }
@@ -18,10 +33,15 @@ pub struct PositionMonitor {
     pub times: Vec<f64>,
     /// The positions of all the particles at each time slice.
     pub positions: Vec<Vec<Position>>,
+    /// If record_forces is true, the forces on all the particles at each time slice.
+    pub forces: Vec<Vec<ForceVector>>,
 
     /// Time between snapshots being take.
     pub snapshot_delay: f64,
 
+    /// Whether to record the forces on the particles along with their positions.
+    pub record_forces: bool,
+
     /// The last time at which a snapshot was taken.
     last_snapshot_time: Option<f64>,
 }
@@ -31,10 +51,20 @@ impl PositionMonitor {
         PositionMonitor {
             times: vec![],
             positions: vec![],
+            forces: vec![],
             snapshot_delay,
+            record_forces: false,
             last_snapshot_time: None
         }
     }
+
+    /// Create a position monitor that also records the forces on the particles.
+    pub fn new_with_forces(snapshot_delay: f64) -> PositionMonitor {
+        PositionMonitor {
+            record_forces: true,
+            ..PositionMonitor::new(snapshot_delay)
+        }
+    }
 }
 
 impl Monitor for PositionMonitor {
@@ -47,6 +77,187 @@ impl Monitor for PositionMonitor {
                 new_positions.push(sim_data.positions[i]);
             }
             self.positions.push(new_positions);
+            if self.record_forces {
+                self.forces.push(sim_data.forces.clone());
+            }
+            self.times.push(sim_data.simulation_time);
+
+            self.last_snapshot_time = Some(sim_data.simulation_time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Records the full state, positions, velocities, and radii, of all the particles, for example for rendering particles
+/// colored by their speed.
+pub struct StateMonitor {
+    /// The times at which snapshots are taken.
+    pub times: Vec<f64>,
+    /// The positions of all the particles at each time slice.
+    pub positions: Vec<Vec<Position>>,
+    /// The velocities of all the particles at each time slice.
+    pub velocities: Vec<Vec<Velocity>>,
+    /// The radii of all the particles at each time slice, since particles can grow, or be added or removed.
+    pub radii: Vec<Vec<f64>>,
+
+    /// Time between snapshots being take.
+    pub snapshot_delay: f64,
+
+    /// The last time at which a snapshot was taken.
+    last_snapshot_time: Option<f64>,
+}
+
+impl StateMonitor {
+    pub fn new(snapshot_delay: f64) -> StateMonitor {
+        StateMonitor {
+            times: vec![],
+            positions: vec![],
+            velocities: vec![],
+            radii: vec![],
+            snapshot_delay,
+            last_snapshot_time: None,
+        }
+    }
+}
+
+impl Monitor for StateMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        if self.last_snapshot_time.is_none()
+            || self.snapshot_delay < sim_data.simulation_time - self.last_snapshot_time.unwrap() {
+            self.positions.push(sim_data.positions.clone());
+            self.velocities.push(sim_data.velocities.clone());
+            self.radii.push(sim_data.radii.clone());
+            self.times.push(sim_data.simulation_time);
+
+            self.last_snapshot_time = Some(sim_data.simulation_time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Records the positions of all the particles at exactly the requested frame times, for example to make animations
+/// with a fixed frame rate. Positions are linearly interpolated between the steps that bracket each frame time, using
+/// the minimum image so particles that wrap around a periodic boundary between the steps are handled correctly.
+///
+/// Frames requested before the first step the monitor sees are skipped.
+pub struct FrameMonitor {
+    /// The times of the frames, in increasing order.
+    pub frame_times: Vec<f64>,
+    /// The positions of all the particles at each recorded frame.
+    pub frames: Vec<Vec<Position>>,
+
+    /// The index of the next frame to record.
+    next_frame: usize,
+
+    /// The time and positions at the last step.
+    last_step: Option<(f64, Vec<Position>)>,
+}
+
+impl FrameMonitor {
+    pub fn new(frame_times: Vec<f64>) -> FrameMonitor {
+        let mut frame_times = frame_times;
+        frame_times.sort_by(f64::total_cmp);
+        FrameMonitor { frame_times, frames: vec![], next_frame: 0, last_step: None }
+    }
+
+    /// Create a frame monitor with frames at a fixed rate, starting at time zero and lasting for `duration`.
+    pub fn with_frame_rate(fps: f64, duration: f64) -> FrameMonitor {
+        let num_frames = (duration * fps).floor() as usize + 1;
+        FrameMonitor::new((0..num_frames).map(|i| i as f64 / fps).collect())
+    }
+}
+
+impl Monitor for FrameMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        let time = sim_data.simulation_time;
+        let (last_time, last_positions) = self.last_step
+            .take()
+            .unwrap_or_else(|| (time, sim_data.positions.clone()));
+
+        // Skip any frames from before the monitor started.
+        while self.next_frame < self.frame_times.len() && self.frame_times[self.next_frame] < last_time {
+            self.next_frame += 1;
+        }
+
+        while self.next_frame < self.frame_times.len() && self.frame_times[self.next_frame] <= time {
+            let fraction = if time == last_time {
+                1.0
+            } else {
+                (self.frame_times[self.next_frame] - last_time) / (time - last_time)
+            };
+            let frame = (0..sim_data.num_particles())
+                .map(|i| {
+                    let step = sim_data.minimum_image(sim_data.positions[i] - last_positions[i]);
+                    let mut position = last_positions[i] + step * fraction;
+                    sim_data.topology.canonical_position(&mut position.x, &mut position.y, &sim_data.bounds);
+                    position
+                })
+                .collect();
+            self.frames.push(frame);
+            self.next_frame += 1;
+        }
+
+        self.last_step = Some((time, sim_data.positions.clone()));
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Records the number of particles in the simulation over time, to catch accidental duplication or loss
+/// of particles.
+pub struct ParticleCountMonitor {
+    /// The times at which snapshots are taken.
+    pub times: Vec<f64>,
+    /// The number of particles at each time slice.
+    pub counts: Vec<usize>,
+
+    /// Time between snapshots being take.
+    pub snapshot_delay: f64,
+
+    /// The last time at which a snapshot was taken.
+    last_snapshot_time: Option<f64>,
+}
+
+impl ParticleCountMonitor {
+    pub fn new(snapshot_delay: f64) -> ParticleCountMonitor {
+        ParticleCountMonitor {
+            times: vec![],
+            counts: vec![],
+            snapshot_delay,
+            last_snapshot_time: None
+        }
+    }
+
+    /// Get the recorded (time, count) pairs.
+    pub fn history(&self) -> Vec<(f64, usize)> {
+        self.times.iter().copied().zip(self.counts.iter().copied()).collect()
+    }
+
+    /// Panic if the particle count ever left the range [min, max]. Useful in tests.
+    pub fn assert_count_within(&self, min: usize, max: usize) {
+        for (time, count) in self.history() {
+            if count < min || max < count {
+                panic!("particle count {} at t = {} is outside of the range [{}, {}]", count, time, min, max);
+            }
+        }
+    }
+}
+
+impl Monitor for ParticleCountMonitor {
+    /// If this is the first timestep, or enough time has gone by, save the number of particles.
+    fn post_step(&mut self, sim_data: &SimData) {
+        if self.last_snapshot_time.is_none()
+            || self.snapshot_delay < sim_data.simulation_time - self.last_snapshot_time.unwrap() {
+            self.times.push(sim_data.simulation_time);
+            self.counts.push(sim_data.num_particles());
 
             self.last_snapshot_time = Some(sim_data.simulation_time);
         }
@@ -55,4 +266,373 @@ impl Monitor for PositionMonitor {
     fn as_any(&self) -> &dyn Any {
         self
     }
-}
\ No newline at end of file
+}
+
+/// Measures the collisional part of the pressure, the pair virial (1 / 2A) sum_{i<j} r_ij . F_ij of the overlapping
+/// particles, averaged over time windows. Adding the kinetic part, N T / A, gives the virial pressure, like the trace of
+/// `analysis::stress_tensor`, but averaged over the collisions of each window instead of at a single instant.
+pub struct CollisionPressureMonitor {
+    /// The force between overlapping particles.
+    force: Box<dyn Force>,
+
+    /// The time at the end of each window.
+    pub times: Vec<f64>,
+    /// The collision pressure in each window.
+    pub pressures: Vec<f64>,
+
+    /// The length of time over which the virial is averaged.
+    pub window: f64,
+
+    /// The time integral of the pair virial accumulated so far in the current window.
+    virial: f64,
+    /// The time at which the current window started.
+    window_start: Option<f64>,
+    /// The last time at which the virial was accumulated.
+    last_time: Option<f64>,
+}
+
+impl CollisionPressureMonitor {
+    pub fn new(force: Box<dyn Force>, window: f64) -> CollisionPressureMonitor {
+        CollisionPressureMonitor {
+            force,
+            times: vec![],
+            pressures: vec![],
+            window,
+            virial: 0.0,
+            window_start: None,
+            last_time: None,
+        }
+    }
+}
+
+impl Monitor for CollisionPressureMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        let time = sim_data.simulation_time;
+        let window_start = *self.window_start.get_or_insert(time);
+
+        // Accumulate the virial of all particles in contact since the last step.
+        if let Some(last_time) = self.last_time {
+            let verlet_lists = create_verlet_lists(sim_data, self.force.extra_reach(sim_data));
+            let virial: f64 = verlet_lists
+                .into_iter()
+                .map(|(id1, id2)| {
+                    let r = sim_data.displacement_between(id1, id2);
+                    let f = self.force.pair_force(sim_data, id1, id2);
+                    r.x * f.x + r.y * f.y
+                })
+                .sum();
+            self.virial += virial * (time - last_time);
+        }
+        self.last_time = Some(time);
+
+        if self.window <= time - window_start {
+            let area = sim_data.width() * sim_data.height();
+            self.times.push(time);
+            self.pressures.push(self.virial / (2.0 * area * (time - window_start)));
+
+            self.virial = 0.0;
+            self.window_start = Some(time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Records the heat current, J = sum_i e_i v_i + (1/2) sum_{i != j} r_ij (F_ij . v_i), where e_i is the kinetic plus
+/// potential energy of particle i, r_ij = r_i - r_j, and F_ij is the force on i due to j. Its autocorrelation gives
+/// the thermal conductivity through the Green-Kubo relation.
+pub struct HeatCurrentMonitor {
+    /// The pairwise force between the particles.
+    force: Box<dyn Force>,
+
+    /// The times at which snapshots are taken.
+    pub times: Vec<f64>,
+    /// The heat current at each time slice.
+    pub currents: Vec<Vector>,
+
+    /// Time between snapshots being take.
+    pub snapshot_delay: f64,
+
+    /// The last time at which a snapshot was taken.
+    last_snapshot_time: Option<f64>,
+}
+
+impl HeatCurrentMonitor {
+    pub fn new(force: Box<dyn Force>, snapshot_delay: f64) -> HeatCurrentMonitor {
+        HeatCurrentMonitor {
+            force,
+            times: vec![],
+            currents: vec![],
+            snapshot_delay,
+            last_snapshot_time: None,
+        }
+    }
+
+    /// Compute the heat current of a configuration.
+    pub fn heat_current(&self, sim_data: &SimData) -> Vector {
+        let potential_energies = sim_data.potential_energies(self.force.as_ref());
+        let mut current = Vector::zero();
+        for (i, potential) in potential_energies.into_iter().enumerate() {
+            let v = sim_data.velocities[i];
+            let energy = 0.5 * sim_data.masses[i] * v.length_sqr() + potential;
+            current += v * energy;
+        }
+
+        // Each pair contributes (1/2) r_ij (F_ij . (v_i + v_j)), which is the same with i and j swapped.
+        let max_radius = sim_data.radii.iter().copied().fold(f64::NAN, f64::max);
+        for (id1, id2) in &create_verlet_lists(sim_data, max_radius) {
+            let force = self.force.pair_force(sim_data, id1, id2);
+            let v = sim_data.velocities[id1] + sim_data.velocities[id2];
+            current += sim_data.displacement_between(id1, id2) * (0.5 * (force.x * v.x + force.y * v.y));
+        }
+        current
+    }
+}
+
+impl Monitor for HeatCurrentMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        if self.last_snapshot_time.is_none()
+            || self.snapshot_delay < sim_data.simulation_time - self.last_snapshot_time.unwrap() {
+            let current = if sim_data.is_empty() { Vector::zero() } else { self.heat_current(sim_data) };
+            self.currents.push(current);
+            self.times.push(sim_data.simulation_time);
+
+            self.last_snapshot_time = Some(sim_data.simulation_time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::analysis::stress_tensor;
+    use crate::core::force::HardSphereForce;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::universe::Universe;
+
+    #[test]
+    fn test_particle_count_monitor_constant_count() {
+        let mut universe = Universe::new(Bounds::from((0.0, 4.0, 0.0, 4.0)));
+        let particles = vec![
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.05).with_velocity_components(0.5, 0.2).to_owned(),
+            Particle::new().with_coords(3.0, 1.0).with_radius(0.05).with_velocity_components(-0.3, 0.1).to_owned(),
+            Particle::new().with_coords(2.0, 3.0).with_radius(0.05).with_velocity_components(0.0, -0.4).to_owned(),
+        ];
+        universe.sim_data.add_particles(&particles);
+        universe.add_monitor("Count", Box::new(ParticleCountMonitor::new(0.01)));
+
+        universe.run_until(0.1);
+
+        let monitor = universe
+            .get_monitor("Count")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ParticleCountMonitor>()
+            .unwrap();
+        assert!(1 < monitor.counts.len());
+        assert_eq!(monitor.times.len(), monitor.counts.len());
+        monitor.assert_count_within(3, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of the range")]
+    fn test_particle_count_monitor_assertion() {
+        let mut monitor = ParticleCountMonitor::new(0.01);
+        monitor.times = vec![0.0, 0.1];
+        monitor.counts = vec![3, 4];
+        monitor.assert_count_within(3, 3);
+    }
+
+    #[test]
+    fn test_collision_pressure_monitor() {
+        // Two particles that collide across the periodic boundary.
+        let mut universe = Universe::new(Bounds::from((0.0, 4.0, 0.0, 4.0)));
+        let particles = vec![
+            Particle::new().with_coords(0.4, 2.0).with_radius(0.3).with_velocity_components(-1.0, 0.0).to_owned(),
+            Particle::new().with_coords(3.4, 2.0).with_radius(0.3).with_velocity_components(1.0, 0.0).to_owned(),
+        ];
+        universe.sim_data.add_particles(&particles);
+        let monitor = CollisionPressureMonitor::new(Box::new(HardSphereForce::new(100.0)), 0.5);
+        universe.add_monitor("Pressure", Box::new(monitor));
+
+        universe.run_until(1.1);
+
+        let monitor = universe
+            .get_monitor("Pressure")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<CollisionPressureMonitor>()
+            .unwrap();
+        assert_eq!(monitor.pressures.len(), 2);
+        assert!(monitor.pressures.iter().all(|p| 0.0 <= *p));
+        assert!(monitor.pressures.iter().any(|p| 0.0 < *p));
+    }
+
+    #[test]
+    fn test_collision_pressure_is_the_pair_virial() {
+        // Two frozen particles that overlap by 0.2, pushing each other apart with a force of 20 over a distance of
+        // 0.8, in a box of area 16.
+        let mut universe = Universe::new(Bounds::from((0.0, 4.0, 0.0, 4.0)));
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(1.6, 2.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(2.4, 2.0).with_radius(0.5).to_owned(),
+        ]);
+        universe.sim_data.frozen = vec![true, true];
+        let monitor = CollisionPressureMonitor::new(Box::new(HardSphereForce::new(100.0)), 0.5);
+        universe.add_monitor("Pressure", Box::new(monitor));
+        universe.run_until(1.1);
+
+        let monitor = universe
+            .get_monitor("Pressure")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<CollisionPressureMonitor>()
+            .unwrap();
+        let stress = stress_tensor(&universe.sim_data, &HardSphereForce::new(100.0));
+        for &pressure in &monitor.pressures {
+            assert_close!(pressure, 0.8 * 20.0 / (2.0 * 16.0), 1.0e-9);
+            assert_close!(pressure, 0.5 * (stress[0][0] + stress[1][1]), 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn test_collision_pressure_of_anisotropic_contact() {
+        // Two frozen particles 1.2 apart, further than the sum of their radii, but the elliptical contact distance
+        // along x is 1.5, so they push each other apart with a force of 30.
+        let mut universe = Universe::new(Bounds::from((0.0, 4.0, 0.0, 4.0)));
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(1.4, 2.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(2.6, 2.0).with_radius(0.5).to_owned(),
+        ]);
+        universe.sim_data.frozen = vec![true, true];
+        let force = HardSphereForce::new(100.0).with_anisotropy(1.5, 0.0).to_owned();
+        universe.add_monitor("Pressure", Box::new(CollisionPressureMonitor::new(Box::new(force), 0.5)));
+        universe.run_until(1.1);
+
+        let monitor = universe
+            .get_monitor("Pressure")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<CollisionPressureMonitor>()
+            .unwrap();
+        assert!(!monitor.pressures.is_empty());
+        for &pressure in &monitor.pressures {
+            assert_close!(pressure, 1.2 * 30.0 / (2.0 * 16.0), 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn test_position_monitor_records_forces() {
+        let mut universe = Universe::new(Bounds::from((0.0, 4.0, 0.0, 4.0)));
+        let particles = vec![
+            Particle::new().with_coords(1.0, 2.0).with_radius(0.3).with_velocity_components(1.0, 0.0).to_owned(),
+            Particle::new().with_coords(1.5, 2.1).with_radius(0.3).to_owned(),
+            Particle::new().with_coords(3.0, 3.0).with_radius(0.3).to_owned(),
+        ];
+        universe.sim_data.add_particles(&particles);
+        universe.add_monitor("Positions", Box::new(PositionMonitor::new_with_forces(0.0)));
+
+        // A snapshot is taken every step. Check each one against the state it was taken from.
+        for step in 1..=100 {
+            universe.run_for_iterations(1);
+            let monitor = universe
+                .get_monitor("Positions")
+                .unwrap()
+                .as_any()
+                .downcast_ref::<PositionMonitor>()
+                .unwrap();
+            assert_eq!(monitor.positions.len(), step);
+            assert_eq!(monitor.forces.len(), step);
+            assert_eq!(monitor.times.len(), step);
+
+            let sim_data = &universe.sim_data;
+            let (positions, forces) = (monitor.positions.last().unwrap(), monitor.forces.last().unwrap());
+            assert_eq!(positions.len(), 3);
+            assert_eq!(forces.len(), 3);
+            for i in 0..3 {
+                assert_eq!((positions[i].x, positions[i].y), (sim_data.positions[i].x, sim_data.positions[i].y));
+                assert_eq!((forces[i].x, forces[i].y), (sim_data.forces[i].x, sim_data.forces[i].y));
+            }
+        }
+
+        let monitor = universe
+            .get_monitor("Positions")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<PositionMonitor>()
+            .unwrap();
+        assert!(monitor.forces.iter().any(|f| 0.0 < f[0].length()));
+    }
+
+    #[test]
+    fn test_position_monitor_does_not_record_forces_by_default() {
+        let mut universe = Universe::new(Bounds::from((0.0, 4.0, 0.0, 4.0)));
+        universe.sim_data.add_particles(&[Particle::new().with_coords(1.0, 2.0).with_radius(0.3).to_owned()]);
+        universe.add_monitor("Positions", Box::new(PositionMonitor::new(0.0)));
+        universe.run_for_iterations(10);
+
+        let monitor = universe
+            .get_monitor("Positions")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<PositionMonitor>()
+            .unwrap();
+        assert_eq!(monitor.positions.len(), 10);
+        assert!(monitor.forces.is_empty());
+    }
+
+    #[test]
+    fn test_frame_monitor_interpolates_between_steps() {
+        // The particle crosses the periodic boundary between the second and third steps.
+        let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 10.0, 0.0, 10.0)), 0.01);
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(9.976, 5.0).with_radius(0.3).with_velocity_components(1.0, 0.0).to_owned(),
+        ]);
+        universe.add_monitor("Frames", Box::new(FrameMonitor::new(vec![0.015, 0.025, 0.001])));
+        universe.run_for_iterations(4);
+
+        let monitor = universe.get_monitor("Frames").unwrap().as_any().downcast_ref::<FrameMonitor>().unwrap();
+        // The frame at t = 0.001 comes before the first step the monitor sees.
+        assert_eq!(monitor.frames.len(), 2);
+        // Halfway between 9.986 and 9.996.
+        assert_close!(monitor.frames[0][0].x, 9.991, 1.0e-9);
+        // Halfway between 9.996 and 10.006, which wraps around to 0.006.
+        assert_close!(monitor.frames[1][0].x, 0.001, 1.0e-9);
+        assert_close!(monitor.frames[1][0].y, 5.0, 1.0e-9);
+    }
+
+    #[test]
+    fn test_heat_current_flows_from_hot_to_cold() {
+        // A row of particles in contact, where only the hot particle on the left moves, towards the cold ones.
+        let particles = vec![
+            Particle::new().with_coords(4.2, 5.0).with_radius(0.5).with_velocity_components(2.0, 0.0).to_owned(),
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.8, 5.0).with_radius(0.5).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let monitor = HeatCurrentMonitor::new(Box::new(HardSphereForce::new(100.0)), 0.0);
+        let current = monitor.heat_current(&sim_data);
+
+        // Convective part: (1/2 m v^2 + u_0) v_0 = (2 + 1) * 2. Virial part: (1/2) * 0.8 * (20 * 2).
+        assert_close!(current.x, 6.0 + 16.0, 1.0e-9);
+        assert_close!(current.y, 0.0, 1.0e-9);
+
+        // Reversing the velocity reverses the current.
+        let mut reversed = sim_data;
+        reversed.velocities[0].x = -2.0;
+        assert!(monitor.heat_current(&reversed).x < 0.0);
+    }
+}
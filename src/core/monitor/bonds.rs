@@ -0,0 +1,111 @@
+use std::any::Any;
+use crate::core::force::bonds::HarmonicBonds;
+use crate::core::monitor::Monitor;
+use crate::core::simdata::SimData;
+
+/// Records the length of each bond over time, to check that bonds behave like proper springs.
+pub struct BondLengthMonitor {
+    /// The unique ids of the pairs of particles that are bonded.
+    pub bonds: Vec<(u64, u64)>,
+
+    /// The times at which snapshots are taken.
+    pub times: Vec<f64>,
+    /// The length of every bond at each time slice. Bonds whose particles have been removed have a length of NaN.
+    pub lengths: Vec<Vec<f64>>,
+
+    /// Time between snapshots being take.
+    pub snapshot_delay: f64,
+
+    /// The last time at which a snapshot was taken.
+    last_snapshot_time: Option<f64>,
+}
+
+impl BondLengthMonitor {
+    /// Create a monitor for the bonds of a HarmonicBonds force.
+    pub fn new(bonds: &HarmonicBonds, snapshot_delay: f64) -> BondLengthMonitor {
+        BondLengthMonitor {
+            bonds: bonds.bonds.iter().map(|&(id1, id2, _)| (id1, id2)).collect(),
+            times: vec![],
+            lengths: vec![],
+            snapshot_delay,
+            last_snapshot_time: None,
+        }
+    }
+
+    /// The time averaged length of each bond.
+    pub fn mean_lengths(&self) -> Vec<f64> {
+        let n = self.lengths.len() as f64;
+        (0..self.bonds.len()).map(|b| self.lengths.iter().map(|l| l[b]).sum::<f64>() / n).collect()
+    }
+
+    /// The standard deviation, over time, of the length of each bond.
+    pub fn fluctuations(&self) -> Vec<f64> {
+        let n = self.lengths.len() as f64;
+        self.mean_lengths()
+            .into_iter()
+            .enumerate()
+            .map(|(b, mean)| (self.lengths.iter().map(|l| (l[b] - mean).powi(2)).sum::<f64>() / n).sqrt())
+            .collect()
+    }
+}
+
+impl Monitor for BondLengthMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        if self.last_snapshot_time.is_none()
+            || self.snapshot_delay < sim_data.simulation_time - self.last_snapshot_time.unwrap() {
+            self.lengths.push(
+                self.bonds
+                    .iter()
+                    .map(|&(uid1, uid2)| match (sim_data.index_of(uid1), sim_data.index_of(uid2)) {
+                        (Some(id1), Some(id2)) => sim_data.distance_sqr_between(id1, id2).sqrt(),
+                        _ => f64::NAN,
+                    })
+                    .collect()
+            );
+            self.times.push(sim_data.simulation_time);
+
+            self.last_snapshot_time = Some(sim_data.simulation_time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::universe::Universe;
+
+    #[test]
+    fn test_oscillating_bond() {
+        // A bond with rest length 1, which starts out stretched by 0.2, so it oscillates between 0.8 and 1.2.
+        let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 10.0, 0.0, 10.0)), 0.001);
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(4.4, 5.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(5.6, 5.0).with_radius(0.1).to_owned(),
+        ]);
+        let bonds = HarmonicBonds::new(50.0).add_bond(0, 1, 1.0).to_owned();
+        universe.add_monitor("Bonds", Box::new(BondLengthMonitor::new(&bonds, 0.0)));
+        universe.add_body_force(Box::new(bonds));
+        // The period is 2 pi sqrt(m / 2k) ~ 0.63, so this covers many periods.
+        universe.run_until(6.3);
+
+        let monitor = universe.get_monitor("Bonds").unwrap().as_any().downcast_ref::<BondLengthMonitor>().unwrap();
+        let lengths: Vec<f64> = monitor.lengths.iter().map(|l| l[0]).collect();
+        assert_close!(lengths.iter().copied().fold(f64::MAX, f64::min), 0.8, 1.0e-3);
+        assert_close!(lengths.iter().copied().fold(f64::MIN, f64::max), 1.2, 1.0e-3);
+        assert_close!(monitor.mean_lengths()[0], 1.0, 1.0e-2);
+        // A sinusoid with amplitude 0.2 has a standard deviation of 0.2 / sqrt(2).
+        assert_close!(monitor.fluctuations()[0], 0.2 / f64::sqrt(2.0), 5.0e-3);
+    }
+}
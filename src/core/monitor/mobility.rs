@@ -0,0 +1,105 @@
+use std::any::Any;
+use crate::core::monitor::Monitor;
+use crate::core::monitor::msd::Unwrapper;
+use crate::core::simdata::SimData;
+
+/// Flags the particles that move further than a threshold between consecutive snapshots as mobile, e.g. to highlight
+/// the rearranging particles in movies of dynamical heterogeneity with `Renderer::with_highlight`. In the first
+/// snapshot, no particle is mobile.
+///
+/// Displacements are unwrapped, like in the `MsdMonitor`, which requires that no particle moves more than half the box
+/// between steps.
+pub struct MobilityMonitor {
+    /// The displacement between snapshots above which a particle counts as mobile.
+    pub threshold: f64,
+
+    /// The times at which snapshots are taken.
+    pub times: Vec<f64>,
+    /// Whether each particle was mobile, at each time slice.
+    pub mobile: Vec<Vec<bool>>,
+
+    /// Time between snapshots being take.
+    pub snapshot_delay: f64,
+
+    /// The last time at which a snapshot was taken.
+    last_snapshot_time: Option<f64>,
+
+    /// Unwraps the displacements of the particles.
+    unwrapper: Unwrapper,
+}
+
+impl MobilityMonitor {
+    pub fn new(threshold: f64, snapshot_delay: f64) -> MobilityMonitor {
+        MobilityMonitor {
+            threshold,
+            times: vec![],
+            mobile: vec![],
+            snapshot_delay,
+            last_snapshot_time: None,
+            unwrapper: Unwrapper::new(),
+        }
+    }
+
+    /// The fraction of the particles that were mobile at each time slice.
+    pub fn mobile_fractions(&self) -> Vec<f64> {
+        self.mobile
+            .iter()
+            .map(|flags| flags.iter().filter(|&&m| m).count() as f64 / flags.len() as f64)
+            .collect()
+    }
+}
+
+impl Monitor for MobilityMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        let unwrapped = self.unwrapper.update(sim_data);
+
+        if self.last_snapshot_time.is_none()
+            || self.snapshot_delay < sim_data.simulation_time - self.last_snapshot_time.unwrap() {
+            let threshold_sqr = self.threshold * self.threshold;
+            self.mobile.push(unwrapped.iter().map(|d| threshold_sqr < d.length_sqr()).collect());
+            self.times.push(sim_data.simulation_time);
+            self.unwrapper.reset();
+
+            self.last_snapshot_time = Some(sim_data.simulation_time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::universe::Universe;
+
+    #[test]
+    fn test_only_fast_particle_is_mobile() {
+        // One particle moving at speed 2, which wraps around the box, and a few that stay put.
+        let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 10.0, 0.0, 10.0)), 0.01);
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(9.0, 5.0).with_radius(0.1).with_velocity_components(2.0, 0.0).to_owned(),
+            Particle::new().with_coords(5.0, 8.0).with_radius(0.1).to_owned(),
+            Particle::new().with_coords(3.0, 3.0).with_radius(0.1).with_velocity_components(0.0, 0.05).to_owned(),
+        ]);
+        universe.add_monitor("Mobility", Box::new(MobilityMonitor::new(0.5, 0.5)));
+        universe.run_until(2.0);
+
+        let monitor = universe.get_monitor("Mobility").unwrap().as_any().downcast_ref::<MobilityMonitor>().unwrap();
+        assert!(3 <= monitor.mobile.len());
+        assert_eq!(monitor.mobile[0], vec![false; 4]);
+        for flags in monitor.mobile.iter().skip(1) {
+            assert_eq!(*flags, vec![false, true, false, false]);
+        }
+        assert_eq!(monitor.mobile_fractions()[1], 0.25);
+    }
+}
@@ -0,0 +1,274 @@
+use std::any::Any;
+use std::collections::HashMap;
+use crate::analysis::linear_fit;
+use crate::core::monitor::Monitor;
+use crate::core::simdata::SimData;
+use crate::core::vector::{Position, Vector};
+
+/// Unwraps the displacements of the particles across periodic boundaries, by adding up the minimum images of their
+/// displacements between steps. This requires that no particle moves more than half the box between steps.
+///
+/// Particles are followed by their unique ids, so particles can be added or removed between steps: a new particle
+/// starts with no displacement, and a removed particle is forgotten.
+#[derive(Debug, Clone, Default)]
+pub struct Unwrapper {
+    /// The unique ids of the particles at the last step.
+    uids: Vec<u64>,
+    /// The positions of the particles at the last step.
+    last_positions: Vec<Position>,
+    /// The current unwrapped displacement of each particle.
+    unwrapped: Vec<Vector>,
+}
+
+impl Unwrapper {
+    pub fn new() -> Unwrapper {
+        Unwrapper::default()
+    }
+
+    /// Add the displacements since the last step, and get the unwrapped displacement of every particle, in the
+    /// order of the particles in the SimData.
+    pub fn update(&mut self, sim_data: &SimData) -> &[Vector] {
+        let n = sim_data.num_particles();
+        if self.uids.len() != n || (0..n).any(|i| self.uids[i] != sim_data.uid_of(i)) {
+            self.follow_particles(sim_data);
+        }
+        for i in 0..n {
+            self.unwrapped[i] += sim_data.minimum_image(sim_data.positions[i] - self.last_positions[i]);
+        }
+        self.last_positions.clone_from(&sim_data.positions);
+        &self.unwrapped
+    }
+
+    /// The unwrapped displacement of every particle, as of the last update.
+    pub fn displacements(&self) -> &[Vector] {
+        &self.unwrapped
+    }
+
+    /// Start measuring the displacements from the particles' current positions.
+    pub fn reset(&mut self) {
+        self.unwrapped.fill(Vector::zero());
+    }
+
+    /// Reorder the tracked particles to match the particles in the SimData.
+    fn follow_particles(&mut self, sim_data: &SimData) {
+        let old: HashMap<u64, (Position, Vector)> = self
+            .uids
+            .iter()
+            .zip(self.last_positions.iter().zip(self.unwrapped.iter()))
+            .map(|(&uid, (&position, &displacement))| (uid, (position, displacement)))
+            .collect();
+        self.uids = (0..sim_data.num_particles()).map(|i| sim_data.uid_of(i)).collect();
+        (self.last_positions, self.unwrapped) = self
+            .uids
+            .iter()
+            .zip(sim_data.positions.iter())
+            .map(|(uid, &position)| old.get(uid).copied().unwrap_or((position, Vector::zero())))
+            .unzip();
+    }
+}
+
+/// Records the mean squared displacement (MSD) of the particles from their positions when the monitor first ran.
+///
+/// Displacements are unwrapped, so particles crossing periodic boundaries are handled correctly. This requires that
+/// no particle moves more than half the box between steps.
+pub struct MsdMonitor {
+    /// The times at which snapshots are taken.
+    pub times: Vec<f64>,
+    /// The mean squared displacement at each time slice.
+    pub msd: Vec<f64>,
+    /// The unwrapped displacement of every particle at each time slice.
+    pub displacements: Vec<Vec<Vector>>,
+
+    /// Time between snapshots being take.
+    pub snapshot_delay: f64,
+
+    /// The last time at which a snapshot was taken.
+    last_snapshot_time: Option<f64>,
+
+    /// Unwraps the displacements of the particles.
+    unwrapper: Unwrapper,
+}
+
+impl MsdMonitor {
+    pub fn new(snapshot_delay: f64) -> MsdMonitor {
+        MsdMonitor {
+            times: vec![],
+            msd: vec![],
+            displacements: vec![],
+            snapshot_delay,
+            last_snapshot_time: None,
+            unwrapper: Unwrapper::new(),
+        }
+    }
+
+    /// The recorded MSD as (lag time, msd) pairs, where the lag time is measured from the first snapshot.
+    pub fn series(&self) -> Vec<(f64, f64)> {
+        let Some(&start) = self.times.first() else {
+            return vec![];
+        };
+        self.times.iter().map(|t| t - start).zip(self.msd.iter().copied()).collect()
+    }
+
+    /// The non-Gaussian parameter alpha_2 = <dr^4> / (2 <dr^2>^2) - 1 of the displacements, as (lag time, alpha_2)
+    /// pairs. The prefactor of 1/2 is the two dimensional one, so alpha_2 vanishes for Gaussian displacements, and a
+    /// peak in it signals heterogeneous dynamics. Snapshots where no particle has moved are skipped.
+    pub fn non_gaussian_parameter(&self) -> Vec<(f64, f64)> {
+        let Some(&start) = self.times.first() else {
+            return vec![];
+        };
+        self.times
+            .iter()
+            .zip(self.displacements.iter())
+            .filter_map(|(t, displacements)| {
+                let n = displacements.len() as f64;
+                let second = displacements.iter().map(|d| d.length_sqr()).sum::<f64>() / n;
+                let fourth = displacements.iter().map(|d| d.length_sqr().powi(2)).sum::<f64>() / n;
+                if second == 0.0 {
+                    return None;
+                }
+                Some((t - start, fourth / (2.0 * second * second) - 1.0))
+            })
+            .collect()
+    }
+
+    /// Estimate the diffusion coefficient from the slope of the MSD at long times, D = slope / 4 in two dimensions.
+    ///
+    /// To skip the early, ballistic, part of the MSD, the slope is found by a least squares fit to only the second
+    /// half of the recorded lag times. Returns NaN if there are fewer than two points to fit.
+    pub fn diffusion_coefficient(&self) -> f64 {
+        let series = self.series();
+        let Some(&(max_lag, _)) = series.last() else {
+            return f64::NAN;
+        };
+        let diffusive: Vec<(f64, f64)> = series.into_iter().filter(|(lag, _)| 0.5 * max_lag <= *lag).collect();
+        if diffusive.len() < 2 {
+            return f64::NAN;
+        }
+
+        let (slope, _) = linear_fit(&diffusive);
+        slope / 4.0
+    }
+}
+
+impl Monitor for MsdMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        let unwrapped = self.unwrapper.update(sim_data);
+
+        // If this is the first timestep, or enough time has gone by, save the displacements.
+        if self.last_snapshot_time.is_none()
+            || self.snapshot_delay < sim_data.simulation_time - self.last_snapshot_time.unwrap() {
+            let msd = unwrapped.iter().map(|d| d.length_sqr()).sum::<f64>() / unwrapped.len() as f64;
+            self.times.push(sim_data.simulation_time);
+            self.msd.push(msd);
+            self.displacements.push(unwrapped.to_vec());
+
+            self.last_snapshot_time = Some(sim_data.simulation_time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::universe::Universe;
+    use crate::utils::rng::SimRng;
+
+    #[test]
+    fn test_msd_across_periodic_boundary() {
+        // A free particle moving ballistically, which wraps around the box several times.
+        let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 4.0, 0.0, 4.0)), 0.01);
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.1).with_velocity_components(3.0, 4.0).to_owned()
+        ]);
+        universe.add_monitor("MSD", Box::new(MsdMonitor::new(0.1)));
+        universe.run_until(5.0);
+
+        let monitor = universe.get_monitor("MSD").unwrap().as_any().downcast_ref::<MsdMonitor>().unwrap();
+        for (lag, msd) in monitor.series() {
+            assert_close!(msd, 25.0 * lag * lag, 1.0e-6);
+        }
+        assert!(100.0 < *monitor.msd.last().unwrap());
+    }
+
+    #[test]
+    fn test_unwrapper_follows_particles_through_removals() {
+        let particles = [
+            Particle::new().with_coords(3.9, 1.0).to_owned(),
+            Particle::new().with_coords(1.0, 2.0).to_owned(),
+            Particle::new().with_coords(2.0, 3.9).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 4.0, 0.0, 4.0)), &particles);
+        let mut unwrapper = Unwrapper::new();
+        assert!(unwrapper.update(&sim_data).iter().all(|d| d.length() == 0.0));
+
+        // The last particle wraps around the top of the box, and then takes the place of the first.
+        sim_data.positions[2].y = 0.1;
+        unwrapper.update(&sim_data);
+        sim_data.remove_particle(0);
+        sim_data.positions[0].y = 0.2;
+        let displacements = unwrapper.update(&sim_data);
+        assert_eq!(displacements.len(), 2);
+        assert_close!(displacements[0].y, 0.3, 1.0e-9);
+        assert_close!(displacements[1].y, 0.0, 1.0e-9);
+
+        // A new particle starts out with no displacement.
+        sim_data.add_particle(&Particle::new().with_coords(1.0, 1.0).to_owned());
+        let displacements = unwrapper.update(&sim_data);
+        assert_eq!(displacements.len(), 3);
+        assert_eq!(displacements[2].length(), 0.0);
+    }
+
+    #[test]
+    fn test_diffusion_coefficient() {
+        // Ballistic at short times, and diffusive at long times, with a crossover time tau.
+        let (d, tau) = (0.35, 0.1);
+        let mut monitor = MsdMonitor::new(0.01);
+        for i in 0..1000 {
+            let t = 0.01 * i as f64;
+            monitor.times.push(t);
+            monitor.msd.push(4.0 * d * (t - tau * (1.0 - f64::exp(-t / tau))));
+        }
+        assert_close!(monitor.diffusion_coefficient(), d, 1.0e-6);
+    }
+
+    #[test]
+    fn test_non_gaussian_parameter() {
+        // Diffusive, Gaussian, displacements of many particles.
+        let mut rng = SimRng::new(11);
+        let mut monitor = MsdMonitor::new(0.1);
+        for i in 0..5 {
+            let t = 0.1 * i as f64;
+            let std = f64::sqrt(2.0 * t);
+            monitor.times.push(t);
+            monitor.displacements.push(
+                (0..20000).map(|_| Vector::new(rng.gaussian(0.0, std), rng.gaussian(0.0, std))).collect()
+            );
+        }
+
+        let alpha = monitor.non_gaussian_parameter();
+        // The first snapshot, where nothing has moved yet, is skipped.
+        assert_eq!(alpha.len(), 4);
+        assert_close!(alpha[0].0, 0.1, 1.0e-12);
+        for (_, alpha_2) in alpha {
+            assert_close!(alpha_2, 0.0, 0.05);
+        }
+
+        // Every particle moving the same distance is as far from Gaussian as it gets, with alpha_2 = -1/2.
+        let mut uniform = MsdMonitor::new(0.1);
+        uniform.times = vec![0.0, 1.0];
+        uniform.displacements = vec![vec![Vector::zero(); 2], vec![Vector::new(1.0, 0.0), Vector::new(0.0, -1.0)]];
+        assert_close!(uniform.non_gaussian_parameter()[0].1, -0.5, 1.0e-12);
+    }
+}
@@ -0,0 +1,142 @@
+use std::any::Any;
+use std::f64::consts::PI;
+use crate::core::monitor::Monitor;
+use crate::core::monitor::msd::Unwrapper;
+use crate::core::simdata::SimData;
+use crate::core::vector::Vector;
+
+/// The number of evenly spaced wavevector directions, in [0, pi), that the scattering function is averaged over.
+const NUM_DIRECTIONS: usize = 8;
+
+/// Records the self-intermediate scattering function F_s(k, t) = <cos(k . dr(t))>, averaged over the particles and
+/// over wavevectors of magnitude k in several directions, where dr(t) is each particle's displacement from where it
+/// was when the monitor first ran. The time it takes F_s to decay gives the structural relaxation time. A k near the
+/// first peak of the structure factor probes relaxation on the scale of the interparticle spacing.
+///
+/// Displacements are unwrapped, like in the `MsdMonitor`, which requires that no particle moves more than half the box
+/// between steps.
+pub struct SelfScatteringMonitor {
+    /// The magnitude of the wavevector.
+    pub wavenumber: f64,
+
+    /// The times at which snapshots are taken.
+    pub times: Vec<f64>,
+    /// The self-intermediate scattering function at each time slice.
+    pub fs: Vec<f64>,
+
+    /// Time between snapshots being take.
+    pub snapshot_delay: f64,
+
+    /// The last time at which a snapshot was taken.
+    last_snapshot_time: Option<f64>,
+
+    /// Unwraps the displacements of the particles.
+    unwrapper: Unwrapper,
+}
+
+impl SelfScatteringMonitor {
+    pub fn new(wavenumber: f64, snapshot_delay: f64) -> SelfScatteringMonitor {
+        SelfScatteringMonitor {
+            wavenumber,
+            times: vec![],
+            fs: vec![],
+            snapshot_delay,
+            last_snapshot_time: None,
+            unwrapper: Unwrapper::new(),
+        }
+    }
+
+    /// The recorded scattering function as (lag time, F_s) pairs, where the lag time is measured from the first
+    /// snapshot.
+    pub fn series(&self) -> Vec<(f64, f64)> {
+        let Some(&start) = self.times.first() else {
+            return vec![];
+        };
+        self.times.iter().map(|t| t - start).zip(self.fs.iter().copied()).collect()
+    }
+
+    /// The scattering function of a set of displacements.
+    fn scattering_function(&self, displacements: &[Vector]) -> f64 {
+        let total: f64 = (0..NUM_DIRECTIONS)
+            .map(|m| {
+                let theta = PI * m as f64 / NUM_DIRECTIONS as f64;
+                let k = Vector::new(theta.cos(), theta.sin()) * self.wavenumber;
+                displacements.iter().map(|d| f64::cos(k.x * d.x + k.y * d.y)).sum::<f64>()
+            })
+            .sum();
+        total / (NUM_DIRECTIONS * displacements.len()) as f64
+    }
+}
+
+impl Monitor for SelfScatteringMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        self.unwrapper.update(sim_data);
+
+        if self.last_snapshot_time.is_none()
+            || self.snapshot_delay < sim_data.simulation_time - self.last_snapshot_time.unwrap() {
+            self.fs.push(self.scattering_function(self.unwrapper.displacements()));
+            self.times.push(sim_data.simulation_time);
+
+            self.last_snapshot_time = Some(sim_data.simulation_time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::universe::Universe;
+
+    #[test]
+    fn test_ballistic_scattering() {
+        // Free particles moving at speed 2 along x, which wrap around the box.
+        let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 4.0, 0.0, 4.0)), 0.01);
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.1).with_velocity_components(2.0, 0.0).to_owned(),
+            Particle::new().with_coords(3.0, 2.0).with_radius(0.1).with_velocity_components(2.0, 0.0).to_owned(),
+        ]);
+        let k = 2.0 * PI;
+        universe.add_monitor("Fs", Box::new(SelfScatteringMonitor::new(k, 0.05)));
+        universe.run_until(3.0);
+
+        let monitor = universe.get_monitor("Fs").unwrap().as_any().downcast_ref::<SelfScatteringMonitor>().unwrap();
+        let series = monitor.series();
+        assert_eq!(series[0], (0.0, 1.0));
+        for (lag, fs) in series {
+            // Each direction contributes cos(k v t cos(theta)).
+            let expected = (0..NUM_DIRECTIONS)
+                .map(|m| f64::cos(k * 2.0 * lag * f64::cos(PI * m as f64 / NUM_DIRECTIONS as f64)))
+                .sum::<f64>() / NUM_DIRECTIONS as f64;
+            assert_close!(fs, expected, 1.0e-6);
+        }
+        // The correlation has decayed after the particles move several wavelengths.
+        assert!(*monitor.fs.last().unwrap() < 0.5);
+    }
+
+    #[test]
+    fn test_frozen_particles_do_not_decorrelate() {
+        let mut universe = Universe::new(Bounds::from((0.0, 4.0, 0.0, 4.0)));
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.1).with_velocity_components(2.0, 1.0).to_owned(),
+            Particle::new().with_coords(3.0, 2.0).with_radius(0.1).with_velocity_components(-1.0, 0.0).to_owned(),
+        ]);
+        universe.sim_data.frozen = vec![true, true];
+        universe.add_monitor("Fs", Box::new(SelfScatteringMonitor::new(2.0 * PI, 0.05)));
+        universe.run_until(1.0);
+
+        let monitor = universe.get_monitor("Fs").unwrap().as_any().downcast_ref::<SelfScatteringMonitor>().unwrap();
+        assert!(monitor.fs.iter().all(|&fs| fs == 1.0));
+    }
+}
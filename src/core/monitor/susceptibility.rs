@@ -0,0 +1,202 @@
+use std::any::Any;
+use std::collections::HashMap;
+use crate::core::monitor::Monitor;
+use crate::core::monitor::msd::Unwrapper;
+use crate::core::simdata::SimData;
+use crate::core::vector::Vector;
+
+/// Records the unwrapped displacements of the particles, and computes the four-point dynamic susceptibility
+///     chi_4(t) = N (<Q(t)^2> - <Q(t)>^2),
+/// where Q(t) = (1/N) sum_i w_i(t) is the self-overlap, the fraction of particles that moved less than an overlap
+/// length a over a lag t, and the averages are over time origins. For independent particles chi_4 stays of order one,
+/// while particles that rearrange cooperatively make Q fluctuate as a whole, so chi_4 grows with the size of the
+/// cooperatively moving regions, and peaks near the structural relaxation time.
+///
+/// Displacements are unwrapped, like in the `MsdMonitor`, which requires that no particle moves more than half the box
+/// between steps. Lags assume that the snapshots are evenly spaced in time. Particles can be added or removed:
+/// snapshots are compared by the particles' unique ids, over the particles present in both, and N is the mean number
+/// of particles compared at each lag.
+pub struct SusceptibilityMonitor {
+    /// The overlap length a. A particle that moved less than this over a lag still overlaps with where it was.
+    pub overlap_length: f64,
+
+    /// The times at which snapshots are taken.
+    pub times: Vec<f64>,
+    /// The unwrapped displacement of every particle at each time slice, from where it was when the monitor first ran.
+    pub displacements: Vec<Vec<Vector>>,
+    /// The unique ids of the particles at each time slice, in the same order as their displacements.
+    pub uids: Vec<Vec<u64>>,
+
+    /// Time between snapshots being take.
+    pub snapshot_delay: f64,
+
+    /// The last time at which a snapshot was taken.
+    last_snapshot_time: Option<f64>,
+
+    /// Unwraps the displacements of the particles.
+    unwrapper: Unwrapper,
+}
+
+impl SusceptibilityMonitor {
+    pub fn new(overlap_length: f64, snapshot_delay: f64) -> SusceptibilityMonitor {
+        SusceptibilityMonitor {
+            overlap_length,
+            times: vec![],
+            displacements: vec![],
+            uids: vec![],
+            snapshot_delay,
+            last_snapshot_time: None,
+            unwrapper: Unwrapper::new(),
+        }
+    }
+
+    /// The self-overlap Q between two snapshots, over the particles present in both, and the number of those
+    /// particles.
+    fn overlap(&self, start: usize, end: usize) -> (f64, usize) {
+        let a_sqr = self.overlap_length * self.overlap_length;
+        let (from, to) = (&self.displacements[start], &self.displacements[end]);
+        let pairs: Vec<(Vector, Vector)> = if self.uids[start] == self.uids[end] {
+            from.iter().copied().zip(to.iter().copied()).collect()
+        } else {
+            let to_by_uid: HashMap<u64, Vector> = self.uids[end].iter().copied().zip(to.iter().copied()).collect();
+            self.uids[start]
+                .iter()
+                .zip(from.iter())
+                .filter_map(|(uid, &d1)| Some((d1, *to_by_uid.get(uid)?)))
+                .collect()
+        };
+        if pairs.is_empty() {
+            return (0.0, 0);
+        }
+        let overlapping = pairs.iter().filter(|(d1, d2)| (*d2 - *d1).length_sqr() < a_sqr).count();
+        (overlapping as f64 / pairs.len() as f64, pairs.len())
+    }
+
+    /// The four-point susceptibility, as (lag time, chi_4) pairs, for every lag that has at least two time origins.
+    pub fn chi4(&self) -> Vec<(f64, f64)> {
+        let num_snapshots = self.displacements.len();
+        if num_snapshots < 2 {
+            return vec![];
+        }
+        (0..num_snapshots - 1)
+            .map(|lag| {
+                let (overlaps, counts): (Vec<f64>, Vec<usize>) =
+                    (0..num_snapshots - lag).map(|start| self.overlap(start, start + lag)).unzip();
+                let num_origins = overlaps.len() as f64;
+                let n = counts.iter().sum::<usize>() as f64 / num_origins;
+                let mean = overlaps.iter().sum::<f64>() / num_origins;
+                let mean_sqr = overlaps.iter().map(|q| q * q).sum::<f64>() / num_origins;
+                (self.times[lag] - self.times[0], n * (mean_sqr - mean * mean))
+            })
+            .collect()
+    }
+}
+
+impl Monitor for SusceptibilityMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        let unwrapped = self.unwrapper.update(sim_data);
+
+        if self.last_snapshot_time.is_none()
+            || self.snapshot_delay < sim_data.simulation_time - self.last_snapshot_time.unwrap() {
+            self.times.push(sim_data.simulation_time);
+            self.displacements.push(unwrapped.to_vec());
+            self.uids.push((0..sim_data.num_particles()).map(|i| sim_data.uid_of(i)).collect());
+
+            self.last_snapshot_time = Some(sim_data.simulation_time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::utils::rng::SimRng;
+
+    /// Random walks of 500 particles over 200 snapshots. If `collective`, all the particles take the same steps.
+    fn random_walks(collective: bool) -> SusceptibilityMonitor {
+        let mut rng = SimRng::new(4);
+        let mut monitor = SusceptibilityMonitor::new(0.5, 1.0);
+        let mut positions = vec![Vector::zero(); 500];
+        for snapshot in 0..200 {
+            monitor.times.push(snapshot as f64);
+            monitor.displacements.push(positions.clone());
+            monitor.uids.push((0..500).collect());
+            let shared = Vector::new(rng.gaussian(0.0, 0.1), rng.gaussian(0.0, 0.1));
+            for p in positions.iter_mut() {
+                *p += if collective { shared } else { Vector::new(rng.gaussian(0.0, 0.1), rng.gaussian(0.0, 0.1)) };
+            }
+        }
+        monitor
+    }
+
+    #[test]
+    fn test_particles_removed_between_steps() {
+        let particles: Vec<Particle> = (0..4).map(|i| Particle::new().with_coords(i as f64, 1.0).to_owned()).collect();
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 4.0, 0.0, 4.0)), &particles);
+        let mut monitor = SusceptibilityMonitor::new(0.5, 0.0);
+        monitor.post_step(&sim_data);
+        sim_data.remove_particle(1);
+        sim_data.simulation_time = 1.0;
+        monitor.post_step(&sim_data);
+
+        assert_eq!(monitor.displacements.len(), 2);
+        assert_eq!(monitor.displacements[1].len(), 3);
+        assert!(monitor.displacements[1].iter().all(|d| d.length() == 0.0));
+    }
+
+    #[test]
+    fn test_chi4_after_removal() {
+        // Only the first particle moves, by more than the overlap length.
+        let particles: Vec<Particle> = (0..4).map(|i| Particle::new().with_coords(i as f64, 1.0).to_owned()).collect();
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 4.0, 0.0, 4.0)), &particles);
+        let mut monitor = SusceptibilityMonitor::new(0.5, 0.0);
+        monitor.post_step(&sim_data);
+        sim_data.positions[0].y += 1.0;
+        sim_data.simulation_time = 1.0;
+        monitor.post_step(&sim_data);
+        // Removing the second particle moves the last one into its place.
+        sim_data.remove_particle(1);
+        sim_data.positions[0].y += 1.0;
+        sim_data.simulation_time = 2.0;
+        monitor.post_step(&sim_data);
+
+        // Comparing by index would count the removed particle's replacement, and divide by the wrong count.
+        assert_eq!(monitor.overlap(1, 2), (2.0 / 3.0, 3));
+        assert_eq!(monitor.overlap(0, 2), (2.0 / 3.0, 3));
+        assert_eq!(monitor.overlap(0, 1), (0.75, 4));
+
+        // At lag one, Q is 3/4 and 2/3, over 4 and 3 particles.
+        let chi4 = monitor.chi4();
+        assert_eq!(chi4.len(), 2);
+        let (mean, mean_sqr) = ((0.75 + 2.0 / 3.0) / 2.0, (0.75f64.powi(2) + (2.0f64 / 3.0).powi(2)) / 2.0);
+        assert_eq!(chi4[1].0, 1.0);
+        assert_close!(chi4[1].1, 3.5 * (mean_sqr - mean * mean), 1.0e-12);
+    }
+
+    #[test]
+    fn test_chi4_of_independent_particles() {
+        let chi4 = random_walks(false).chi4();
+        assert_eq!(chi4.len(), 199);
+        assert_eq!(chi4[0], (0.0, 0.0));
+        // Each particle overlaps independently, so chi_4 is at most the variance of a single particle's overlap, 1/4.
+        for &(_, chi) in chi4.iter().take(100) {
+            assert!(chi < 0.5);
+        }
+
+        // When all the particles move together, the overlap fluctuates as a whole, and chi_4 is of order N.
+        let collective = random_walks(true).chi4();
+        assert!(50.0 < collective.iter().take(100).map(|&(_, chi)| chi).fold(0.0, f64::max));
+    }
+}
@@ -0,0 +1,145 @@
+use std::any::Any;
+use crate::core::monitor::Monitor;
+use crate::core::simdata::SimData;
+
+/// Records the kinetic temperature over time, and estimates its mean with an error bar by block averaging: the
+/// samples are split into consecutive blocks, and the spread of the block means gives the standard error. Blocks that
+/// are long compared to the correlation time of the samples make the block means independent, so the error is not
+/// underestimated the way it is by treating every (correlated) sample as independent.
+pub struct TemperatureMonitor {
+    /// The times at which snapshots are taken.
+    pub times: Vec<f64>,
+    /// The temperature at each time slice.
+    pub temperatures: Vec<f64>,
+
+    /// The number of consecutive samples that are averaged into each block. This is never zero.
+    block_size: usize,
+
+    /// Time between snapshots being take.
+    pub snapshot_delay: f64,
+
+    /// The last time at which a snapshot was taken.
+    last_snapshot_time: Option<f64>,
+}
+
+impl TemperatureMonitor {
+    /// Create a temperature monitor that averages blocks of `block_size` samples. Panics if the block size is zero.
+    pub fn new(snapshot_delay: f64, block_size: usize) -> TemperatureMonitor {
+        if block_size == 0 {
+            panic!("block size must be at least one sample");
+        }
+        TemperatureMonitor { times: vec![], temperatures: vec![], block_size, snapshot_delay, last_snapshot_time: None }
+    }
+
+    /// The number of consecutive samples that are averaged into each block.
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// The mean temperature of each complete block. Samples at the end that do not fill a block are not used.
+    pub fn block_averages(&self) -> Vec<f64> {
+        self.temperatures
+            .chunks_exact(self.block_size)
+            .map(|block| block.iter().sum::<f64>() / self.block_size as f64)
+            .collect()
+    }
+
+    /// The mean temperature over all complete blocks, and its standard error, estimated from the spread of the block
+    /// averages. The error is infinite if there are fewer than two blocks, and the mean is NaN if there are none.
+    pub fn mean_and_error(&self) -> (f64, f64) {
+        let blocks = self.block_averages();
+        let n = blocks.len() as f64;
+        let mean = blocks.iter().sum::<f64>() / n;
+        if blocks.len() < 2 {
+            return (mean, f64::INFINITY);
+        }
+        let variance = blocks.iter().map(|b| (b - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        (mean, (variance / n).sqrt())
+    }
+}
+
+impl Monitor for TemperatureMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        if self.last_snapshot_time.is_none()
+            || self.snapshot_delay < sim_data.simulation_time - self.last_snapshot_time.unwrap() {
+            self.temperatures.push(sim_data.temperature());
+            self.times.push(sim_data.simulation_time);
+
+            self.last_snapshot_time = Some(sim_data.simulation_time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::universe::Universe;
+    use crate::utils::rng::SimRng;
+
+    #[test]
+    fn test_block_averaging() {
+        // A noisy temperature signal around 2.
+        let mut rng = SimRng::new(3);
+        let mut monitor = TemperatureMonitor::new(0.0, 50);
+        monitor.temperatures = (0..500).map(|_| rng.gaussian(2.0, 0.5)).collect();
+        let (few_mean, few_error) = monitor.mean_and_error();
+        assert_eq!(monitor.block_averages().len(), 10);
+
+        monitor.temperatures.extend((0..7500).map(|_| rng.gaussian(2.0, 0.5)));
+        let (many_mean, many_error) = monitor.mean_and_error();
+        assert_eq!(monitor.block_averages().len(), 160);
+
+        // The error bars cover the true mean, and shrink like one over the square root of the number of blocks.
+        assert!((few_mean - 2.0).abs() < 3.0 * few_error);
+        assert!((many_mean - 2.0).abs() < 3.0 * many_error);
+        assert!(many_error < few_error);
+        assert_close!(many_error, 0.5 / f64::sqrt(8000.0), 2.0e-3);
+    }
+
+    #[test]
+    #[should_panic(expected = "block size must be at least one sample")]
+    fn test_empty_blocks_are_rejected() {
+        TemperatureMonitor::new(0.0, 0);
+    }
+
+    #[test]
+    fn test_no_complete_blocks() {
+        let mut monitor = TemperatureMonitor::new(0.0, 10);
+        assert_eq!(monitor.block_size(), 10);
+        monitor.temperatures = vec![1.0; 9];
+        let (mean, error) = monitor.mean_and_error();
+        assert!(mean.is_nan());
+        assert_eq!(error, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_records_temperature() {
+        let mut universe = Universe::new(Bounds::from((0.0, 10.0, 0.0, 10.0)));
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(2.0, 5.0).with_radius(0.1).with_velocity_components(1.0, 0.0).to_owned(),
+            Particle::new().with_coords(7.0, 5.0).with_radius(0.1).with_velocity_components(0.0, 3.0).to_owned(),
+        ]);
+        universe.add_monitor("Temperature", Box::new(TemperatureMonitor::new(0.0, 10)));
+        universe.run_for_iterations(100);
+
+        let monitor =
+            universe.get_monitor("Temperature").unwrap().as_any().downcast_ref::<TemperatureMonitor>().unwrap();
+        assert_eq!(monitor.block_averages().len(), 10);
+        // Free particles keep their kinetic energy, so there is no spread between blocks.
+        let (mean, error) = monitor.mean_and_error();
+        assert_close!(mean, 2.5, 1.0e-12);
+        assert_close!(error, 0.0, 1.0e-12);
+    }
+}
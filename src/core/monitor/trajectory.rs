@@ -0,0 +1,227 @@
+use std::any::Any;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, Write};
+use crate::core::monitor::Monitor;
+use crate::core::simdata::SimData;
+use crate::core::vector::{Position, Vector, Velocity};
+
+/// Streams the positions and velocities of the particles to a binary file as the simulation runs, so long runs do
+/// not have to keep the whole trajectory in memory. Use a `TrajectoryReader` to read the frames back.
+///
+/// Each frame is written as little endian values: the time (f64), the number of particles (u64), then x, y, vx, vy
+/// (f64) for each particle.
+pub struct StreamingTrajectoryMonitor {
+    writer: BufWriter<File>,
+
+    /// The number of frames written so far.
+    pub num_frames: usize,
+
+    /// Time between snapshots being take.
+    pub snapshot_delay: f64,
+
+    /// The last time at which a snapshot was taken.
+    last_snapshot_time: Option<f64>,
+
+    /// The first error that happened while writing, after which no more frames are written.
+    error: Option<io::Error>,
+}
+
+impl StreamingTrajectoryMonitor {
+    /// Create a monitor that writes to a file, which is created or truncated.
+    pub fn new(path: &str, snapshot_delay: f64) -> io::Result<StreamingTrajectoryMonitor> {
+        Ok(StreamingTrajectoryMonitor {
+            writer: BufWriter::new(File::create(path)?),
+            num_frames: 0,
+            snapshot_delay,
+            last_snapshot_time: None,
+            error: None,
+        })
+    }
+
+    /// The error that stopped the monitor from writing frames, if there was one.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Write any buffered frames to the file. This also happens when the monitor is dropped, but errors are then
+    /// ignored.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn write_frame(&mut self, sim_data: &SimData) -> io::Result<()> {
+        self.writer.write_all(&sim_data.simulation_time.to_le_bytes())?;
+        self.writer.write_all(&(sim_data.num_particles() as u64).to_le_bytes())?;
+        for i in 0..sim_data.num_particles() {
+            let (p, v) = (sim_data.positions[i], sim_data.velocities[i]);
+            for value in [p.x, p.y, v.x, v.y] {
+                self.writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Monitor for StreamingTrajectoryMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        if self.error.is_some() {
+            return;
+        }
+        if self.last_snapshot_time.is_none()
+            || self.snapshot_delay < sim_data.simulation_time - self.last_snapshot_time.unwrap() {
+            match self.write_frame(sim_data) {
+                Ok(()) => self.num_frames += 1,
+                Err(error) => self.error = Some(error),
+            }
+
+            self.last_snapshot_time = Some(sim_data.simulation_time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// A single frame of a trajectory written by a `StreamingTrajectoryMonitor`.
+pub struct TrajectoryFrame {
+    pub time: f64,
+    pub positions: Vec<Position>,
+    pub velocities: Vec<Velocity>,
+}
+
+/// The number of bytes each particle takes up in a frame: x, y, vx, vy.
+const BYTES_PER_PARTICLE: u64 = 32;
+
+/// Reads the frames of a trajectory written by a `StreamingTrajectoryMonitor` one at a time.
+pub struct TrajectoryReader {
+    reader: BufReader<File>,
+
+    /// The length of the file, in bytes.
+    file_length: u64,
+}
+
+impl TrajectoryReader {
+    pub fn open(path: &str) -> io::Result<TrajectoryReader> {
+        let file = File::open(path)?;
+        let file_length = file.metadata()?.len();
+        Ok(TrajectoryReader { reader: BufReader::new(file), file_length })
+    }
+
+    fn read_f64(&mut self) -> io::Result<f64> {
+        let mut bytes = [0u8; 8];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    fn read_frame(&mut self) -> io::Result<TrajectoryFrame> {
+        let time = self.read_f64()?;
+        let mut bytes = [0u8; 8];
+        self.reader.read_exact(&mut bytes)?;
+        let num_particles = u64::from_le_bytes(bytes);
+
+        // Check the particle count against what is left of the file before reserving space for the particles, so a
+        // corrupt count is an error rather than a huge allocation.
+        let remaining = self.file_length.saturating_sub(self.reader.stream_position()?);
+        if remaining / BYTES_PER_PARTICLE < num_particles {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the frame at t = {} has {} particles, but only {} bytes are left",
+                    time, num_particles, remaining
+                ),
+            ));
+        }
+        let num_particles = num_particles as usize;
+
+        let mut positions = Vec::with_capacity(num_particles);
+        let mut velocities = Vec::with_capacity(num_particles);
+        for _ in 0..num_particles {
+            positions.push(Vector::new(self.read_f64()?, self.read_f64()?));
+            velocities.push(Vector::new(self.read_f64()?, self.read_f64()?));
+        }
+        Ok(TrajectoryFrame { time, positions, velocities })
+    }
+}
+
+impl Iterator for TrajectoryReader {
+    type Item = io::Result<TrajectoryFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The trajectory ends cleanly if there is no data left at the start of a frame.
+        match self.reader.fill_buf() {
+            Ok([]) => None,
+            Ok(_) => Some(self.read_frame()),
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::universe::Universe;
+
+    #[test]
+    fn test_stream_and_read_trajectory() {
+        let path = std::env::temp_dir().join("rust_md_test_trajectory.bin");
+        let path = path.to_str().unwrap();
+
+        let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 10.0, 0.0, 10.0)), 0.01);
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(1.0, 2.0).with_radius(0.3).with_velocity_components(1.0, 0.5).to_owned(),
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.3).with_velocity_components(-0.5, 0.0).to_owned(),
+        ]);
+        let mut monitor = StreamingTrajectoryMonitor::new(path, 0.0).unwrap();
+
+        // Step the universe, streaming every step and keeping a copy of the expected frames.
+        let mut expected = vec![];
+        for _ in 0..25 {
+            universe.run_for_iterations(1);
+            monitor.post_step(&universe.sim_data);
+            expected.push((
+                universe.sim_data.simulation_time,
+                universe.sim_data.positions.clone(),
+                universe.sim_data.velocities.clone(),
+            ));
+        }
+        assert_eq!(monitor.num_frames, 25);
+        monitor.flush().unwrap();
+        assert!(monitor.error().is_none());
+
+        let frames: Vec<TrajectoryFrame> = TrajectoryReader::open(path).unwrap().map(|f| f.unwrap()).collect();
+        assert_eq!(frames.len(), expected.len());
+        for (frame, (time, positions, velocities)) in frames.iter().zip(expected.iter()) {
+            assert_eq!(frame.time, *time);
+            for i in 0..2 {
+                assert_eq!((frame.positions[i].x, frame.positions[i].y), (positions[i].x, positions[i].y));
+                assert_eq!((frame.velocities[i].x, frame.velocities[i].y), (velocities[i].x, velocities[i].y));
+            }
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_truncated_trajectory() {
+        let path = std::env::temp_dir().join("rust_md_test_truncated_trajectory.bin");
+        let path = path.to_str().unwrap();
+
+        // A frame that claims far more particles than the file holds.
+        let mut bytes = 1.0f64.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&(u64::MAX / 2).to_le_bytes());
+        bytes.extend_from_slice(&[0u8; 64]);
+        std::fs::write(path, bytes).unwrap();
+
+        let mut reader = TrajectoryReader::open(path).unwrap();
+        let error = reader.next().unwrap().err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(path).unwrap();
+    }
+}
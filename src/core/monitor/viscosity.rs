@@ -0,0 +1,131 @@
+use std::any::Any;
+use crate::analysis::stress_tensor;
+use crate::core::force::Force;
+use crate::core::monitor::Monitor;
+use crate::core::simdata::SimData;
+
+/// Records the off-diagonal element of the pressure tensor, sigma_xy, and computes its autocorrelation
+/// <sigma_xy(0) sigma_xy(t)>. Through the Green-Kubo relation, the shear viscosity is eta = (A / T) times the time
+/// integral of the autocorrelation, where A is the area of the box and T the temperature.
+///
+/// The autocorrelation assumes that the snapshots are evenly spaced in time.
+pub struct StressAutocorrelationMonitor {
+    /// The pairwise force between the particles.
+    force: Box<dyn Force>,
+
+    /// The times at which snapshots are taken.
+    pub times: Vec<f64>,
+    /// The shear stress, sigma_xy, at each time slice.
+    pub stresses: Vec<f64>,
+
+    /// Time between snapshots being take.
+    pub snapshot_delay: f64,
+
+    /// The last time at which a snapshot was taken.
+    last_snapshot_time: Option<f64>,
+}
+
+impl StressAutocorrelationMonitor {
+    pub fn new(force: Box<dyn Force>, snapshot_delay: f64) -> StressAutocorrelationMonitor {
+        StressAutocorrelationMonitor {
+            force,
+            times: vec![],
+            stresses: vec![],
+            snapshot_delay,
+            last_snapshot_time: None,
+        }
+    }
+
+    /// The autocorrelation of the shear stress, as (time lag, correlation) pairs, for lags of up to `max_lag`
+    /// snapshots. Each lag is averaged over every pair of snapshots that far apart, so long lags are noisier.
+    pub fn autocorrelation(&self, max_lag: usize) -> Vec<(f64, f64)> {
+        let n = self.stresses.len();
+        (0..usize::min(max_lag + 1, n))
+            .map(|lag| {
+                let sum: f64 = self.stresses.iter().zip(self.stresses[lag..].iter()).map(|(a, b)| a * b).sum();
+                (self.times[lag] - self.times[0], sum / (n - lag) as f64)
+            })
+            .collect()
+    }
+
+    /// The time integral of the autocorrelation up to `max_lag` snapshots, by the trapezoidal rule. Multiplying by
+    /// A / T gives the shear viscosity.
+    pub fn autocorrelation_integral(&self, max_lag: usize) -> f64 {
+        self.autocorrelation(max_lag)
+            .windows(2)
+            .map(|pair| 0.5 * (pair[1].0 - pair[0].0) * (pair[0].1 + pair[1].1))
+            .sum()
+    }
+}
+
+impl Monitor for StressAutocorrelationMonitor {
+    fn post_step(&mut self, sim_data: &SimData) {
+        if self.last_snapshot_time.is_none()
+            || self.snapshot_delay < sim_data.simulation_time - self.last_snapshot_time.unwrap() {
+            self.stresses.push(stress_tensor(sim_data, self.force.as_ref())[0][1]);
+            self.times.push(sim_data.simulation_time);
+
+            self.last_snapshot_time = Some(sim_data.simulation_time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::force::HardSphereForce;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+    use crate::core::universe::Universe;
+    use crate::utils::rng::SimRng;
+
+    #[test]
+    fn test_autocorrelation_of_exponentially_correlated_stress() {
+        // An Ornstein-Uhlenbeck process, whose autocorrelation is sigma^2 exp(-t / tau), with integral sigma^2 tau.
+        let (sigma, tau, dt) = (2.0, 0.1, 0.01);
+        let decay = f64::exp(-dt / tau);
+        let mut rng = SimRng::new(11);
+        let mut monitor = StressAutocorrelationMonitor::new(Box::new(HardSphereForce::new(1.0)), dt);
+        let mut stress = rng.gaussian(0.0, sigma);
+        for i in 0..200_000 {
+            monitor.times.push(i as f64 * dt);
+            monitor.stresses.push(stress);
+            stress = decay * stress + rng.gaussian(0.0, sigma * f64::sqrt(1.0 - decay * decay));
+        }
+
+        let correlation = monitor.autocorrelation(100);
+        assert_eq!(correlation.len(), 101);
+        for &lag in &[0, 5, 10, 20] {
+            let (t, c) = correlation[lag];
+            assert_close!(t, lag as f64 * dt, 1.0e-9);
+            assert_close!(c, sigma * sigma * f64::exp(-t / tau), 0.05 * sigma * sigma);
+        }
+        assert_close!(monitor.autocorrelation_integral(100), sigma * sigma * tau, 0.1 * sigma * sigma * tau);
+    }
+
+    #[test]
+    fn test_records_shear_stress() {
+        let mut universe = Universe::new(Bounds::from((0.0, 10.0, 0.0, 10.0)));
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(2.0, 2.0).with_radius(0.1).with_velocity_components(1.0, 1.0).to_owned(),
+        ]);
+        let monitor = StressAutocorrelationMonitor::new(Box::new(HardSphereForce::new(1.0)), 0.01);
+        universe.add_monitor("Stress", Box::new(monitor));
+        universe.run_until(0.05);
+
+        let monitor = universe.get_monitor("Stress").unwrap().as_any().downcast_ref::<StressAutocorrelationMonitor>();
+        let monitor = monitor.unwrap();
+        assert!(3 <= monitor.stresses.len());
+        assert!(monitor.stresses.iter().all(|&s| (s - 0.01).abs() < 1.0e-12));
+    }
+}
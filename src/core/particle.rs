@@ -9,6 +9,9 @@ pub struct Particle {
     pub velocity: Velocity,
 
     pub force: Force,
+
+    /// The type, or species, of the particle, e.g. to tell the components of a mixture apart.
+    pub particle_type: usize,
 }
 
 impl Particle {
@@ -20,6 +23,7 @@ impl Particle {
             mass: 1.,
             velocity: Vector::zero(),
             force: Vector::zero(),
+            particle_type: 0,
         }
     }
 
@@ -61,14 +65,26 @@ impl Particle {
         self
     }
 
+    /// Set the type of a particle. Allows for chaining.
+    pub fn with_type(&mut self, particle_type: usize) -> &mut Self {
+        self.particle_type = particle_type;
+        self
+    }
+
+    /// Set the mass of a particle from its density and current radius. Allows for chaining.
     pub fn with_density(&mut self, density: f64) -> &mut Self {
         let area = std::f64::consts::PI * self.radius * self.radius;
-        let mut p = *self;
         self.mass = density * area;
         self
     }
 }
 
+impl Default for Particle {
+    fn default() -> Self {
+        Particle::new()
+    }
+}
+
 // =================================================================================================
 //  Unit Tests.
 // =================================================================================================
@@ -83,7 +99,8 @@ mod tests {
         let particle = Particle::new()
             .with_radius(2.34)
             .with_velocity(Velocity::new(1.2, 2.5))
-            .with_mass(1.77);
+            .with_mass(1.77)
+            .to_owned();
         assert_eq!(particle.radius, 2.34);
         assert_eq!(particle.velocity.x, 1.2);
         assert_eq!(particle.velocity.y, 2.5);
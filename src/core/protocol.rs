@@ -0,0 +1,20 @@
+/// A step of a simulation protocol, see `Universe::execute`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProtocolStep {
+    /// Relax the particles with an overdamped integrator for a time, see `Universe::relax_for`. The particles are left
+    /// at rest.
+    Relax(f64),
+    /// Run the simulation for a time.
+    Run(f64),
+    /// Rescale the velocities of the particles so that the kinetic temperature is exactly this value.
+    SetTemperature(f64),
+    /// Scale the box and the positions of the particles by factors in x and y.
+    ScaleBox(f64, f64),
+    /// Run the simulation for a time, while linearly ramping the temperature from its current value down (or up) to
+    /// a target, by periodically rescaling the velocities.
+    Quench { temperature: f64, time: f64 },
+}
+
+/// A reusable recipe for a simulation, e.g. relaxing, heating, and then compressing, which `Universe::execute` runs in
+/// order.
+pub type Protocol = Vec<ProtocolStep>;
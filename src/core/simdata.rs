@@ -1,5 +1,11 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use crate::core::force;
 use crate::core::particle::Particle;
 use crate::core::vector::{Force, Position, Vector, Velocity};
+use crate::core::verlet_lists::create_verlet_lists;
+use crate::utils::rng::SimRng;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Bounds {
@@ -11,6 +17,22 @@ pub struct Bounds {
 }
 
 impl Bounds {
+    /// Create a bounds object centered on a point, extending `half_width` to either side horizontally and
+    /// `half_height` to either side vertically.
+    pub fn from_center(center: Vector, half_width: f64, half_height: f64) -> Bounds {
+        Bounds {
+            xlo: center.x - half_width,
+            xhi: center.x + half_width,
+            ylo: center.y - half_height,
+            yhi: center.y + half_height,
+        }
+    }
+
+    /// The center of the region.
+    pub fn center(&self) -> Position {
+        Position::new(0.5 * (self.xlo + self.xhi), 0.5 * (self.ylo + self.yhi))
+    }
+
     /// The width of the region represented by the SimData.
     pub fn width(&self) -> f64 {
         self.xhi - self.xlo
@@ -21,6 +43,22 @@ impl Bounds {
         self.yhi - self.ylo
     }
 
+    /// Get the shortest periodic image of a displacement vector, treating the region as periodic.
+    pub fn minimum_image(&self, displacement: Vector) -> Vector {
+        let mut displacement = displacement;
+        if 0.5 * self.width() < displacement.x {
+            displacement.x -= self.width();
+        } else if displacement.x < -0.5 * self.width() {
+            displacement.x += self.width();
+        }
+        if 0.5 * self.height() < displacement.y {
+            displacement.y -= self.height();
+        } else if displacement.y < -0.5 * self.height() {
+            displacement.y += self.height();
+        }
+        displacement
+    }
+
     /// Checks whether a position falls within the bounds.
     pub fn is_in_bounds(&self, position: Position) -> bool {
         self.xlo <= position.x
@@ -37,7 +75,23 @@ impl From<(f64, f64, f64, f64)> for Bounds {
     }
 }
 
+/// The physical size of the simulation's units, for converting results to real-world units. Each scale is the size of
+/// one simulation unit in physical units, e.g. a time scale of 2 means one unit of simulation time is 2 seconds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Units {
+    pub length_scale: f64,
+    pub time_scale: f64,
+    pub energy_scale: f64,
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Units { length_scale: 1.0, time_scale: 1.0, energy_scale: 1.0 }
+    }
+}
+
 /// Object that stores the fundamental data of the simulation.
+#[derive(Clone)]
 pub struct SimData {
     /// The radius of each particle.
     pub radii: Vec<f64>,
@@ -54,6 +108,13 @@ pub struct SimData {
     /// Buffer to accumulate the force on each particle.
     pub forces: Vec<Force>,
 
+    /// Whether each particle is frozen. Frozen particles still exert and feel forces, but the integrators do not move
+    /// them.
+    pub frozen: Vec<bool>,
+
+    /// The type, or species, of each particle.
+    pub types: Vec<usize>,
+
     /// The bounds of the SimData region.
     pub bounds: Bounds,
 
@@ -62,6 +123,25 @@ pub struct SimData {
 
     /// The current simulation time.
     pub simulation_time: f64,
+
+    /// If set, the physical units that the simulation's units correspond to.
+    pub units: Option<Units>,
+
+    /// The unique id of each particle. Unlike the index of a particle, its unique id does not change when other
+    /// particles are removed.
+    uids: Vec<u64>,
+
+    /// Map from unique id to the index of the particle.
+    uid_indices: HashMap<u64, usize>,
+
+    /// The unique id that will be given to the next particle that is added.
+    next_uid: u64,
+
+    /// The center of mass velocity removed by `to_com_frame`, which `from_com_frame` adds back.
+    com_frame_velocity: Velocity,
+
+    /// If set, the y coordinate that every particle is held at, turning the simulation into a 1D one.
+    locked_y: Option<f64>,
 }
 
 impl From<Bounds> for SimData {
@@ -79,18 +159,68 @@ impl SimData {
             positions: Vec::new(),
             velocities: Vec::new(),
             forces: Vec::new(),
+            frozen: Vec::new(),
+            types: Vec::new(),
             bounds: Bounds { xlo, xhi, ylo, yhi },
             topology: Box::new(HarmonicTopology{ wrap_x: true, wrap_y: true }),
-            simulation_time: 0.0
+            simulation_time: 0.0,
+            units: None,
+            uids: Vec::new(),
+            uid_indices: HashMap::new(),
+            next_uid: 0,
+            com_frame_velocity: Vector::zero(),
+            locked_y: None,
         }
     }
 
-    pub fn new_with_particles(bounds: Bounds, particles: &Vec<Particle>) -> Self {
+    pub fn new_with_particles(bounds: Bounds, particles: &[Particle]) -> Self {
         let mut sim_data = SimData::from(bounds);
         sim_data.add_particles(particles);
         sim_data
     }
 
+    /// Read a SimData from a CSV file with the header `x,y,vx,vy,radius,mass`, with one particle per row. The
+    /// bounds are fitted to just contain all the particles.
+    pub fn from_csv(path: &str) -> io::Result<SimData> {
+        let particles = read_particles_csv(path)?;
+        if particles.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "cannot fit bounds to a CSV file with no particles"));
+        }
+
+        let mut bounds = Bounds { xlo: f64::MAX, xhi: f64::MIN, ylo: f64::MAX, yhi: f64::MIN };
+        for p in particles.iter() {
+            bounds.xlo = f64::min(bounds.xlo, p.position.x - p.radius);
+            bounds.xhi = f64::max(bounds.xhi, p.position.x + p.radius);
+            bounds.ylo = f64::min(bounds.ylo, p.position.y - p.radius);
+            bounds.yhi = f64::max(bounds.yhi, p.position.y + p.radius);
+        }
+        Ok(SimData::new_with_particles(bounds, &particles))
+    }
+
+    /// Read a SimData with the specified bounds from a CSV file with the header `x,y,vx,vy,radius,mass`, with one
+    /// particle per row.
+    pub fn from_csv_with_bounds(path: &str, bounds: Bounds) -> io::Result<SimData> {
+        let particles = read_particles_csv(path)?;
+        Ok(SimData::new_with_particles(bounds, &particles))
+    }
+
+    /// Write the positions, velocities, and radii of the particles as NumPy arrays, to `{prefix}_positions.npy` and
+    /// `{prefix}_velocities.npy`, with shape (N, 2), and `{prefix}_radii.npy`, with shape (N,). They can be read with
+    /// `numpy.load`.
+    pub fn write_npy(&self, prefix: &str) -> io::Result<()> {
+        let n = self.num_particles();
+        let positions: Vec<f64> = self.positions.iter().flat_map(|p| [p.x, p.y]).collect();
+        let velocities: Vec<f64> = self.velocities.iter().flat_map(|v| [v.x, v.y]).collect();
+        write_npy_array(&format!("{}_positions.npy", prefix), &positions, &[n, 2])?;
+        write_npy_array(&format!("{}_velocities.npy", prefix), &velocities, &[n, 2])?;
+        write_npy_array(&format!("{}_radii.npy", prefix), &self.radii, &[n])
+    }
+
+    /// The simulation time in physical units. Without unit metadata, this is just the simulation time.
+    pub fn physical_time(&self) -> f64 {
+        self.simulation_time * self.units.unwrap_or_default().time_scale
+    }
+
     /// Return the number of particles.
     pub fn num_particles(&self) -> usize {
         self.radii.len()
@@ -111,6 +241,34 @@ impl SimData {
         self.bounds.height()
     }
 
+    /// The fraction of the area of the bounds that is covered by particles.
+    pub fn area_fraction(&self) -> f64 {
+        let particle_area: f64 = self.radii.iter().map(|r| std::f64::consts::PI * r * r).sum();
+        particle_area / (self.width() * self.height())
+    }
+
+    /// Scale the bounds by factors of fx and fy about the center of the bounds, moving the particles' positions
+    /// along with it. Particle sizes are unchanged.
+    pub fn scale_box(&mut self, fx: f64, fy: f64) {
+        let center = self.bounds.center();
+        self.bounds = Bounds::from_center(center, 0.5 * fx * self.width(), 0.5 * fy * self.height());
+
+        for p in self.positions.iter_mut() {
+            p.x = center.x + fx * (p.x - center.x);
+            p.y = center.y + fy * (p.y - center.y);
+        }
+    }
+
+    /// Uniformly scale the box and the particles' positions so the area fraction is equal to the target. Particle
+    /// sizes are unchanged.
+    pub fn set_area_fraction(&mut self, target: f64) {
+        if target <= 0.0 {
+            panic!("target area fraction must be positive");
+        }
+        let factor = f64::sqrt(self.area_fraction() / target);
+        self.scale_box(factor, factor);
+    }
+
     /// Add a particle, represented by a particle structure, to the SimData.
     /// ```
     /// let mut particles: Vec<Particle> = Vec::new();
@@ -124,66 +282,555 @@ impl SimData {
     /// let mut sim_data = SimData::new(0.0, 10.0, 0.0, 10.0);
     /// sim_data.add_particles(particles);
     /// ```
-    pub fn add_particle(&mut self, particle: &Particle) -> &Self {
+    ///
+    /// Returns the unique id assigned to the particle.
+    pub fn add_particle(&mut self, particle: &Particle) -> u64 {
         self.radii.push(particle.radius);
         self.masses.push(particle.mass);
         self.positions.push(particle.position);
         self.velocities.push(particle.velocity);
         self.forces.push(particle.force);
-        self
+        self.frozen.push(false);
+        self.types.push(particle.particle_type);
+
+        let uid = self.next_uid;
+        self.next_uid += 1;
+        self.uids.push(uid);
+        self.uid_indices.insert(uid, self.uids.len() - 1);
+        uid
     }
 
     /// Add many particles to a SimData.
-    pub fn add_particles(&mut self, particles: &Vec<Particle>) {
+    pub fn add_particles(&mut self, particles: &[Particle]) {
         for p in particles.iter() {
-            self.radii.push(p.radius);
-            self.masses.push(p.mass);
-            self.positions.push(p.position);
-            self.velocities.push(p.velocity);
-            self.forces.push(Vector::zero());
+            self.add_particle(p);
         }
     }
 
-    /// Get the distance squared between two particles.
+    /// Remove the particle with the given index. The last particle is moved into its place, so the indices of other
+    /// particles may change, but their unique ids do not.
+    pub fn remove_particle(&mut self, index: usize) {
+        self.radii.swap_remove(index);
+        self.masses.swap_remove(index);
+        self.positions.swap_remove(index);
+        self.velocities.swap_remove(index);
+        self.forces.swap_remove(index);
+        self.frozen.swap_remove(index);
+        self.types.swap_remove(index);
+
+        let uid = self.uids.swap_remove(index);
+        self.uid_indices.remove(&uid);
+        if index < self.uids.len() {
+            self.uid_indices.insert(self.uids[index], index);
+        }
+    }
+
+    /// Create a new SimData containing copies of only the particles whose positions fall within a region, with the
+    /// region as its bounds. The new SimData has the default topology, and its particles get new unique ids.
+    pub fn extract_region(&self, region: Bounds) -> SimData {
+        let mut sim_data = SimData::from(region);
+        sim_data.simulation_time = self.simulation_time;
+        for i in 0..self.num_particles() {
+            if region.is_in_bounds(self.positions[i]) {
+                sim_data.add_particle(&Particle {
+                    position: self.positions[i],
+                    radius: self.radii[i],
+                    mass: self.masses[i],
+                    velocity: self.velocities[i],
+                    force: self.forces[i],
+                    particle_type: self.types[i],
+                });
+                *sim_data.frozen.last_mut().unwrap() = self.frozen[i];
+            }
+        }
+        sim_data
+    }
+
+    /// Freeze every particle within `thickness` of any edge of the box, turning them into a rough, disordered wall.
+    /// The velocities of the frozen particles are set to zero.
+    pub fn freeze_boundary_layer(&mut self, thickness: f64) {
+        let b = self.bounds;
+        for i in 0..self.num_particles() {
+            let p = self.positions[i];
+            if p.x < b.xlo + thickness || b.xhi - thickness < p.x || p.y < b.ylo + thickness || b.yhi - thickness < p.y {
+                self.frozen[i] = true;
+                self.velocities[i] = Vector::zero();
+            }
+        }
+    }
+
+    /// Add a velocity of magnitude `speed`, directed away from `center`, to every particle. The direction uses the
+    /// minimum image of the displacement from the center. Particles exactly at the center, and frozen particles, are
+    /// not kicked.
+    pub fn radial_kick(&mut self, center: Position, speed: f64) {
+        for i in 0..self.num_particles() {
+            let displacement = self.minimum_image(self.positions[i] - center);
+            if self.frozen[i] || displacement.length_sqr() == 0.0 {
+                continue;
+            }
+            self.velocities[i] += Vector::normalize(displacement) * speed;
+        }
+    }
+
+    /// Set every particle's velocity to an affine flow, v = G . (r - c), where c is the center of the box and the
+    /// velocity gradient G is given row by row, so v_x = G[0][0] dx + G[0][1] dy. E.g. a gradient with only G[0][1]
+    /// set is a simple shear flow, an antisymmetric gradient is a rotation, and the identity is a uniform expansion.
+    pub fn set_affine_velocity(&mut self, gradient: [[f64; 2]; 2]) {
+        let center = self.bounds.center();
+        for i in 0..self.num_particles() {
+            let d = self.positions[i] - center;
+            self.velocities[i] = Velocity::new(
+                gradient[0][0] * d.x + gradient[0][1] * d.y,
+                gradient[1][0] * d.x + gradient[1][1] * d.y,
+            );
+        }
+    }
+
+    /// Re-thermalize the particles inside a region, e.g. as a heat pulse that sets up a thermal gradient, by drawing
+    /// new velocities for them from the Maxwell-Boltzmann distribution at `temperature`. Particles outside the region,
+    /// and frozen particles, keep their velocities.
+    pub fn heat_region(&mut self, region: Bounds, temperature: f64, seed: u64) {
+        let mut rng = SimRng::new(seed);
+        for i in 0..self.num_particles() {
+            if self.frozen[i] || !region.is_in_bounds(self.positions[i]) {
+                continue;
+            }
+            let std = f64::sqrt(temperature / self.masses[i]);
+            self.velocities[i] = Velocity::new(rng.gaussian(0.0, std), rng.gaussian(0.0, std));
+        }
+    }
+
+    /// The kinetic temperature, in units where Boltzmann's constant is 1. In two dimensions each particle has two
+    /// degrees of freedom, so the temperature is the mean kinetic energy per particle.
+    pub fn temperature(&self) -> f64 {
+        let kinetic: f64 = (0..self.num_particles())
+            .map(|i| 0.5 * self.masses[i] * self.velocities[i].length_sqr())
+            .sum();
+        kinetic / self.num_particles() as f64
+    }
+
+    /// Compute the (mean, RMS, kurtosis) of the distribution of particle speeds. The kurtosis is the fourth central
+    /// moment divided by the squared variance, so deviations from its Maxwell-Boltzmann value flag a system that is
+    /// not in equilibrium.
+    pub fn velocity_moments(&self) -> (f64, f64, f64) {
+        let n = self.num_particles() as f64;
+        let speeds: Vec<f64> = self.velocities.iter().map(|v| v.length()).collect();
+        let mean = speeds.iter().sum::<f64>() / n;
+        let rms = (speeds.iter().map(|s| s * s).sum::<f64>() / n).sqrt();
+        let variance = speeds.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+        let fourth_moment = speeds.iter().map(|s| (s - mean).powi(4)).sum::<f64>() / n;
+        (mean, rms, fourth_moment / (variance * variance))
+    }
+
+    /// Count the number of particles that each particle is in contact with, i.e. overlaps.
+    pub fn contact_numbers(&self) -> Vec<usize> {
+        let mut contacts = vec![0; self.num_particles()];
+        if self.is_empty() {
+            return contacts;
+        }
+        for (id1, id2) in &create_verlet_lists(self, 0.0) {
+            let sum_radii = self.radii[id1] + self.radii[id2];
+            if self.distance_sqr_between(id1, id2) < sum_radii * sum_radii {
+                contacts[id1] += 1;
+                contacts[id2] += 1;
+            }
+        }
+        contacts
+    }
+
+    /// Find every pair of overlapping particles, as (lower index, higher index, overlap), sorted by index. Useful for
+    /// validating an initial configuration before running, since large overlaps make the first steps explode.
+    pub fn check_overlaps(&self) -> Vec<(usize, usize, f64)> {
+        if self.is_empty() {
+            return vec![];
+        }
+        let mut overlaps: Vec<(usize, usize, f64)> = create_verlet_lists(self, 0.0)
+            .into_iter()
+            .filter_map(|(id1, id2)| {
+                let overlap = self.radii[id1] + self.radii[id2] - self.distance_sqr_between(id1, id2).sqrt();
+                (0.0 < overlap).then_some((usize::min(id1, id2), usize::max(id1, id2), overlap))
+            })
+            .collect();
+        overlaps.sort_by_key(|&(id1, id2, _)| (id1, id2));
+        overlaps
+    }
+
+    /// Geometrically relax overlaps, e.g. after random placement. In each iteration, every overlapping pair is pushed
+    /// apart along the line between their centers, each particle moving by half of the overlap. Frozen particles do
+    /// not move. Stops early once there are no overlaps left.
+    pub fn push_apart_overlaps(&mut self, iterations: usize) {
+        if self.is_empty() {
+            return;
+        }
+        for _ in 0..iterations {
+            let mut displacements = vec![Vector::zero(); self.num_particles()];
+            let mut any_overlap = false;
+            for (id1, id2) in &create_verlet_lists(self, 0.0) {
+                let displacement = self.displacement_between(id1, id2);
+                let distance = displacement.length();
+                let overlap = self.radii[id1] + self.radii[id2] - distance;
+                if overlap <= 0.0 || distance == 0.0 {
+                    continue;
+                }
+                any_overlap = true;
+                let push = displacement * (0.5 * overlap / distance);
+                displacements[id1] -= push;
+                displacements[id2] += push;
+            }
+            if !any_overlap {
+                return;
+            }
+            for (i, displacement) in displacements.into_iter().enumerate() {
+                if !self.frozen[i] {
+                    self.positions[i] += displacement;
+                }
+            }
+            self.canonical_positions();
+        }
+    }
+
+    /// Compute the magnitude of the net force on each particle due to a force, without changing the SimData's forces.
+    pub fn force_residuals(&self, force: &dyn force::Force) -> Vec<f64> {
+        let mut net_forces = vec![Vector::zero(); self.num_particles()];
+        if self.is_empty() {
+            return vec![];
+        }
+        // Include pairs somewhat beyond contact, for forces that have some range.
+        let max_radius = self.radii.iter().copied().fold(f64::NAN, f64::max);
+        for (id1, id2) in &create_verlet_lists(self, max_radius) {
+            let f = force.pair_force(self, id1, id2);
+            net_forces[id1] -= f;
+            net_forces[id2] += f;
+        }
+        net_forces.iter().map(|f| f.length()).collect()
+    }
+
+    /// Compute the potential energy of each particle due to a force, with the energy of each pair split evenly
+    /// between the two particles.
+    pub fn potential_energies(&self, force: &dyn force::Force) -> Vec<f64> {
+        let mut energies = vec![0.0; self.num_particles()];
+        if self.is_empty() {
+            return energies;
+        }
+        // Include pairs somewhat beyond contact, for forces that have some range.
+        let max_radius = self.radii.iter().copied().fold(f64::NAN, f64::max);
+        for (id1, id2) in &create_verlet_lists(self, max_radius) {
+            let u = force.pair_potential(self, id1, id2);
+            energies[id1] += 0.5 * u;
+            energies[id2] += 0.5 * u;
+        }
+        energies
+    }
+
+    /// Check whether the configuration is jammed: the mean number of contacts per particle must reach the isostatic
+    /// value for frictionless disks, Z = 4, and every particle must be in force balance, with a residual force
+    /// that is negligible compared to the mean contact force.
+    pub fn is_jammed(&self, force: &dyn force::Force) -> bool {
+        const ISOSTATIC_CONTACT_NUMBER: f64 = 4.0;
+        const FORCE_BALANCE_TOLERANCE: f64 = 1.0e-6;
+
+        if self.is_empty() {
+            return false;
+        }
+        let contacts = self.contact_numbers();
+        let mean_contacts = contacts.iter().sum::<usize>() as f64 / self.num_particles() as f64;
+        if mean_contacts < ISOSTATIC_CONTACT_NUMBER {
+            return false;
+        }
+
+        // Find the mean magnitude of the contact forces.
+        let (mut total_force, mut num_contacts) = (0.0, 0);
+        for (id1, id2) in &create_verlet_lists(self, 0.0) {
+            let f = force.pair_force(self, id1, id2).length();
+            if 0.0 < f {
+                total_force += f;
+                num_contacts += 1;
+            }
+        }
+        if num_contacts == 0 {
+            return false;
+        }
+        let mean_force = total_force / num_contacts as f64;
+
+        self.force_residuals(force).iter().all(|r| *r < FORCE_BALANCE_TOLERANCE * mean_force)
+    }
+
+    /// Get the current index of the particle with a unique id, if it still exists.
+    pub fn index_of(&self, uid: u64) -> Option<usize> {
+        self.uid_indices.get(&uid).copied()
+    }
+
+    /// Get the unique id of the particle at some index.
+    pub fn uid_of(&self, index: usize) -> u64 {
+        self.uids[index]
+    }
+
+    /// Get the distance squared between two particles, using the minimum image.
     pub fn distance_sqr_between(&self, id1: usize, id2: usize) -> f64 {
-        let r1 = self.positions[id1];
-        let r2 = self.positions[id2];
+        self.displacement_between(id1, id2).length_sqr()
+    }
+
+    /// The velocity of the center of mass of all the particles.
+    pub fn center_of_mass_velocity(&self) -> Velocity {
+        let mut momentum = Vector::zero();
+        let mut total_mass = 0.0;
+        for i in 0..self.num_particles() {
+            momentum += self.velocities[i] * self.masses[i];
+            total_mass += self.masses[i];
+        }
+        if total_mass == 0.0 { Vector::zero() } else { momentum / total_mass }
+    }
+
+    /// Subtract the center of mass velocity from every particle, so the total momentum is zero. Returns the velocity
+    /// that was removed.
+    pub fn zero_total_momentum(&mut self) -> Velocity {
+        let com_velocity = self.center_of_mass_velocity();
+        for v in self.velocities.iter_mut() {
+            *v -= com_velocity;
+        }
+        com_velocity
+    }
+
+    /// Move to the center of mass frame, like `zero_total_momentum`, but remember the removed velocity so that
+    /// `from_com_frame` can restore the original frame.
+    pub fn to_com_frame(&mut self) {
+        let com_velocity = self.zero_total_momentum();
+        self.com_frame_velocity += com_velocity;
+    }
+
+    /// Undo `to_com_frame`, adding the removed center of mass velocity back to every particle.
+    pub fn from_com_frame(&mut self) {
+        let com_velocity = self.com_frame_velocity;
+        for v in self.velocities.iter_mut() {
+            *v += com_velocity;
+        }
+        self.com_frame_velocity = Vector::zero();
+    }
 
-        let dx = f64::abs(r1.x - r2.x);
-        let dx = f64::min(dx, f64::abs(dx - self.width()));
+    /// Compute the mass weighted mean velocity of a group of particles, and subtract it from the velocity of
+    /// each particle in the group. Particles not in the group are left untouched.
+    pub fn subtract_group_drift(&mut self, ids: &[usize]) {
+        let mut momentum = Vector::zero();
+        let mut total_mass = 0.0;
+        for &id in ids {
+            momentum += self.velocities[id] * self.masses[id];
+            total_mass += self.masses[id];
+        }
+        if total_mass == 0.0 {
+            return;
+        }
+
+        let drift = momentum / total_mass;
+        for &id in ids {
+            self.velocities[id] -= drift;
+        }
+    }
 
-        let dy = f64::abs(r1.y - r2.y);
-        let dy = f64::min(dy, f64::abs(dy - self.height()));
+    /// Get the displacement vector pointing from the first particle to the second, using the minimum image.
+    pub fn displacement_between(&self, id1: usize, id2: usize) -> Vector {
+        self.minimum_image(self.positions[id2] - self.positions[id1])
+    }
 
-        dx * dx + dy * dy
+    /// Get the shortest periodic image of a displacement vector.
+    pub fn minimum_image(&self, displacement: Vector) -> Vector {
+        self.topology.minimum_image(displacement, &self.bounds)
+    }
+
+    /// Run the simulation in 1D: hold every particle at the given y, so only x evolves. The Universe enforces this
+    /// every step by calling `apply_constraints`.
+    pub fn lock_y(&mut self, value: f64) {
+        self.locked_y = Some(value);
+        self.apply_constraints();
+    }
+
+    /// Enforce any constraints on the particles' motion, i.e. a locked y coordinate. The y components of the
+    /// positions, velocities, and forces are reset.
+    pub fn apply_constraints(&mut self) {
+        if let Some(y) = self.locked_y {
+            for i in 0..self.num_particles() {
+                self.positions[i].y = y;
+                self.velocities[i].y = 0.0;
+                self.forces[i].y = 0.0;
+            }
+        }
+    }
+
+    /// Copies of the particles' positions, periodically wrapped into the box, whatever the topology is. The stored
+    /// positions are not changed, so e.g. an open simulation can be analyzed as if it were periodic.
+    pub fn wrapped_positions(&self) -> Vec<Position> {
+        let b = self.bounds;
+        self.positions
+            .iter()
+            .map(|p| Position::new(
+                b.xlo + (p.x - b.xlo).rem_euclid(self.width()),
+                b.ylo + (p.y - b.ylo).rem_euclid(self.height()),
+            ))
+            .collect()
     }
 
     /// Set all particles' positions to be their canonical positions.
     pub fn canonical_positions(&mut self) {
+        // No need to loop over the particles if the topology will not do anything.
+        if self.topology.is_noop() {
+            return;
+        }
+
         for i in 0 .. self.num_particles() {
             let p: &mut Position = &mut self.positions[i];
+            self.topology.canonical_velocity(*p, &mut self.velocities[i], &self.bounds);
             self.topology.canonical_position(&mut p.x, &mut p.y, &self.bounds);
         }
     }
+
+    /// Replace the topology, for example to switch from periodic to confined boundary conditions partway through a
+    /// simulation, and immediately put the particles in their canonical positions for the new topology.
+    pub fn set_topology(&mut self, topology: Box<dyn Topology>) {
+        self.topology = topology;
+        self.canonical_positions();
+    }
+}
+
+/// Read particles from a CSV file with the header `x,y,vx,vy,radius,mass`.
+fn read_particles_csv(path: &str) -> io::Result<Vec<Particle>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let contents = fs::read_to_string(path)?;
+    let mut lines = contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+    let header: Vec<&str> = match lines.next() {
+        Some((_, line)) => line.split(',').map(|s| s.trim()).collect(),
+        None => return Err(invalid(format!("{} is empty", path))),
+    };
+    if header != ["x", "y", "vx", "vy", "radius", "mass"] {
+        return Err(invalid(format!("expected the header x,y,vx,vy,radius,mass, found {}", header.join(","))));
+    }
+
+    let mut particles = Vec::new();
+    for (line_number, line) in lines {
+        let values = line
+            .split(',')
+            .map(|s| s.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|e| invalid(format!("line {}: {}: {}", line_number + 1, e, line)))?;
+        if values.len() != 6 {
+            return Err(invalid(format!("line {}: expected 6 values, found {}", line_number + 1, values.len())));
+        }
+        particles.push(Particle::new()
+            .with_coords(values[0], values[1])
+            .with_velocity_components(values[2], values[3])
+            .with_radius(values[4])
+            .with_mass(values[5])
+            .to_owned());
+    }
+    Ok(particles)
 }
 
-pub trait Topology {
+/// Write an array of doubles in the NumPy .npy format (version 1.0), in C order with the given shape. The file is a
+/// magic string, the format version, and the length of a header describing the array, followed by the header,
+/// padded with spaces so that the data starts on a multiple of 64 bytes, and then the raw little endian data.
+fn write_npy_array(path: &str, data: &[f64], shape: &[usize]) -> io::Result<()> {
+    const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => format!("({})", shape.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': {}, }}", shape);
+    // The magic string, the two byte header length, the header, and its terminating newline.
+    let unpadded = MAGIC.len() + 2 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 2 + header.len() + 8 * data.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    for value in data {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fs::write(path, bytes)
+}
+
+/// Lets boxed topologies be cloned. This is implemented for every topology that implements Clone.
+pub trait TopologyClone {
+    fn clone_box(&self) -> Box<dyn Topology>;
+}
+
+impl<T: 'static + Topology + Clone> TopologyClone for T {
+    fn clone_box(&self) -> Box<dyn Topology> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Topology> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+pub trait Topology: TopologyClone + Send + Sync {
     /// Take a particle in the sim data an put them in their canonical positions. For example,
     /// if the particles are on a torus (harmonic boundary conditions), and a particle goes beyond
     /// the "edge" of the simulation, canonical_positions will "wrap" the particle back so it appears
     /// on the other side of the simulation.
     fn canonical_position(&self, x: &mut f64, y: &mut f64, bounds: &Bounds);
+
+    /// Adjust the velocity of a particle before its position is made canonical, for example reversing it when the
+    /// particle is reflected off of a wall. By default, velocities are unchanged.
+    fn canonical_velocity(&self, _position: Position, _velocity: &mut Velocity, _bounds: &Bounds) {}
+
+    /// Whether canonical_position never changes positions, in which case calling it can be skipped.
+    fn is_noop(&self) -> bool {
+        false
+    }
+
+    /// Whether particles interact with periodic images across the edges of the bounds, in x and in y. By default,
+    /// topologies are periodic in both.
+    fn periodic(&self) -> (bool, bool) {
+        (true, true)
+    }
+
+    /// Get the shortest image of a displacement vector between two particles. By default, this is the periodic
+    /// minimum image in the bounds.
+    fn minimum_image(&self, displacement: Vector, bounds: &Bounds) -> Vector {
+        bounds.minimum_image(displacement)
+    }
+
+    /// Advance any time dependence of the topology, like the deformation of a sheared box, by a timestep. The
+    /// Universe calls this at the end of every step. By default, topologies do not change with time.
+    fn advance(&mut self, _dt: f64) {}
+
+    /// The accumulated shear strain of a deforming topology, which is zero for topologies that do not deform.
+    fn strain(&self) -> f64 {
+        0.0
+    }
+
+    /// The x offset of the periodic images above the bounds, relative to the bounds, for topologies whose images slide
+    /// past each other. The images below the bounds are offset by the negative of this. By default, there is no offset.
+    fn image_offset(&self, _bounds: &Bounds) -> f64 {
+        0.0
+    }
 }
 
-struct OpenTopology {}
+#[derive(Clone)]
+pub struct OpenTopology {}
 
+#[derive(Clone)]
 struct HarmonicTopology {
     wrap_x: bool,
     wrap_y: bool,
 }
 
 impl Topology for OpenTopology {
-    fn canonical_position(&self, x: &mut f64, y: &mut f64, bounds: &Bounds) {}
+    fn canonical_position(&self, _x: &mut f64, _y: &mut f64, _bounds: &Bounds) {}
+
+    fn is_noop(&self) -> bool {
+        true
+    }
+
+    fn periodic(&self) -> (bool, bool) {
+        (false, false)
+    }
 }
 
 impl Topology for HarmonicTopology {
@@ -208,6 +855,171 @@ impl Topology for HarmonicTopology {
 
         assert!(bounds.is_in_bounds(Vector::new(*x, *y)));
     }
+
+    fn periodic(&self) -> (bool, bool) {
+        (self.wrap_x, self.wrap_y)
+    }
+}
+
+/// A box with hard walls at the bounds. Particles that leave the box are reflected back inside of it, and the
+/// component of their velocity normal to the wall is reversed.
+#[derive(Clone)]
+pub struct ReflectingTopology {}
+
+impl Topology for ReflectingTopology {
+    fn canonical_position(&self, x: &mut f64, y: &mut f64, bounds: &Bounds) {
+        let reflect = |v: &mut f64, lo: f64, hi: f64| {
+            if *v < lo {
+                *v = 2.0 * lo - *v;
+            } else if hi < *v {
+                *v = 2.0 * hi - *v;
+            }
+            // A particle that went more than the whole box outside of it ends up at the wall.
+            *v = f64::clamp(*v, lo, hi);
+        };
+        reflect(x, bounds.xlo, bounds.xhi);
+        reflect(y, bounds.ylo, bounds.yhi);
+    }
+
+    fn canonical_velocity(&self, position: Position, velocity: &mut Velocity, bounds: &Bounds) {
+        if (position.x < bounds.xlo && velocity.x < 0.0) || (bounds.xhi < position.x && 0.0 < velocity.x) {
+            velocity.x = -velocity.x;
+        }
+        if (position.y < bounds.ylo && velocity.y < 0.0) || (bounds.yhi < position.y && 0.0 < velocity.y) {
+            velocity.y = -velocity.y;
+        }
+    }
+
+    fn periodic(&self) -> (bool, bool) {
+        (false, false)
+    }
+}
+
+/// How the shear rate of a Lees-Edwards topology changes with time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShearProtocol {
+    /// Shear at a constant rate.
+    Steady { rate: f64 },
+    /// Shear back and forth, with a strain of amplitude * sin(omega t), and so a shear rate of
+    /// amplitude * omega * cos(omega t).
+    OscillatoryShear { amplitude: f64, omega: f64 },
+}
+
+impl ShearProtocol {
+    /// The shear rate at a time.
+    pub fn rate(&self, time: f64) -> f64 {
+        match *self {
+            ShearProtocol::Steady { rate } => rate,
+            ShearProtocol::OscillatoryShear { amplitude, omega } => amplitude * omega * f64::cos(omega * time),
+        }
+    }
+
+    /// The strain accumulated between two times, the integral of the shear rate.
+    fn strain_between(&self, t0: f64, t1: f64) -> f64 {
+        match *self {
+            ShearProtocol::Steady { rate } => rate * (t1 - t0),
+            ShearProtocol::OscillatoryShear { amplitude, omega } => {
+                amplitude * (f64::sin(omega * t1) - f64::sin(omega * t0))
+            }
+        }
+    }
+}
+
+/// Lees-Edwards boundary conditions, for simulating shear flow: periodic in both directions, but with the periodic
+/// images above and below the box sliding in x, with the accumulated strain times the height of the box as their
+/// offset. Particles that cross the top or bottom of the box are shifted in x by the offset, and their x velocity by
+/// the velocity difference across the box, so they keep moving with the shear flow.
+#[derive(Clone)]
+pub struct LeesEdwardsTopology {
+    pub shear: ShearProtocol,
+
+    /// The accumulated shear strain.
+    strain: f64,
+    /// The time the topology has been advanced to.
+    time: f64,
+}
+
+impl LeesEdwardsTopology {
+    pub fn new(shear: ShearProtocol) -> LeesEdwardsTopology {
+        LeesEdwardsTopology { shear, strain: 0.0, time: 0.0 }
+    }
+}
+
+impl Topology for LeesEdwardsTopology {
+    fn canonical_position(&self, x: &mut f64, y: &mut f64, bounds: &Bounds) {
+        if bounds.yhi < *y {
+            *y -= bounds.height();
+            *x -= self.image_offset(bounds);
+        } else if *y < bounds.ylo {
+            *y += bounds.height();
+            *x += self.image_offset(bounds);
+        }
+        *x = bounds.xlo + (*x - bounds.xlo).rem_euclid(bounds.width());
+        *y = bounds.ylo + (*y - bounds.ylo).rem_euclid(bounds.height());
+    }
+
+    fn canonical_velocity(&self, position: Position, velocity: &mut Velocity, bounds: &Bounds) {
+        let velocity_difference = self.shear.rate(self.time) * bounds.height();
+        if bounds.yhi < position.y {
+            velocity.x -= velocity_difference;
+        } else if position.y < bounds.ylo {
+            velocity.x += velocity_difference;
+        }
+    }
+
+    fn minimum_image(&self, displacement: Vector, bounds: &Bounds) -> Vector {
+        let mut displacement = displacement;
+        if 0.5 * bounds.height() < displacement.y {
+            displacement.y -= bounds.height();
+            displacement.x -= self.image_offset(bounds);
+        } else if displacement.y < -0.5 * bounds.height() {
+            displacement.y += bounds.height();
+            displacement.x += self.image_offset(bounds);
+        }
+        displacement.x -= bounds.width() * (displacement.x / bounds.width()).round();
+        displacement
+    }
+
+    fn advance(&mut self, dt: f64) {
+        self.strain += self.shear.strain_between(self.time, self.time + dt);
+        self.time += dt;
+    }
+
+    fn strain(&self) -> f64 {
+        self.strain
+    }
+
+    /// The offset is the strain times the height of the box, wrapped into the width of the box.
+    fn image_offset(&self, bounds: &Bounds) -> f64 {
+        (self.strain * bounds.height()).rem_euclid(bounds.width())
+    }
+}
+
+/// A disk shaped domain, where particles that leave the disk are reflected back inside of it. The bounds of the
+/// SimData should cover the disk, the cells outside of the disk will simply be empty.
+#[derive(Clone)]
+pub struct DiskTopology {
+    pub center: Position,
+    pub radius: f64,
+}
+
+impl Topology for DiskTopology {
+    fn canonical_position(&self, x: &mut f64, y: &mut f64, _bounds: &Bounds) {
+        let displacement = Vector::new(*x, *y) - self.center;
+        let r = displacement.length();
+        if self.radius < r {
+            // Reflect the particle across the edge of the disk. If it went so far that the reflection is still
+            // outside of the disk, it ends up at the center.
+            let reflected_r = f64::max(2.0 * self.radius - r, 0.0);
+            let position = self.center + displacement * (reflected_r / r);
+            *x = position.x;
+            *y = position.y;
+        }
+    }
+
+    fn periodic(&self) -> (bool, bool) {
+        (false, false)
+    }
 }
 
 // =================================================================================================
@@ -219,6 +1031,8 @@ mod tests {
     use crate::assert_close;
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use crate::core::universe::Universe;
+    use crate::utils::init::square_lattice;
 
     #[test]
     fn test_bounds() {
@@ -232,6 +1046,15 @@ mod tests {
         assert_eq!(bounds.height(), 5.25);
     }
 
+    #[test]
+    fn test_bounds_from_center() {
+        let bounds = Bounds::from_center(Vector::new(1.0, -2.0), 3.0, 0.5);
+        assert_eq!((bounds.xlo, bounds.xhi), (-2.0, 4.0));
+        assert_eq!((bounds.ylo, bounds.yhi), (-2.5, -1.5));
+        assert_eq!(bounds.center().x, 1.0);
+        assert_eq!(bounds.center().y, -2.0);
+    }
+
     #[test]
     fn test_simdata_construction_from_bounds() {
         let bounds = Bounds {
@@ -288,6 +1111,521 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_set_topology() {
+        // Under the default, periodic, topology the particle would wrap around to the left side of the box.
+        let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 10.0, 0.0, 10.0)), 0.01);
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(9.8, 5.0).with_radius(0.1).with_velocity_components(1.0, 0.0).to_owned(),
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.1).to_owned(),
+        ]);
+        universe.sim_data.set_topology(Box::new(ReflectingTopology {}));
+        universe.run_until(0.5);
+
+        // Instead, it bounced off of the right wall.
+        let sim_data = &universe.sim_data;
+        assert_close!(sim_data.positions[0].x, 9.7, 0.02);
+        assert_close!(sim_data.velocities[0].x, -1.0, 1.0e-9);
+
+        // Particles that are outside of the box when the topology is set are brought back inside.
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &[Particle::new()]);
+        sim_data.set_topology(Box::new(OpenTopology {}));
+        sim_data.positions[0] = Position::new(10.3, -0.5);
+        sim_data.set_topology(Box::new(ReflectingTopology {}));
+        assert_close!(sim_data.positions[0].x, 9.7, 1.0e-9);
+        assert_close!(sim_data.positions[0].y, 0.5, 1.0e-9);
+        assert!(sim_data.bounds.is_in_bounds(sim_data.positions[0]));
+    }
+
+    #[test]
+    fn test_com_frame() {
+        let particles = [
+            Particle::new().with_velocity_components(1.0, -2.0).to_owned(),
+            Particle::new().with_velocity_components(2.0, 0.5).to_owned(),
+            Particle::new().with_velocity_components(3.5, 0.25).with_mass(2.0).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let original: Vec<(f64, f64)> = sim_data.velocities.iter().map(|v| (v.x, v.y)).collect();
+
+        sim_data.to_com_frame();
+        let com_velocity = sim_data.center_of_mass_velocity();
+        assert_close!(com_velocity.x, 0.0, 1.0e-12);
+        assert_close!(com_velocity.y, 0.0, 1.0e-12);
+
+        sim_data.from_com_frame();
+        let restored: Vec<(f64, f64)> = sim_data.velocities.iter().map(|v| (v.x, v.y)).collect();
+        assert_eq!(restored, original);
+    }
+
+    #[test]
+    fn test_disk_topology() {
+        let topology = DiskTopology { center: Position::new(5.0, 5.0), radius: 4.0 };
+        let bounds = Bounds::from((0.0, 10.0, 0.0, 10.0));
+
+        {
+            // Inside of the disk, nothing happens.
+            let mut pos = Position::new(7.0, 6.0);
+            topology.canonical_position(&mut pos.x, &mut pos.y, &bounds);
+            assert_close!(pos.x, 7.0, 1.0e-6);
+            assert_close!(pos.y, 6.0, 1.0e-6);
+        }
+        {
+            // Outside of the disk, the particle is reflected back inside.
+            let mut pos = Position::new(9.5, 5.0);
+            topology.canonical_position(&mut pos.x, &mut pos.y, &bounds);
+            assert_close!(pos.x, 8.5, 1.0e-6);
+            assert_close!(pos.y, 5.0, 1.0e-6);
+        }
+        {
+            let mut pos = Position::new(2.0, 1.0);
+            topology.canonical_position(&mut pos.x, &mut pos.y, &bounds);
+            let r = (pos - topology.center).length();
+            assert_close!(r, 3.0, 1.0e-6);
+            assert_close!(pos.x, 5.0 - 3.0 * 0.6, 1.0e-6);
+            assert_close!(pos.y, 5.0 - 3.0 * 0.8, 1.0e-6);
+        }
+    }
+
+    #[test]
+    fn test_subtract_group_drift() {
+        let particles = vec![
+            Particle::new().with_coords(1.0, 1.0).with_mass(1.0).with_velocity_components(1.0, 2.0).to_owned(),
+            Particle::new().with_coords(2.0, 1.0).with_mass(3.0).with_velocity_components(-1.0, 0.5).to_owned(),
+            Particle::new().with_coords(3.0, 1.0).with_mass(2.0).with_velocity_components(4.0, -3.0).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        sim_data.subtract_group_drift(&[0, 1]);
+
+        let mut momentum = Vector::zero();
+        for id in [0, 1] {
+            momentum += sim_data.velocities[id] * sim_data.masses[id];
+        }
+        assert_close!(momentum.x, 0.0, 1.0e-9);
+        assert_close!(momentum.y, 0.0, 1.0e-9);
+
+        // The excluded particle is untouched.
+        assert_eq!(sim_data.velocities[2].x, 4.0);
+        assert_eq!(sim_data.velocities[2].y, -3.0);
+    }
+
+    #[test]
+    fn test_set_area_fraction() {
+        let particles = vec![
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.0, 2.0).with_radius(0.3).to_owned(),
+            Particle::new().with_coords(8.0, 7.0).with_radius(0.4).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        assert!(sim_data.area_fraction() < 0.02);
+
+        sim_data.set_area_fraction(0.3);
+        assert_close!(sim_data.area_fraction(), 0.3, 1.0e-9);
+        assert_eq!(sim_data.radii[0], 0.5);
+
+        // Positions are scaled along with the box, about its center.
+        assert_close!(sim_data.bounds.xlo + sim_data.bounds.xhi, 10.0, 1.0e-9);
+        let factor = sim_data.width() / 10.0;
+        assert_close!(sim_data.positions[1].x, 5.0, 1.0e-9);
+        assert_close!(sim_data.positions[1].y, 5.0 - 3.0 * factor, 1.0e-9);
+        assert!(sim_data.bounds.is_in_bounds(sim_data.positions[0]));
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let path = std::env::temp_dir().join("rust_md_test_from_csv.csv");
+        let path = path.to_str().unwrap();
+        fs::write(path, "x,y,vx,vy,radius,mass\n1.0,2.0,0.5,-0.5,0.1,1.5\n3.0,4.5,0.0,1.0,0.2,2.0\n").unwrap();
+
+        let sim_data = SimData::from_csv(path).unwrap();
+        assert_eq!(sim_data.num_particles(), 2);
+        assert_eq!(sim_data.positions[1].x, 3.0);
+        assert_eq!(sim_data.positions[1].y, 4.5);
+        assert_eq!(sim_data.velocities[0].x, 0.5);
+        assert_eq!(sim_data.velocities[0].y, -0.5);
+        assert_eq!(sim_data.radii[1], 0.2);
+        assert_eq!(sim_data.masses[0], 1.5);
+        assert_close!(sim_data.bounds.xlo, 0.9, 1.0e-9);
+        assert_close!(sim_data.bounds.yhi, 4.7, 1.0e-9);
+
+        let sim_data = SimData::from_csv_with_bounds(path, Bounds::from((0.0, 10.0, 0.0, 10.0))).unwrap();
+        assert_eq!(sim_data.bounds.xhi, 10.0);
+        assert_eq!(sim_data.num_particles(), 2);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_write_npy() {
+        let prefix = std::env::temp_dir().join("rust_md_test_write_npy");
+        let prefix = prefix.to_str().unwrap();
+        let particles: Vec<Particle> = (0..5)
+            .map(|i| Particle::new().with_coords(i as f64, 1.0).with_radius(0.1 * (i + 1) as f64).to_owned())
+            .collect();
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        sim_data.write_npy(prefix).unwrap();
+
+        let read = |name: &str| {
+            let path = format!("{}_{}.npy", prefix, name);
+            let bytes = fs::read(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            bytes
+        };
+        for (name, shape, len) in [("positions", "(5, 2)", 10), ("velocities", "(5, 2)", 10), ("radii", "(5,)", 5)] {
+            let bytes = read(name);
+            assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+            let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+            assert_eq!((10 + header_len) % 64, 0);
+            let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+            assert!(header.starts_with("{'descr': '<f8', 'fortran_order': False, 'shape': "));
+            assert!(header.contains(&format!("'shape': {}, ", shape)));
+            assert!(header.ends_with('\n'));
+            assert_eq!(bytes.len(), 10 + header_len + 8 * len);
+
+            if name == "radii" {
+                let data = &bytes[10 + header_len..];
+                let last = f64::from_le_bytes(data[32..40].try_into().unwrap());
+                assert_close!(last, 0.5, 1.0e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_csv_malformed_row() {
+        let path = std::env::temp_dir().join("rust_md_test_from_csv_malformed.csv");
+        let path = path.to_str().unwrap();
+        fs::write(path, "x,y,vx,vy,radius,mass\n1.0,2.0,0.5,-0.5,0.1,1.5\n3.0,abc,0.0,1.0,0.2,2.0\n").unwrap();
+
+        let error = SimData::from_csv(path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 3"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_topology_canonical_positions() {
+        let particles = vec![
+            Particle::new().with_coords(-1.0, 3.0).to_owned(),
+            Particle::new().with_coords(12.0, 4.0).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        sim_data.topology = Box::new(OpenTopology {});
+        assert!(sim_data.topology.is_noop());
+
+        sim_data.canonical_positions();
+        assert_eq!(sim_data.positions[0].x, -1.0);
+        assert_eq!(sim_data.positions[1].x, 12.0);
+    }
+
+    /// A topology that claims to be a no-op, but panics if it is ever asked to canonicalize a position.
+    #[derive(Clone)]
+    struct PanickingNoopTopology {}
+
+    impl Topology for PanickingNoopTopology {
+        fn canonical_position(&self, _x: &mut f64, _y: &mut f64, _bounds: &Bounds) {
+            panic!("canonical_position should not be called for a no-op topology");
+        }
+
+        fn is_noop(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_canonical_positions_returns_early_for_noop_topology() {
+        let particles = vec![Particle::new().with_coords(1.0, 3.0).to_owned()];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        sim_data.topology = Box::new(PanickingNoopTopology {});
+        sim_data.canonical_positions();
+    }
+
+    #[test]
+    fn test_stable_unique_ids() {
+        let mut sim_data = SimData::new(0.0, 10.0, 0.0, 10.0);
+        let uid0 = sim_data.add_particle(Particle::new().with_coords(1.0, 1.0));
+        let uid1 = sim_data.add_particle(Particle::new().with_coords(2.0, 2.0));
+        let uid2 = sim_data.add_particle(Particle::new().with_coords(3.0, 3.0));
+        assert_eq!(sim_data.uid_of(1), uid1);
+
+        // Removing the first particle moves the last particle into its place.
+        sim_data.remove_particle(0);
+        assert_eq!(sim_data.num_particles(), 2);
+        assert_eq!(sim_data.index_of(uid0), None);
+
+        let index = sim_data.index_of(uid2).unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(sim_data.positions[index].x, 3.0);
+        assert_eq!(sim_data.uid_of(index), uid2);
+        let index = sim_data.index_of(uid1).unwrap();
+        assert_eq!(sim_data.positions[index].x, 2.0);
+
+        // Unique ids are never reused.
+        let uid3 = sim_data.add_particle(Particle::new().with_coords(4.0, 4.0));
+        assert!(uid3 != uid0 && uid3 != uid1 && uid3 != uid2);
+        assert_eq!(sim_data.index_of(uid3), Some(2));
+    }
+
+    #[test]
+    fn test_extract_region() {
+        let particles = vec![
+            Particle::new().with_coords(1.0, 1.0).with_velocity_components(0.5, 0.0).to_owned(),
+            Particle::new().with_coords(7.0, 2.0).to_owned(),
+            Particle::new().with_coords(4.0, 9.0).with_radius(0.3).with_type(1).to_owned(),
+            Particle::new().with_coords(6.0, 5.0).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+
+        let left = sim_data.extract_region(Bounds::from((0.0, 5.0, 0.0, 10.0)));
+        assert_eq!(left.num_particles(), 2);
+        assert_eq!(left.bounds.xhi, 5.0);
+        assert!(left.positions.iter().all(|p| p.x < 5.0));
+        assert_eq!(left.positions[0].x, 1.0);
+        assert_eq!(left.velocities[0].x, 0.5);
+        assert_eq!(left.positions[1].y, 9.0);
+        assert_eq!(left.radii[1], 0.3);
+        assert_eq!(left.types, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_freeze_boundary_layer() {
+        let particles = [
+            Particle::new().with_coords(0.5, 5.0).with_radius(0.5).with_velocity_components(1.0, 0.0).to_owned(),
+            Particle::new().with_coords(5.0, 9.2).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.5).with_velocity_components(1.0, 0.0).to_owned(),
+            Particle::new().with_coords(2.0, 7.9).with_radius(0.5).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        assert_eq!(sim_data.frozen, vec![false; 4]);
+
+        sim_data.freeze_boundary_layer(2.0);
+        assert_eq!(sim_data.frozen, vec![true, true, false, false]);
+        assert_eq!(sim_data.velocities[0].x, 0.0);
+        assert_eq!(sim_data.velocities[2].x, 1.0);
+
+        // Frozen state follows the particles when others are removed.
+        sim_data.remove_particle(0);
+        assert_eq!(sim_data.frozen, vec![false, true, false]);
+    }
+
+    #[test]
+    fn test_radial_kick() {
+        let particles = [
+            Particle::new().with_coords(8.0, 5.0).to_owned(),
+            Particle::new().with_coords(2.0, 1.0).to_owned(),
+            Particle::new().with_coords(5.0, 5.0).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let center = Position::new(5.0, 5.0);
+        sim_data.radial_kick(center, 2.0);
+
+        for i in 0..2 {
+            let v = sim_data.velocities[i];
+            let outward = sim_data.positions[i] - center;
+            assert_close!(v.length(), 2.0, 1.0e-12);
+            assert_close!(v.x * outward.x + v.y * outward.y, 2.0 * outward.length(), 1.0e-12);
+        }
+        assert_eq!(sim_data.velocities[2].length(), 0.0);
+    }
+
+    #[test]
+    fn test_physical_time() {
+        let mut sim_data = SimData::new(0.0, 1.0, 0.0, 1.0);
+        sim_data.simulation_time = 1.5;
+        assert_eq!(sim_data.physical_time(), 1.5);
+
+        sim_data.units = Some(Units { time_scale: 2.0, ..Units::default() });
+        assert_eq!(sim_data.physical_time(), 3.0);
+    }
+
+    #[test]
+    fn test_wrapped_positions() {
+        let particles = [
+            Particle::new().with_coords(12.5, -3.0).to_owned(),
+            Particle::new().with_coords(4.0, 6.0).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        sim_data.set_topology(Box::new(OpenTopology {}));
+
+        let wrapped = sim_data.wrapped_positions();
+        assert!(sim_data.bounds.is_in_bounds(wrapped[0]));
+        assert_close!(wrapped[0].x, 2.5, 1.0e-12);
+        assert_close!(wrapped[0].y, 7.0, 1.0e-12);
+        assert_eq!((wrapped[1].x, wrapped[1].y), (4.0, 6.0));
+        // The live state is unchanged.
+        assert_eq!((sim_data.positions[0].x, sim_data.positions[0].y), (12.5, -3.0));
+    }
+
+    #[test]
+    fn test_check_overlaps() {
+        let particles = [
+            Particle::new().with_coords(2.0, 2.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.5).to_owned(),
+            // Just touching the first particle, which is not an overlap.
+            Particle::new().with_coords(3.0, 2.0).with_radius(0.5).to_owned(),
+            // A pair that overlaps across the periodic boundary.
+            Particle::new().with_coords(5.0, 9.6).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.0, 0.3).with_radius(0.5).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let overlaps = sim_data.check_overlaps();
+
+        assert_eq!(overlaps.len(), 1);
+        let (id1, id2, overlap) = overlaps[0];
+        assert_eq!((id1, id2), (3, 4));
+        assert_close!(overlap, 0.3, 1.0e-12);
+    }
+
+    #[test]
+    fn test_heat_region() {
+        // A cold lattice, whose left half gets heated.
+        let bounds = Bounds::from((0.0, 40.0, 0.0, 20.0));
+        let mut particles = square_lattice(40, 20, bounds, 0.1);
+        for p in particles.iter_mut() {
+            p.with_velocity_components(0.01, -0.02);
+        }
+        let mut sim_data = SimData::new_with_particles(bounds, &particles);
+        sim_data.heat_region(Bounds::from((0.0, 20.0, 0.0, 20.0)), 2.0, 7);
+
+        let (inside, outside): (Vec<usize>, Vec<usize>) =
+            (0..sim_data.num_particles()).partition(|&i| sim_data.positions[i].x < 20.0);
+        assert_eq!(inside.len(), 400);
+        let kinetic: f64 = inside.iter().map(|&i| 0.5 * sim_data.masses[i] * sim_data.velocities[i].length_sqr()).sum();
+        assert_close!(kinetic / inside.len() as f64, 2.0, 0.2);
+        for i in outside {
+            assert_eq!((sim_data.velocities[i].x, sim_data.velocities[i].y), (0.01, -0.02));
+        }
+
+        // The same seed heats the same way.
+        let mut again = SimData::new_with_particles(bounds, &particles);
+        again.heat_region(Bounds::from((0.0, 20.0, 0.0, 20.0)), 2.0, 7);
+        assert!(inside.iter().all(|&i| again.velocities[i].x == sim_data.velocities[i].x));
+    }
+
+    #[test]
+    fn test_set_affine_velocity() {
+        let particles = [
+            Particle::new().with_coords(5.0, 8.0).to_owned(),
+            Particle::new().with_coords(2.0, 5.0).to_owned(),
+            Particle::new().with_coords(7.0, 1.0).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+
+        // A shear flow, with a shear rate of 0.5.
+        sim_data.set_affine_velocity([[0.0, 0.5], [0.0, 0.0]]);
+        let vx: Vec<f64> = sim_data.velocities.iter().map(|v| v.x).collect();
+        assert_eq!(vx, vec![1.5, 0.0, -2.0]);
+        assert!(sim_data.velocities.iter().all(|v| v.y == 0.0));
+
+        // A rotation, about the center of the box.
+        sim_data.set_affine_velocity([[0.0, -1.0], [1.0, 0.0]]);
+        assert_eq!((sim_data.velocities[0].x, sim_data.velocities[0].y), (-3.0, 0.0));
+        assert_eq!((sim_data.velocities[1].x, sim_data.velocities[1].y), (0.0, -3.0));
+    }
+
+    #[test]
+    fn test_lees_edwards_topology() {
+        let bounds = Bounds::from((0.0, 10.0, 0.0, 10.0));
+        let mut topology = LeesEdwardsTopology::new(ShearProtocol::Steady { rate: 0.1 });
+        topology.advance(2.0);
+        assert_close!(topology.strain(), 0.2, 1.0e-12);
+
+        // Leaving through the top shifts the particle back by the offset of 2, and slows it down by the velocity
+        // difference of 1 across the box.
+        let (mut x, mut y) = (3.0, 10.5);
+        let mut velocity = Velocity::new(0.5, 1.0);
+        topology.canonical_velocity(Position::new(x, y), &mut velocity, &bounds);
+        topology.canonical_position(&mut x, &mut y, &bounds);
+        assert_close!(x, 1.0, 1.0e-12);
+        assert_close!(y, 0.5, 1.0e-12);
+        assert_close!(velocity.x, -0.5, 1.0e-12);
+
+        // The image of a particle near the top, seen from a particle near the bottom, is shifted by the offset.
+        let d = topology.minimum_image(Vector::new(2.5, 9.0), &bounds);
+        assert_close!(d.x, 0.5, 1.0e-12);
+        assert_close!(d.y, -1.0, 1.0e-12);
+    }
+
+    #[test]
+    fn test_push_apart_overlaps() {
+        let particles = [
+            Particle::new().with_coords(4.0, 5.0).with_radius(1.0).to_owned(),
+            Particle::new().with_coords(5.5, 5.0).with_radius(1.0).to_owned(),
+            Particle::new().with_coords(8.5, 5.0).with_radius(0.5).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        sim_data.push_apart_overlaps(10);
+
+        // The overlap of 0.5 is split evenly between the pair, so they end up just touching.
+        assert_close!(sim_data.positions[0].x, 3.75, 1.0e-12);
+        assert_close!(sim_data.positions[1].x, 5.75, 1.0e-12);
+        assert_close!(sim_data.distance_sqr_between(0, 1), 4.0, 1.0e-12);
+        // The particle that did not overlap anything has not moved.
+        assert_eq!(sim_data.positions[2].x, 8.5);
+        assert!(sim_data.contact_numbers().iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn test_velocity_moments() {
+        // Speeds of 5, 1, 1, and 3.
+        let particles = [
+            Particle::new().with_velocity_components(3.0, 4.0).to_owned(),
+            Particle::new().with_velocity_components(0.0, 1.0).to_owned(),
+            Particle::new().with_velocity_components(1.0, 0.0).to_owned(),
+            Particle::new().with_velocity_components(0.0, -3.0).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let (mean, rms, kurtosis) = sim_data.velocity_moments();
+        assert_close!(mean, 2.5, 1.0e-12);
+        assert_close!(rms, 3.0, 1.0e-12);
+        // Variance 11/4 and fourth central moment 197/16.
+        assert_close!(kurtosis, (197.0 / 16.0) / (121.0 / 16.0), 1.0e-12);
+    }
+
+    #[test]
+    fn test_potential_energies() {
+        use crate::core::force::HardSphereForce;
+
+        // Particle 1 overlaps both of the others by 0.2.
+        let particles = [
+            Particle::new().with_coords(4.2, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.8, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.5).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let energies = sim_data.potential_energies(&HardSphereForce::new(100.0));
+        assert_close!(energies[0], 1.0, 1.0e-9);
+        assert_close!(energies[1], 2.0, 1.0e-9);
+        assert_close!(energies[2], 1.0, 1.0e-9);
+        assert_eq!(energies[3], 0.0);
+    }
+
+    #[test]
+    fn test_is_jammed() {
+        use crate::core::force::HardSphereForce;
+        use crate::utils::init::hexagonal_lattice;
+
+        let force = HardSphereForce::new(100.0);
+
+        // A compressed hexagonal packing, where every particle has six balanced contacts.
+        let bounds = Bounds::from((0.0, 6.0, 0.0, 3.0 * f64::sqrt(3.0)));
+        let sim_data = SimData::new_with_particles(bounds, &hexagonal_lattice(6, 6, bounds, 0.51));
+        assert!(sim_data.contact_numbers().iter().all(|z| *z == 6));
+        assert!(sim_data.is_jammed(&force));
+
+        // A dilute gas.
+        let particles = vec![
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.2).to_owned(),
+            Particle::new().with_coords(3.0, 4.0).with_radius(0.2).to_owned(),
+            Particle::new().with_coords(4.5, 2.0).with_radius(0.2).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(bounds, &particles);
+        assert!(!sim_data.is_jammed(&force));
+
+        // A dense, but unbalanced, configuration.
+        let mut sim_data = SimData::new_with_particles(bounds, &hexagonal_lattice(6, 6, bounds, 0.51));
+        sim_data.positions[7].x += 0.05;
+        assert!(!sim_data.is_jammed(&force));
+    }
+
     #[test]
     fn test_simdata_canonical_positions() {
 
@@ -1,30 +1,93 @@
-use std::borrow::{Borrow, BorrowMut};
-use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
 use std::ops::Deref;
-use std::rc::Rc;
-use crate::core::force::{Force, HardSphereForce, force_loop};
+use crate::core::force::{BodyForce, Force, HardSphereForce, ScaledForce, force_loop};
 use crate::core::simdata::{Bounds, SimData};
-use crate::core::integrator::{Integrator, velocity_verlet::VelocityVerlet};
+use crate::core::vector::Vector;
+use crate::core::integrator::{Integrator, overdamped::OverdampedIntegrator, velocity_verlet::VelocityVerlet};
 use crate::core::verlet_lists::create_verlet_lists;
-use crate::core::monitor::{Monitor, PositionMonitor};
+use crate::core::monitor::Monitor;
+use crate::core::protocol::{Protocol, ProtocolStep};
+use crate::utils::rng::SimRng;
 
-use std::time::{Duration, Instant};
-use crate::core::integrator::overdamped::OverdampedIntegrator;
+use std::time::Instant;
+
+/// The skin distance used when creating verlet lists.
+const VERLET_SKIN: f64 = 0.1;
+
+/// Energy minimization stops once the net force on every particle is smaller than this.
+const MINIMIZATION_FORCE_TOLERANCE: f64 = 1.0e-8;
+
+/// The maximum number of steepest descent steps taken by an energy minimization.
+const MINIMIZATION_MAX_STEPS: usize = 100_000;
+
+/// The number of steps the barostat of `equilibrate_pressure` runs between box adjustments.
+const BAROSTAT_STEPS: i64 = 10;
+
+/// The largest fractional change in area that the barostat of `equilibrate_pressure` makes at once.
+const BAROSTAT_MAX_AREA_CHANGE: f64 = 0.01;
+
+/// The number of steps a quench runs between rescaling the velocities.
+const QUENCH_STEPS: f64 = 10.0;
+
+/// A phase of a simulation step. Each iteration, the universe runs its phases in order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    PreStep,
+    PreForces,
+    Forces,
+    PostForces,
+    PostStep,
+}
+
+/// The phases of a normal simulation step.
+pub const DEFAULT_PHASES: [Phase; 5] = [Phase::PreStep, Phase::PreForces, Phase::Forces, Phase::PostForces, Phase::PostStep];
+
+/// A predicate that decides whether a universe should stop running.
+pub type StopCondition = Box<dyn Fn(&Universe) -> bool>;
 
 pub struct Universe {
     pub sim_data: SimData,
     pub integrator: Box<dyn Integrator>,
     pub forces: Box<dyn Force>,
+    /// Forces, like bonds, that are applied on top of the pairwise forces.
+    pub body_forces: Vec<Box<dyn BodyForce>>,
 
     pub is_running: bool,
     pub iterations: i64,
 
+    /// Set if the energy check found the total energy growing beyond its allowed multiple of the initial energy.
+    pub became_unstable: bool,
+
+    /// Set if the verlet lists of some step were built in a box too small for the minimum image convention, so
+    /// contacts with more than one periodic image of the same particle were missed. A warning is printed when this
+    /// first happens.
+    pub box_too_small: bool,
+
     pub monitors: HashMap<String, Box<dyn Monitor>>,
 
+    /// The sequence of phases that make up each step.
+    pub phases: Vec<Phase>,
+
     max_time: Option<f64>,
     max_iterations: Option<i64>,
 
+    /// If set, a predicate checked every iteration, which stops the simulation when it returns true.
+    stop_condition: Option<StopCondition>,
+
+    /// If set, the (start time, duration) of a warmup phase, over which the forces are linearly ramped up from zero.
+    warmup: Option<(f64, f64)>,
+
+    /// If set, the (multiplier, stop on instability) of the energy check, which tracks the total energy every step.
+    energy_check: Option<(f64, bool)>,
+    /// The total energy of the first step checked by the energy check.
+    initial_energy: Option<f64>,
+    /// The potential energy found in the last force evaluation, if the energy check is on.
+    step_potential_energy: f64,
+
+    /// The master random number generator. All randomness in the universe should come from it, so a seeded universe
+    /// is reproducible.
+    rng: SimRng,
+
     integrator_time: u128,
     forces_time: u128,
     verlet_lists_time: u128,
@@ -33,19 +96,42 @@ pub struct Universe {
 
 impl Universe {
     pub fn new(bounds: Bounds) -> Universe {
+        Universe::new_with_rng(bounds, SimRng::from_entropy())
+    }
+
+    /// Create a new universe whose randomness is all derived from a single seed. Two universes with the same seed that
+    /// run the same protocol produce identical trajectories.
+    pub fn new_seeded(bounds: Bounds, seed: u64) -> Universe {
+        Universe::new_with_rng(bounds, SimRng::new(seed))
+    }
+
+    /// Create a new universe whose velocity verlet integrator has the specified timestep.
+    pub fn new_with_timestep(bounds: Bounds, dt: f64) -> Universe {
+        let mut universe = Universe::new(bounds);
+        universe.with_timestep(dt);
+        universe
+    }
+
+    fn new_with_rng(bounds: Bounds, rng: SimRng) -> Universe {
         Universe {
             sim_data: SimData::from(bounds),
-            integrator: Box::new(VelocityVerlet {
-                dt: 0.001
-            }),
-            forces: Box::new(HardSphereForce {
-                repulsion: 100.0
-            }),
+            integrator: Box::new(VelocityVerlet::new(0.001)),
+            forces: Box::new(HardSphereForce::new(100.0)),
+            body_forces: vec![],
             is_running: true,
             iterations: 0,
+            became_unstable: false,
+            box_too_small: false,
             monitors: HashMap::new(),
+            phases: DEFAULT_PHASES.to_vec(),
             max_time: None,
             max_iterations: None,
+            stop_condition: None,
+            warmup: None,
+            energy_check: None,
+            initial_energy: None,
+            step_potential_energy: 0.0,
+            rng,
 
             integrator_time: 0,
             forces_time: 0,
@@ -69,14 +155,37 @@ impl Universe {
         self
     }
 
+    /// Set the sequence of phases that make up each step, for example to experiment with operator splitting schemes.
+    /// Allows for chaining.
+    pub fn with_phases(&mut self, phases: Vec<Phase>) -> &mut Self {
+        self.phases = phases;
+        self
+    }
+
+    /// Set the timestep of the integrator. Allows for chaining.
+    pub fn with_timestep(&mut self, dt: f64) -> &mut Self {
+        self.integrator.set_timestep(dt);
+        self
+    }
+
+    /// Set a predicate that is checked at the end of every iteration, and stops the simulation when it returns true.
+    pub fn set_stop_condition(&mut self, predicate: StopCondition) {
+        self.stop_condition = Some(predicate);
+    }
+
+    /// Add a force, like bonds, that is applied on top of the pairwise forces.
+    pub fn add_body_force(&mut self, force: Box<dyn BodyForce>) {
+        self.body_forces.push(force);
+    }
+
     /// Add a monitoring object to the universe.
     pub fn add_monitor(&mut self, name: &str, monitor: Box<dyn Monitor>) {
         self.monitors.insert(name.to_string(), monitor);
     }
 
     /// Get a monitoring object from the universe. If none exists, None is returned.
-    pub fn get_monitor(&self, name: &str) -> Option<&Box<dyn Monitor>> {
-        self.monitors.get(name)
+    pub fn get_monitor(&self, name: &str) -> Option<&dyn Monitor> {
+        self.monitors.get(name).map(|monitor| monitor.deref())
     }
 
     pub fn get_sim_data(&mut self) -> &mut SimData {
@@ -91,10 +200,236 @@ impl Universe {
         self.forces.deref()
     }
 
+    /// Get the universe's random number generator, e.g. for generating initial conditions. Stochastic components
+    /// should be seeded with `rng().next_seed()`.
+    pub fn rng(&mut self) -> &mut SimRng {
+        &mut self.rng
+    }
+
+    /// Get the timestep of the integrator.
+    pub fn timestep(&self) -> f64 {
+        self.integrator.get_timestep()
+    }
+
+    /// Set the timestep of the integrator.
+    pub fn set_timestep(&mut self, dt: f64) {
+        self.integrator.set_timestep(dt);
+    }
+
+    /// Swap in a new integrator mid-run, e.g. to relax a configuration with an overdamped integrator before running
+    /// dynamics. The particles, forces, and simulation time carry over. If the old integrator did not keep the
+    /// velocities up to date, they are zeroed so that the new integrator does not start from stale values.
+    pub fn switch_integrator(&mut self, integrator: Box<dyn Integrator>) {
+        if !self.integrator.updates_velocities() {
+            self.sim_data.velocities.iter_mut().for_each(|v| *v = Vector::zero());
+        }
+        self.integrator = integrator;
+    }
+
+    /// Only integrate the particles currently inside a region, by freezing every particle outside it. Forces are
+    /// still computed for all particles, so the active zone feels its frozen surroundings.
+    pub fn set_active_region(&mut self, region: Bounds) {
+        for i in 0..self.sim_data.num_particles() {
+            if !region.is_in_bounds(self.sim_data.positions[i]) {
+                self.sim_data.frozen[i] = true;
+            }
+        }
+    }
+
+    /// Track the total energy every step, and flag the simulation as unstable if its magnitude ever grows beyond
+    /// `multiplier` times that of the first step. Blow-ups are caught before NaNs appear. If `stop` is true, the
+    /// simulation also stops running once it becomes unstable. Allows for chaining.
+    pub fn with_energy_check(&mut self, multiplier: f64, stop: bool) -> &mut Self {
+        self.energy_check = Some((multiplier, stop));
+        self.initial_energy = None;
+        self.became_unstable = false;
+        self
+    }
+
+    /// Summarize where the time spent running the simulation went, as percentages of the total run time, e.g.
+    /// "Forces: 62.0%, Verlet: 25.0%, Integrator: 12.0%, Other: 1.0%". Other covers monitors and bookkeeping.
+    pub fn timing_summary(&self) -> String {
+        let percent = |time: u128| 100.0 * time as f64 / u128::max(self.total_time, 1) as f64;
+        let other = self.total_time.saturating_sub(self.forces_time + self.verlet_lists_time + self.integrator_time);
+        format!("Forces: {:.1}%, Verlet: {:.1}%, Integrator: {:.1}%, Other: {:.1}%",
+                percent(self.forces_time),
+                percent(self.verlet_lists_time),
+                percent(self.integrator_time),
+                percent(other))
+    }
+
+    /// Compute the total kinetic energy of the particles.
+    pub fn kinetic_energy(&self) -> f64 {
+        let sim_data = &self.sim_data;
+        (0..sim_data.num_particles())
+            .map(|i| 0.5 * sim_data.masses[i] * sim_data.velocities[i].length_sqr())
+            .sum()
+    }
+
+    /// Compute the total potential energy of the current configuration, without advancing the simulation or
+    /// running any monitors.
+    pub fn potential_energy(&self) -> f64 {
+        self.potential_energy_of(&self.sim_data)
+    }
+
+    /// Compute the total potential energy of some configuration, using the universe's forces.
+    fn potential_energy_of(&self, sim_data: &SimData) -> f64 {
+        let verlet_lists = create_verlet_lists(sim_data, neighbor_cutoff(self.forces.deref(), sim_data));
+        let pair_energy: f64 = verlet_lists
+            .into_iter()
+            .map(|(id1, id2)| self.forces.pair_potential(sim_data, id1, id2))
+            .sum();
+        pair_energy + self.body_potential_energy(sim_data)
+    }
+
+    fn body_potential_energy(&self, sim_data: &SimData) -> f64 {
+        self.body_forces.iter().map(|force| force.potential_energy(sim_data)).sum()
+    }
+
+    /// Find the inherent structure of the current configuration, the local minimum of the potential energy that
+    /// steepest descent leads to from it. This removes the thermal noise from a snapshot. The minimization happens
+    /// on a copy, so the live simulation is untouched, and the velocities of the returned configuration are zero.
+    pub fn inherent_structure(&self) -> SimData {
+        let mut sim_data = self.sim_data.clone();
+        for v in sim_data.velocities.iter_mut() {
+            *v = Vector::zero();
+        }
+
+        // Steepest descent, with a step size that grows while the energy goes down, and shrinks when it does not.
+        let mut step_size = 1.0e-3;
+        let mut energy = self.potential_energy_of(&sim_data);
+        evaluate_forces(self.forces.deref(), &self.body_forces, &mut sim_data);
+        for _ in 0..MINIMIZATION_MAX_STEPS {
+            let max_force = sim_data.forces.iter().map(|f| f.length()).fold(0.0, f64::max);
+            if max_force < MINIMIZATION_FORCE_TOLERANCE {
+                break;
+            }
+
+            let previous_positions = sim_data.positions.clone();
+            for i in 0..sim_data.num_particles() {
+                if !sim_data.frozen[i] {
+                    sim_data.positions[i] += sim_data.forces[i] * step_size;
+                }
+            }
+            sim_data.canonical_positions();
+
+            let new_energy = self.potential_energy_of(&sim_data);
+            if new_energy <= energy {
+                energy = new_energy;
+                step_size *= 1.2;
+                evaluate_forces(self.forces.deref(), &self.body_forces, &mut sim_data);
+            } else {
+                sim_data.positions = previous_positions;
+                step_size *= 0.5;
+            }
+        }
+        sim_data
+    }
+
+    /// Compute the virial pressure of the current configuration, P = (N T + (1/2) sum_{i<j} r_ij . F_ij) / A, from the
+    /// kinetic temperature and the pairwise forces. Body forces are not included.
+    pub fn pressure(&self) -> f64 {
+        let verlet_lists = create_verlet_lists(&self.sim_data, neighbor_cutoff(self.forces.deref(), &self.sim_data));
+        let virial: f64 = verlet_lists
+            .into_iter()
+            .map(|(id1, id2)| {
+                let r = self.sim_data.displacement_between(id1, id2);
+                let f = self.forces.pair_force(&self.sim_data, id1, id2);
+                r.x * f.x + r.y * f.y
+            })
+            .sum();
+        let area = self.sim_data.width() * self.sim_data.height();
+        let n = self.sim_data.num_particles() as f64;
+        (n * self.sim_data.temperature() + 0.5 * virial) / area
+    }
+
+    /// Drive the virial pressure towards a target with a simple barostat: alternately run a few steps, and rescale the
+    /// box and the particles' positions, expanding the box if the pressure is above the target and compressing it if
+    /// it is below. The change in area is proportional to the relative pressure difference, up to a maximum, so the
+    /// box is adjusted gently. Runs for `max_iterations` adjustments.
+    pub fn equilibrate_pressure(&mut self, target_p: f64, max_iterations: usize) {
+        for _ in 0..max_iterations {
+            self.run_for_iterations(BAROSTAT_STEPS);
+            let pressure = self.pressure();
+            let scale = pressure.abs() + target_p.abs();
+            if scale == 0.0 {
+                continue;
+            }
+            let area_change = BAROSTAT_MAX_AREA_CHANGE * (pressure - target_p) / scale;
+            let f = f64::sqrt(1.0 + area_change);
+            self.sim_data.scale_box(f, f);
+        }
+    }
+
+    /// Measure the bulk modulus K = -V dP/dV by a finite difference: the virial pressure is measured, the box and the
+    /// particles' positions are compressed by a small fraction of the area, and the pressure is measured again. The
+    /// box and the positions are then restored.
+    pub fn bulk_modulus(&mut self) -> f64 {
+        const AREA_STRAIN: f64 = 1.0e-5;
+
+        let (bounds, positions) = (self.sim_data.bounds, self.sim_data.positions.clone());
+        let area = self.sim_data.width() * self.sim_data.height();
+        let pressure = self.pressure();
+
+        let f = f64::sqrt(1.0 - AREA_STRAIN);
+        self.sim_data.scale_box(f, f);
+        let compressed_area = self.sim_data.width() * self.sim_data.height();
+        let compressed_pressure = self.pressure();
+
+        self.sim_data.bounds = bounds;
+        self.sim_data.positions = positions;
+        -area * (compressed_pressure - pressure) / (compressed_area - area)
+    }
+
+    /// Compute the configurational temperature, T = sum_i |F_i|^2 / sum_i (-div_i F_i), which only depends on the
+    /// positions of the particles, so it is an independent check of the kinetic temperature. The divergence of each
+    /// pair force is estimated by central differences of the force, i.e. of the derivative of the pair potential.
+    /// Body forces are not included.
+    pub fn configurational_temperature(&mut self) -> f64 {
+        const STEP: f64 = 1.0e-6;
+
+        let verlet_lists = create_verlet_lists(&self.sim_data, neighbor_cutoff(self.forces.deref(), &self.sim_data));
+        force_loop(self.forces.deref(), &mut self.sim_data, &verlet_lists);
+        let force_sqr: f64 = self.sim_data.forces.iter().map(|f| f.length_sqr()).sum();
+
+        // The divergence, with respect to the second particle's position, of the force the first particle exerts on
+        // it. Each pair contributes it twice, once for each particle.
+        let mut divergence = 0.0;
+        for (id1, id2) in &verlet_lists {
+            let position = self.sim_data.positions[id2];
+            for direction in [Vector::new(STEP, 0.0), Vector::new(0.0, STEP)] {
+                self.sim_data.positions[id2] = position + direction;
+                let forward = self.forces.pair_force(&self.sim_data, id1, id2);
+                self.sim_data.positions[id2] = position - direction;
+                let backward = self.forces.pair_force(&self.sim_data, id1, id2);
+                let change = forward - backward;
+                divergence += 2.0 * (change.x * direction.x + change.y * direction.y) / (2.0 * STEP * STEP);
+            }
+            self.sim_data.positions[id2] = position;
+        }
+        -force_sqr / divergence
+    }
+
+    /// The compressibility factor Z = P A / (N T), using the virial pressure and the kinetic temperature. An ideal gas
+    /// has Z = 1, and repulsive interactions make Z > 1.
+    pub fn compressibility_factor(&self) -> f64 {
+        let area = self.sim_data.width() * self.sim_data.height();
+        let n = self.sim_data.num_particles() as f64;
+        self.pressure() * area / (n * self.sim_data.temperature())
+    }
+
+    /// Evaluate the forces for the current configuration, without integrating, and return the magnitude of the
+    /// net force on each particle. Useful for spotting particles that feel anomalously large forces.
+    pub fn force_histogram(&mut self) -> Vec<f64> {
+        evaluate_forces(self.forces.deref(), &self.body_forces, &mut self.sim_data);
+        self.sim_data.forces.iter().map(|f| f.length()).collect()
+    }
+
     fn run(&mut self) {
         // Make sure all particles start out in their canonical positions.
         self.sim_data.canonical_positions();
 
+        self.is_running = true;
         let start_time = Instant::now();
         while self.is_running {
             println!("Iteration {}, t = {}. There are {} particles.",
@@ -102,19 +437,21 @@ impl Universe {
                      self.sim_data.simulation_time,
                      self.sim_data.num_particles());
 
-            self.pre_step();
-
-            self.pre_forces();
-
-            self.forces();
-
-            self.post_forces();
-
-            self.post_step();
+            for i in 0..self.phases.len() {
+                match self.phases[i] {
+                    Phase::PreStep => self.pre_step(),
+                    Phase::PreForces => self.pre_forces(),
+                    Phase::Forces => self.forces(),
+                    Phase::PostForces => self.post_forces(),
+                    Phase::PostStep => self.post_step(),
+                }
+            }
 
             // Update iteration count.
             self.iterations += 1;
 
+            self.check_energy();
+
             // Check termination conditions.
             if let Some(max_time) = self.max_time {
                 if max_time < self.sim_data.simulation_time {
@@ -126,22 +463,80 @@ impl Universe {
                     self.is_running = false;
                 }
             }
+            if self.stop_condition.as_ref().is_some_and(|stop| stop(self)) {
+                self.is_running = false;
+            }
         }
-        self.total_time = start_time.elapsed().as_nanos();
+        self.total_time += start_time.elapsed().as_nanos();
     }
 
     pub fn run_until(&mut self, time: f64) {
         self.max_time = Some(time);
+        self.max_iterations = None;
         self.run();
     }
 
+    /// Run the simulation for a fixed number of iterations.
+    pub fn run_for_iterations(&mut self, iterations: i64) {
+        self.max_time = None;
+        self.max_iterations = Some(self.iterations + iterations);
+        self.run();
+    }
+
+    /// Run the simulation for a total time, during the first `warmup_time` of which the strength of the forces is
+    /// linearly ramped up from zero. This keeps imperfect initial configurations from exploding.
+    pub fn run_with_warmup(&mut self, warmup_time: f64, total_time: f64) {
+        let start_time = self.sim_data.simulation_time;
+        self.warmup = Some((start_time, warmup_time));
+        self.run_until(start_time + total_time);
+        self.warmup = None;
+    }
+
+    /// Relax the particles for a time with an overdamped integrator, which has the same timestep as the current
+    /// integrator, e.g. to remove overlaps before running dynamics. The current integrator is restored afterwards, and
+    /// the particles are left at rest.
     pub fn relax_for(&mut self, time: f64) {
-        // let relaxer = Universe::new(self.sim_data.bounds)
-        //     .with_simdata(self.sim_data.clone())
-        //     .with_forces(self.forces.clone())
-        //     .with_integrator(Box::new(OverdampedIntegrator::new(0.001, 5.0)));
+        let relaxer = Box::new(OverdampedIntegrator::new(self.timestep(), 5.0));
+        let integrator = std::mem::replace(&mut self.integrator, relaxer);
+        self.run_until(self.sim_data.simulation_time + time);
+        self.switch_integrator(integrator);
+    }
+
+    /// Rescale the velocities of the particles so that the kinetic temperature is exactly `temperature`. If the
+    /// particles are all at rest, they are first given random velocities.
+    pub fn set_temperature(&mut self, temperature: f64) {
+        if self.sim_data.temperature() == 0.0 {
+            for v in self.sim_data.velocities.iter_mut() {
+                *v = Vector::new(self.rng.gaussian(0.0, 1.0), self.rng.gaussian(0.0, 1.0));
+            }
+        }
+        let current = self.sim_data.temperature();
+        if current == 0.0 {
+            return;
+        }
+        let factor = f64::sqrt(temperature / current);
+        self.sim_data.velocities.iter_mut().for_each(|v| *v = *v * factor);
+    }
 
-        // TODO(Nate): Finish.
+    /// Run the steps of a protocol in order.
+    pub fn execute(&mut self, protocol: &Protocol) {
+        for step in protocol {
+            match *step {
+                ProtocolStep::Relax(time) => self.relax_for(time),
+                ProtocolStep::Run(time) => self.run_until(self.sim_data.simulation_time + time),
+                ProtocolStep::SetTemperature(temperature) => self.set_temperature(temperature),
+                ProtocolStep::ScaleBox(fx, fy) => self.sim_data.scale_box(fx, fy),
+                ProtocolStep::Quench { temperature, time } => {
+                    let (start_time, start_temperature) = (self.sim_data.simulation_time, self.sim_data.temperature());
+                    let num_stages = f64::max(f64::ceil(time / (QUENCH_STEPS * self.timestep())), 1.0);
+                    for stage in 1..=num_stages as usize {
+                        let fraction = stage as f64 / num_stages;
+                        self.set_temperature(start_temperature + fraction * (temperature - start_temperature));
+                        self.run_until(start_time + fraction * time);
+                    }
+                }
+            }
+        }
     }
 
     fn pre_step(&mut self) {
@@ -158,35 +553,601 @@ impl Universe {
 
         // Run all monitor objects.
         for (_, monitor) in self.monitors.iter_mut() {
-            monitor.pre_forces(&mut self.sim_data);
+            monitor.pre_forces(&self.sim_data);
         }
     }
 
     fn forces(&mut self) {
         let vl_now = Instant::now();
-        let verlet_lists = create_verlet_lists(&mut self.sim_data, 0.1);
+        let verlet_lists = create_verlet_lists(&self.sim_data, neighbor_cutoff(self.forces.deref(), &self.sim_data));
         self.verlet_lists_time += vl_now.elapsed().as_nanos();
+        if verlet_lists.box_too_small() && !self.box_too_small {
+            self.box_too_small = true;
+            eprintln!(
+                "Warning: the box ({} x {}) is less than twice the interaction range wide, so contacts with more \
+                 than one periodic image of the same particle are missed.",
+                self.sim_data.width(), self.sim_data.height()
+            );
+        }
 
         let fl_now = Instant::now();
-        force_loop(self.forces.deref(), &mut self.sim_data, &verlet_lists);
+        if let Some((start_time, warmup_time)) = self.warmup {
+            let scale = f64::clamp((self.sim_data.simulation_time - start_time) / warmup_time, 0.0, 1.0);
+            let force = ScaledForce { inner: self.forces.deref(), scale };
+            force_loop(&force, &mut self.sim_data, &verlet_lists);
+        } else {
+            force_loop(self.forces.deref(), &mut self.sim_data, &verlet_lists);
+        }
+        for force in self.body_forces.iter() {
+            force.apply_body_forces(&mut self.sim_data);
+        }
+        self.sim_data.apply_constraints();
+        if self.energy_check.is_some() {
+            let pair_energy: f64 = verlet_lists
+                .into_iter()
+                .map(|(id1, id2)| self.forces.pair_potential(&self.sim_data, id1, id2))
+                .sum();
+            self.step_potential_energy = pair_energy + self.body_potential_energy(&self.sim_data);
+        }
         self.forces_time += fl_now.elapsed().as_nanos();
     }
 
     fn post_forces(&mut self) {
+        let ig_now = Instant::now();
         self.integrator.post_forces(&mut self.sim_data);
+        self.integrator_time += ig_now.elapsed().as_nanos();
 
         // Run all monitor objects.
         for (_, monitor) in self.monitors.iter_mut() {
-            monitor.post_forces(&mut self.sim_data);
+            monitor.post_forces(&self.sim_data);
         }
     }
 
     fn post_step(&mut self) {
+        let ig_now = Instant::now();
+        // Integrators that take substeps advance the time by less than their timestep.
+        let start_time = self.sim_data.simulation_time;
         self.integrator.post_step(&mut self.sim_data);
+        self.sim_data.topology.advance(self.sim_data.simulation_time - start_time);
+        self.sim_data.apply_constraints();
+        self.integrator_time += ig_now.elapsed().as_nanos();
 
         // Run all monitor objects.
         for (_, monitor) in self.monitors.iter_mut() {
-            monitor.post_step(&mut self.sim_data);
+            monitor.post_step(&self.sim_data);
+        }
+    }
+
+    fn check_energy(&mut self) {
+        let Some((multiplier, stop)) = self.energy_check else {
+            return;
+        };
+        let energy = self.kinetic_energy() + self.step_potential_energy;
+        let initial_energy = *self.initial_energy.get_or_insert(energy);
+
+        if energy.is_nan() || multiplier * initial_energy.abs() < energy.abs() {
+            self.became_unstable = true;
+            if stop {
+                self.is_running = false;
+            }
+        }
+    }
+}
+
+/// The cutoff to build verlet lists with for a force: the skin, widened by however far beyond contact the force
+/// reaches.
+fn neighbor_cutoff(force: &dyn Force, sim_data: &SimData) -> f64 {
+    VERLET_SKIN + force.extra_reach(sim_data)
+}
+
+/// Fill the force buffer of a SimData with the pairwise forces and the body forces.
+fn evaluate_forces(force: &dyn Force, body_forces: &[Box<dyn BodyForce>], sim_data: &mut SimData) {
+    let verlet_lists = create_verlet_lists(sim_data, neighbor_cutoff(force, sim_data));
+    force_loop(force, sim_data, &verlet_lists);
+    for body_force in body_forces.iter() {
+        body_force.apply_body_forces(sim_data);
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::{LeesEdwardsTopology, ReflectingTopology, ShearProtocol};
+    use crate::core::vector::Position;
+    use crate::utils::init::square_lattice;
+
+    #[test]
+    fn test_potential_energy() {
+        let mut universe = Universe::new(Bounds::from((0.0, 10.0, 0.0, 10.0)));
+        let particles = vec![
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.8, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(2.0, 2.0).with_radius(0.5).to_owned(),
+        ];
+        universe.sim_data.add_particles(&particles);
+
+        // The repulsion is 100, and the overlap is 0.2.
+        assert_close!(universe.potential_energy(), 0.5 * 100.0 * 0.2 * 0.2, 1.0e-9);
+        assert_eq!(universe.sim_data.simulation_time, 0.0);
+        assert_eq!(universe.iterations, 0);
+    }
+
+    #[test]
+    fn test_anisotropic_contacts_beyond_skin() {
+        // The particles are 1.2 apart along the x axis, further than the sum of their radii plus the skin, but the
+        // elliptical contact distance along x is 1.5.
+        let mut universe = Universe::new(Bounds::from((0.0, 10.0, 0.0, 10.0)));
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(4.4, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.6, 5.0).with_radius(0.5).to_owned(),
+        ]);
+        universe.forces = Box::new(HardSphereForce::new(100.0).with_anisotropy(1.5, 0.0).to_owned());
+        assert_close!(universe.potential_energy(), 0.5 * 100.0 * 0.3 * 0.3, 1.0e-9);
+    }
+
+    #[test]
+    fn test_force_histogram() {
+        let mut universe = Universe::new(Bounds::from((0.0, 10.0, 0.0, 10.0)));
+        let particles = vec![
+            Particle::new().with_coords(2.0, 2.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(2.98, 2.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(6.0, 6.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(6.5, 6.5).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(8.0, 2.0).with_radius(0.5).to_owned(),
+        ];
+        universe.sim_data.add_particles(&particles);
+
+        let histogram = universe.force_histogram();
+        assert_eq!(histogram.len(), 5);
+        assert_close!(histogram[0], 2.0, 1.0e-9);
+        assert_close!(histogram[1], 2.0, 1.0e-9);
+        assert_eq!(histogram[4], 0.0);
+
+        // The heavily overlapping pair dominates.
+        let expected = 100.0 * (1.0 - f64::sqrt(0.5));
+        assert_close!(histogram[2], expected, 1.0e-9);
+        assert_close!(histogram[3], expected, 1.0e-9);
+        assert!(10.0 * histogram[0] < histogram[2]);
+        assert_eq!(universe.sim_data.simulation_time, 0.0);
+    }
+
+    fn run_seeded(seed: u64) -> Vec<Position> {
+        let mut universe = Universe::new_seeded(Bounds::from((0.0, 4.0, 0.0, 4.0)), seed);
+        let mut particles = Vec::new();
+        for _ in 0..20 {
+            let rng = universe.rng();
+            particles.push(Particle::new()
+                .with_coords(rng.uniform(0.0, 4.0), rng.uniform(0.0, 4.0))
+                .with_velocity_components(rng.gaussian(0.0, 1.0), rng.gaussian(0.0, 1.0))
+                .with_radius(0.2)
+                .to_owned());
         }
+        universe.sim_data.add_particles(&particles);
+        universe.run_for_iterations(50);
+        universe.sim_data.positions.clone()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_seeded_universes_are_reproducible() {
+        let positions1 = run_seeded(11);
+        let positions2 = run_seeded(11);
+        for (p1, p2) in positions1.iter().zip(positions2.iter()) {
+            assert_eq!(p1.x, p2.x);
+            assert_eq!(p1.y, p2.y);
+        }
+
+        let positions3 = run_seeded(12);
+        assert!(positions1.iter().zip(positions3.iter()).any(|(p1, p3)| p1.x != p3.x));
+    }
+
+    #[test]
+    fn test_new_with_timestep() {
+        let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 4.0, 0.0, 4.0)), 0.002);
+        assert_eq!(universe.timestep(), 0.002);
+        universe.run_for_iterations(1);
+        assert_close!(universe.sim_data.simulation_time, 0.002, 1.0e-12);
+        universe.run_for_iterations(9);
+        assert_close!(universe.sim_data.simulation_time, 0.02, 1.0e-12);
+
+        // The default timestep is unchanged.
+        assert_eq!(Universe::new(Bounds::from((0.0, 4.0, 0.0, 4.0))).timestep(), 0.001);
+    }
+
+    /// Records the magnitude of the force on the first particle after every force evaluation.
+    struct ForceRecorder {
+        records: Vec<(f64, f64)>,
+    }
+
+    impl Monitor for ForceRecorder {
+        fn post_forces(&mut self, sim_data: &SimData) {
+            self.records.push((sim_data.simulation_time, sim_data.forces[0].length()));
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[test]
+    fn test_run_with_warmup() {
+        // An overlapping pair of particles that are so heavy they do not move.
+        let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 10.0, 0.0, 10.0)), 0.01);
+        let particles = vec![
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.5).with_mass(1.0e12).to_owned(),
+            Particle::new().with_coords(5.8, 5.0).with_radius(0.5).with_mass(1.0e12).to_owned(),
+        ];
+        universe.sim_data.add_particles(&particles);
+        universe.add_monitor("Forces", Box::new(ForceRecorder { records: vec![] }));
+
+        universe.run_with_warmup(0.5, 1.0);
+
+        let records = &universe
+            .get_monitor("Forces")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ForceRecorder>()
+            .unwrap()
+            .records;
+        let full_force = 100.0 * 0.2;
+        for &(time, force) in records.iter() {
+            if time < 0.5 {
+                assert_close!(force, full_force * time / 0.5, 1.0e-6);
+            } else {
+                assert_close!(force, full_force, 1.0e-6);
+            }
+        }
+        assert!(records.iter().any(|&(time, _)| 0.2 < time && time < 0.3));
+    }
+
+    #[test]
+    fn test_switch_integrator() {
+        let mut universe = compressed_lattice(0.001);
+        universe.with_integrator(Box::new(OverdampedIntegrator::new(0.001, 1.0)));
+        universe.run_for_iterations(100);
+        let time = universe.sim_data.simulation_time;
+        let positions = universe.sim_data.positions.clone();
+
+        universe.switch_integrator(Box::new(VelocityVerlet::new(0.002)));
+        assert_eq!(universe.timestep(), 0.002);
+        assert!(universe.sim_data.velocities.iter().all(|v| v.length_sqr() == 0.0));
+        assert!(universe.sim_data.positions.iter().zip(positions.iter()).all(|(p, q)| p.x == q.x && p.y == q.y));
+
+        // Starting from rest, the particles barely move in the first step.
+        universe.run_for_iterations(1);
+        assert_close!(universe.sim_data.simulation_time, time + 0.002, 1.0e-12);
+        for (p, q) in universe.sim_data.positions.iter().zip(positions.iter()) {
+            assert!(universe.sim_data.minimum_image(*p - *q).length() < 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn test_execute_protocol() {
+        // Two pairs of overlapping particles, which the relaxation pushes apart.
+        let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 10.0, 0.0, 10.0)), 0.001);
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(2.0, 2.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(2.6, 2.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(6.0, 6.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(6.0, 6.7).with_radius(0.5).to_owned(),
+        ]);
+        let energy = universe.potential_energy();
+        assert!(0.0 < energy);
+
+        universe.execute(&vec![ProtocolStep::Relax(0.5), ProtocolStep::Run(0.25)]);
+        assert_close!(universe.sim_data.simulation_time, 0.75, 1.0e-9);
+        assert!(universe.potential_energy() < 0.01 * energy);
+        // The relaxation leaves the particles at rest, and dynamics resume with the original integrator.
+        assert!(universe.integrator.updates_velocities());
+        assert_eq!(universe.timestep(), 0.001);
+    }
+
+    #[test]
+    fn test_set_temperature() {
+        let mut universe = compressed_lattice(0.001);
+        universe.execute(&vec![ProtocolStep::SetTemperature(0.5)]);
+        assert_close!(universe.sim_data.temperature(), 0.5, 1.0e-12);
+
+        universe.execute(&vec![ProtocolStep::Quench { temperature: 0.0, time: 0.1 }]);
+        assert_close!(universe.sim_data.simulation_time, 0.1, 1.0e-9);
+        assert!(universe.sim_data.temperature() < 0.05);
+    }
+
+    /// A compressed square lattice of particles, with small random velocities.
+    fn compressed_lattice(dt: f64) -> Universe {
+        let bounds = Bounds::from((0.0, 5.4, 0.0, 5.4));
+        let mut universe = Universe::new_seeded(bounds, 5);
+        universe.with_timestep(dt);
+        let mut particles = square_lattice(6, 6, bounds, 0.5);
+        for p in particles.iter_mut() {
+            p.with_velocity_components(universe.rng().gaussian(0.0, 0.1), universe.rng().gaussian(0.0, 0.1));
+        }
+        universe.sim_data.add_particles(&particles);
+        universe
+    }
+
+    #[test]
+    fn test_energy_check() {
+        let mut universe = compressed_lattice(0.001);
+        universe.with_energy_check(2.0, true);
+        universe.run_for_iterations(200);
+        assert!(!universe.became_unstable);
+        assert_eq!(universe.iterations, 200);
+
+        // Far too large a timestep for the stiff contacts.
+        let mut universe = compressed_lattice(0.3);
+        universe.with_energy_check(2.0, true);
+        universe.run_for_iterations(200);
+        assert!(universe.became_unstable);
+        assert!(universe.iterations < 200);
+    }
+
+    #[test]
+    fn test_box_too_small_is_recorded() {
+        // Particles of radius 0.5 interact out to 1 + VERLET_SKIN, more than half of the width of the box.
+        let small_box = |periodic: bool| {
+            let mut universe = Universe::new(Bounds::from((0.0, 2.0, 0.0, 10.0)));
+            universe.sim_data.add_particles(&[
+                Particle::new().with_coords(0.5, 5.0).with_radius(0.5).to_owned(),
+                Particle::new().with_coords(1.5, 5.0).with_radius(0.5).to_owned(),
+            ]);
+            if !periodic {
+                universe.sim_data.set_topology(Box::new(ReflectingTopology {}));
+            }
+            universe.run_for_iterations(1);
+            universe.box_too_small
+        };
+        assert!(small_box(true));
+        // Periodic images do not matter in a box with walls.
+        assert!(!small_box(false));
+    }
+
+    #[test]
+    fn test_set_active_region() {
+        let mut universe = Universe::new(Bounds::from((0.0, 10.0, 0.0, 10.0)));
+        universe.sim_data.add_particles(&[
+            // A moving particle outside the active region.
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.5).with_velocity_components(1.0, 0.0).to_owned(),
+            // Two overlapping particles straddling the edge of the active region.
+            Particle::new().with_coords(4.6, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.4, 5.0).with_radius(0.5).to_owned(),
+        ]);
+        universe.set_active_region(Bounds::from((5.0, 10.0, 0.0, 10.0)));
+        universe.run_for_iterations(100);
+
+        let positions = &universe.sim_data.positions;
+        assert_eq!((positions[0].x, positions[0].y), (1.0, 1.0));
+        assert_eq!((positions[1].x, positions[1].y), (4.6, 5.0));
+        // The active particle is pushed away by its frozen neighbor.
+        assert!(5.4 < positions[2].x);
+    }
+
+    #[test]
+    fn test_timing_summary() {
+        let mut universe = compressed_lattice(0.001);
+        universe.run_for_iterations(20);
+
+        let summary = universe.timing_summary();
+        assert!(summary.starts_with("Forces: "));
+        let total: f64 = summary
+            .split(", ")
+            .map(|entry| entry.split(": ").nth(1).unwrap().trim_end_matches('%').parse::<f64>().unwrap())
+            .sum();
+        assert_close!(total, 100.0, 0.5);
+    }
+
+    #[test]
+    fn test_compressibility_factor() {
+        // A dilute gas of particles that do not touch.
+        let mut universe = Universe::new_seeded(Bounds::from((0.0, 10.0, 0.0, 10.0)), 3);
+        let mut particles = square_lattice(5, 5, universe.sim_data.bounds, 0.1);
+        for p in particles.iter_mut() {
+            p.with_velocity_components(universe.rng().gaussian(0.0, 1.0), universe.rng().gaussian(0.0, 1.0));
+        }
+        universe.sim_data.add_particles(&particles);
+        assert_close!(universe.compressibility_factor(), 1.0, 1.0e-9);
+
+        // Overlapping, repulsive, particles raise the pressure.
+        let universe = compressed_lattice(0.001);
+        assert!(1.0 < universe.compressibility_factor());
+    }
+
+    #[test]
+    fn test_stop_condition() {
+        let mut universe = compressed_lattice(0.001);
+        universe.set_stop_condition(Box::new(|universe| 10 <= universe.iterations));
+        universe.run_until(1.0);
+        assert_eq!(universe.iterations, 10);
+    }
+
+    #[test]
+    fn test_custom_phases() {
+        // Two overlapping particles, initially at rest, pushing each other apart with a force of 20.
+        let make_universe = || {
+            let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 10.0, 0.0, 10.0)), 0.01);
+            universe.sim_data.add_particles(&[
+                Particle::new().with_coords(4.6, 5.0).with_radius(0.5).to_owned(),
+                Particle::new().with_coords(5.4, 5.0).with_radius(0.5).to_owned(),
+            ]);
+            universe
+        };
+
+        // By default, the first half kick uses the force buffer, which has not been filled yet, so only the second
+        // half kick moves the particles.
+        let mut default = make_universe();
+        assert_eq!(default.phases, DEFAULT_PHASES.to_vec());
+        default.run_for_iterations(1);
+        assert_close!(default.sim_data.velocities[1].x, 0.1, 1.0e-9);
+        assert_eq!(default.sim_data.positions[1].x, 5.4);
+
+        // Evaluating the forces at the start of the step as well gives both half kicks.
+        let mut custom = make_universe();
+        custom.with_phases(vec![
+            Phase::PreStep, Phase::Forces, Phase::PreForces, Phase::Forces, Phase::PostForces, Phase::PostStep,
+        ]);
+        custom.run_for_iterations(1);
+        assert_close!(custom.sim_data.velocities[1].x, 0.199, 1.0e-9);
+        assert_close!(custom.sim_data.positions[1].x, 5.401, 1.0e-9);
+    }
+
+    #[test]
+    fn test_inherent_structure() {
+        // Randomly placed particles, with many overlaps.
+        let mut universe = Universe::new_seeded(Bounds::from((0.0, 5.0, 0.0, 5.0)), 17);
+        let particles: Vec<Particle> = (0..20)
+            .map(|_| {
+                let (x, y) = (universe.rng().uniform(0.0, 5.0), universe.rng().uniform(0.0, 5.0));
+                Particle::new().with_coords(x, y).with_radius(0.5).with_velocity_components(1.0, 0.0).to_owned()
+            })
+            .collect();
+        universe.sim_data.add_particles(&particles);
+        let live_positions: Vec<(f64, f64)> = universe.sim_data.positions.iter().map(|p| (p.x, p.y)).collect();
+
+        let inherent = universe.inherent_structure();
+        let mut minimized = Universe::new(inherent.bounds);
+        minimized.with_simdata(inherent);
+        assert!(minimized.potential_energy() < universe.potential_energy());
+        assert!(minimized.force_histogram().iter().all(|f| *f < 1.0e-6));
+        assert!(minimized.sim_data.velocities.iter().all(|v| v.length() == 0.0));
+
+        // The live simulation is untouched.
+        let positions: Vec<(f64, f64)> = universe.sim_data.positions.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(positions, live_positions);
+    }
+
+    #[test]
+    fn test_lock_y() {
+        // Particles that would be pushed apart diagonally in 2D.
+        let mut universe = Universe::new(Bounds::from((0.0, 10.0, 0.0, 10.0)));
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(4.6, 4.8).with_radius(0.5).with_velocity_components(0.0, 1.0).to_owned(),
+            Particle::new().with_coords(5.4, 5.2).with_radius(0.5).to_owned(),
+        ]);
+        universe.sim_data.lock_y(5.0);
+        universe.run_until(0.5);
+
+        assert!(universe.sim_data.positions[0].x < 4.6);
+        assert!(5.4 < universe.sim_data.positions[1].x);
+        for i in 0..2 {
+            assert_eq!(universe.sim_data.positions[i].y, 5.0);
+            assert_eq!(universe.sim_data.velocities[i].y, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_configurational_temperature() {
+        let mut universe = compressed_lattice(0.002);
+        universe.run_until(5.0);
+
+        // Average both temperatures over the equilibrated run.
+        let (mut kinetic, mut configurational) = (0.0, 0.0);
+        for i in 0..200 {
+            universe.run_until(5.0 + 0.05 * (i + 1) as f64);
+            kinetic += universe.sim_data.temperature();
+            configurational += universe.configurational_temperature();
+        }
+        assert_close!(configurational / kinetic, 1.0, 0.1);
+
+        // A perfect, force free lattice has no configurational temperature.
+        let mut lattice = compressed_lattice(0.002);
+        assert_close!(lattice.configurational_temperature(), 0.0, 1.0e-12);
+    }
+
+    #[test]
+    fn test_bulk_modulus() {
+        // In a square lattice with spacing a, compressed by harmonic contacts with stiffness k and a contact distance
+        // of sigma, P = k (sigma - a) / a, so K = k sigma / 2a, plus a small kinetic part, N T / V.
+        let mut universe = compressed_lattice(0.001);
+        let positions: Vec<(f64, f64)> = universe.sim_data.positions.iter().map(|p| (p.x, p.y)).collect();
+        let modulus = universe.bulk_modulus();
+        let n_over_v = 36.0 / (5.4 * 5.4);
+        assert_close!(modulus, 100.0 / 1.8 + n_over_v * universe.sim_data.temperature(), 1.0e-2);
+
+        // The box and the particles are restored.
+        assert_eq!(universe.sim_data.bounds.xhi, 5.4);
+        assert_eq!(universe.sim_data.bounds.ylo, 0.0);
+        let restored: Vec<(f64, f64)> = universe.sim_data.positions.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(restored, positions);
+    }
+
+    #[test]
+    fn test_equilibrate_pressure() {
+        let mut universe = compressed_lattice(0.001);
+        let (initial_area, initial_pressure) = (5.4 * 5.4, universe.pressure());
+        let target = 2.0;
+        assert!(5.0 * target < initial_pressure);
+
+        universe.equilibrate_pressure(target, 100);
+        let area = universe.sim_data.width() * universe.sim_data.height();
+        assert!(initial_area < area);
+        let early_difference = (universe.pressure() - target).abs();
+        assert!(early_difference < initial_pressure - target);
+
+        // Average out the thermal fluctuations of the pressure.
+        let mut pressure = 0.0;
+        for _ in 0..50 {
+            universe.equilibrate_pressure(target, 2);
+            pressure += universe.pressure() / 50.0;
+        }
+        assert_close!(pressure, target, 0.2 * target);
+    }
+
+    #[test]
+    fn test_oscillatory_shear() {
+        let (amplitude, omega) = (0.05, 2.0 * std::f64::consts::PI / 1.5);
+        let mut universe = Universe::new_with_timestep(Bounds::from((0.0, 5.0, 0.0, 5.0)), 0.01);
+        universe.sim_data.add_particle(Particle::new().with_coords(2.5, 2.5).with_radius(0.1));
+        universe.sim_data.set_topology(Box::new(LeesEdwardsTopology::new(ShearProtocol::OscillatoryShear {
+            amplitude,
+            omega,
+        })));
+
+        // The strain follows amplitude * sin(omega t), through two periods of 1.5.
+        let mut strains = vec![];
+        for step in 1..=300 {
+            universe.run_for_iterations(1);
+            let strain = universe.sim_data.topology.strain();
+            assert_close!(strain, amplitude * f64::sin(omega * 0.01 * step as f64), 1.0e-12);
+            strains.push(strain);
+        }
+        assert_close!(strains.iter().copied().fold(f64::MIN, f64::max), amplitude, 1.0e-4);
+        assert_close!(strains.iter().copied().fold(f64::MAX, f64::min), -amplitude, 1.0e-4);
+        assert_close!(strains[149], 0.0, 1.0e-12);
+    }
+
+    #[test]
+    fn test_oscillatory_shear_with_tiers() {
+        // With two tiers, each iteration is a substep of half the timestep, and the strain follows the time taken.
+        let (amplitude, omega) = (0.05, 2.0 * std::f64::consts::PI / 1.5);
+        let mut universe = Universe::new(Bounds::from((0.0, 5.0, 0.0, 5.0)));
+        universe.with_integrator(Box::new(VelocityVerlet::new(0.02).with_tiers(vec![1]).to_owned()));
+        universe.sim_data.add_particle(Particle::new().with_coords(2.5, 2.5).with_radius(0.1));
+        universe.sim_data.set_topology(Box::new(LeesEdwardsTopology::new(ShearProtocol::OscillatoryShear {
+            amplitude,
+            omega,
+        })));
+
+        for step in 1..=150 {
+            universe.run_for_iterations(1);
+            assert_close!(universe.sim_data.simulation_time, 0.01 * step as f64, 1.0e-9);
+            let strain = universe.sim_data.topology.strain();
+            assert_close!(strain, amplitude * f64::sin(omega * 0.01 * step as f64), 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn test_set_timestep() {
+        let mut universe = Universe::new(Bounds::from((0.0, 4.0, 0.0, 4.0)));
+        universe.set_timestep(0.01);
+        assert_eq!(universe.timestep(), 0.01);
+
+        universe.run_for_iterations(5);
+        assert_close!(universe.sim_data.simulation_time, 0.05, 1.0e-9);
+    }
+}
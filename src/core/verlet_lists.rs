@@ -1,5 +1,13 @@
+use std::collections::HashSet;
+use rayon::prelude::*;
+use crate::core::kd_tree::KdTree;
 use crate::core::linked_cells::LinkedCells;
 use crate::core::simdata::SimData;
+use crate::core::vector::Vector;
+
+/// If there are fewer particles than this per cell on average, `create_verlet_lists` uses a KD-tree instead of linked
+/// cells, since the search would spend most of its time looking through empty cells.
+const KD_TREE_OCCUPANCY: f64 = 0.05;
 
 /// A verlet lists structure, that stores particles that are "close" to one another.
 pub struct VerletLists {
@@ -9,6 +17,23 @@ pub struct VerletLists {
 
     /// The number of potential pairs in the verlet lists object.
     num_pairs: usize,
+
+    /// Whether the box was too small for the minimum image convention when the lists were built, in which case
+    /// contacts with more than one periodic image of the same particle are missing.
+    box_too_small: bool,
+}
+
+impl VerletLists {
+    /// The number of potential pairs in the verlet lists.
+    pub fn num_pairs(&self) -> usize {
+        self.num_pairs
+    }
+
+    /// Whether the box was too small for the minimum image convention when the lists were built. See
+    /// `box_too_small_for_cutoff`.
+    pub fn box_too_small(&self) -> bool {
+        self.box_too_small
+    }
 }
 
 impl From<Vec<(usize, Vec<usize>)>> for VerletLists {
@@ -17,6 +42,7 @@ impl From<Vec<(usize, Vec<usize>)>> for VerletLists {
         VerletLists {
             verlet_lists: value,
             num_pairs,
+            box_too_small: false,
         }
     }
 }
@@ -53,7 +79,7 @@ impl<'a> Iterator for VLIter<'a> {
 
         let r = Some((vl[self.head_count].0, vl[self.head_count].1[self.secondary_count]));
         self.secondary_count += 1;
-        return r;
+        r
     }
 }
 
@@ -63,7 +89,7 @@ impl<'a> IntoIterator for &'a VerletLists {
 
     fn into_iter(self) -> Self::IntoIter {
         VLIter {
-            verlet_lists: &self,
+            verlet_lists: self,
             head_count: 0,
             secondary_count: 0,
         }
@@ -88,90 +114,287 @@ pub fn check_neighbors(
     }
 }
 
+/// How to choose the size of the cells that particles are binned into when creating verlet lists.
+///
+/// Too large cells slow down the search, since many particles in neighboring cells are too far away to interact.
+/// Cells smaller than the largest interaction distance (twice the largest radius plus the cutoff) would miss
+/// neighbors, so the search stencil is expanded to reach as many cells as needed, at the cost of checking more cells.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellSizingStrategy {
+    /// Cells that are as wide as the largest interaction distance, so only adjacent cells need to be searched.
+    MaxRadius,
+    /// Cells that are as wide as the interaction distance of two particles with the mean radius. Better for
+    /// polydisperse systems with a few large particles.
+    MeanRadius,
+    /// Cells with a fixed target size.
+    Fixed(f64),
+}
+
+/// The data structure used to find the particles near each particle when creating verlet lists.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NeighborSearch {
+    /// Bin the particles into a uniform grid of cells, and search the nearby cells. Best for dense systems.
+    CellList,
+    /// Search a KD-tree of the particles. Best for very dilute or very non-uniform systems, where most cells of a
+    /// uniform grid would be empty.
+    KdTree,
+}
+
+/// Create verlet lists, using linked cells, or a KD-tree if the cells would be nearly empty.
 pub fn create_verlet_lists(sim_data: &SimData, cutoff: f64) -> VerletLists {
+    create_verlet_lists_with_search(sim_data, cutoff, choose_neighbor_search(sim_data, cutoff))
+}
+
+/// Choose the neighbor search for a SimData: a KD-tree if there would be fewer than `KD_TREE_OCCUPANCY` particles per
+/// cell of the linked cells, and the linked cells otherwise.
+pub fn choose_neighbor_search(sim_data: &SimData, cutoff: f64) -> NeighborSearch {
+    if sim_data.is_empty() {
+        return NeighborSearch::CellList;
+    }
+    let max_radius = sim_data.radii.iter().copied().fold(0.0, f64::max);
+    let max_interaction = 2.0 * max_radius + cutoff;
+    let num_cells = f64::max(1.0, (sim_data.width() / max_interaction).floor())
+        * f64::max(1.0, (sim_data.height() / max_interaction).floor());
+    if (sim_data.num_particles() as f64) < KD_TREE_OCCUPANCY * num_cells {
+        NeighborSearch::KdTree
+    } else {
+        NeighborSearch::CellList
+    }
+}
+
+/// Create verlet lists with a particular neighbor search. Both searches find the same pairs.
+pub fn create_verlet_lists_with_search(sim_data: &SimData, cutoff: f64, search: NeighborSearch) -> VerletLists {
+    match search {
+        NeighborSearch::CellList => {
+            create_verlet_lists_with_cell_sizing(sim_data, cutoff, CellSizingStrategy::MaxRadius)
+        }
+        NeighborSearch::KdTree => create_verlet_lists_kd_tree(sim_data, cutoff),
+    }
+}
+
+/// Create verlet lists by searching a KD-tree of the particles' positions. Periodic images are found by also
+/// searching around the images of each particle shifted by the box size, and by the topology's image offset for the
+/// images above and below the box.
+fn create_verlet_lists_kd_tree(sim_data: &SimData, cutoff: f64) -> VerletLists {
+    if sim_data.is_empty() {
+        return VerletLists::from(Vec::new());
+    }
+
+    let tree = KdTree::new(sim_data.positions.iter().copied().zip(0..).collect());
+    let max_radius = sim_data.radii.iter().copied().fold(0.0, f64::max);
+    let (width, height) = (sim_data.width(), sim_data.height());
+    let offset = sim_data.topology.image_offset(&sim_data.bounds);
+
+    let mut verlet_lists = Vec::new();
+    for id1 in 0..sim_data.num_particles() {
+        let reach = sim_data.radii[id1] + max_radius + cutoff;
+        let mut neighbors = Vec::new();
+        for dx in [-width, 0.0, width] {
+            for (dy, shift) in [(-height, -offset), (0.0, 0.0), (height, offset)] {
+                let image = sim_data.positions[id1] + Vector::new(dx + shift, dy);
+                let candidates: Vec<usize> = tree.within(image, reach).into_iter().filter(|&id2| id1 < id2).collect();
+                check_neighbors(id1, &candidates, sim_data, &mut neighbors, cutoff);
+            }
+        }
+        // In small boxes, a particle can be found around more than one image.
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        if !neighbors.is_empty() {
+            verlet_lists.push((id1, neighbors));
+        }
+    }
+    finish_verlet_lists(sim_data, cutoff, verlet_lists)
+}
+
+/// Create verlet lists, choosing the size of the cells used for the search with some strategy.
+///
+/// If the box is so small that there are too few cells for the ghost cells to hold the periodic images, the periodic
+/// images are searched for explicitly, with a KD-tree, instead.
+pub fn create_verlet_lists_with_cell_sizing(
+    sim_data: &SimData,
+    cutoff: f64,
+    strategy: CellSizingStrategy,
+) -> VerletLists {
     let mut verlet_lists = Vec::new(); // : Vec<(i32, Vec<i32>)>
                                        // If there are no particles, there is nothing to do.
     if sim_data.is_empty() {
         return VerletLists::from(verlet_lists);
     }
 
-    // Bin particles into sectors.
+    // Create verlet lists from the linked cells.
+    let (linked_cells, stencil) = bin_particles(sim_data, cutoff, strategy);
+    if !linked_cells.ghost_cells_wrap() {
+        return create_verlet_lists_kd_tree(sim_data, cutoff);
+    }
+    for ix in 0..linked_cells.get_num_x() {
+        for iy in 0..linked_cells.get_num_y() {
+            cell_verlet_lists(&linked_cells, &stencil, ix, iy, sim_data, cutoff, &mut verlet_lists);
+        }
+    }
+
+    finish_verlet_lists(sim_data, cutoff, verlet_lists)
+}
+
+/// Create verlet lists like `create_verlet_lists`, but with the columns of cells split between rayon threads.
+///
+/// Each pair is only ever found from one cell, the one whose half stencil covers the other particle's cell (ghost
+/// cells included), so the threads can build lists for their cells independently, with no pair missed or counted
+/// twice. The thread-local lists are concatenated in cell order, giving the same lists as the serial version.
+pub fn create_verlet_lists_parallel(sim_data: &SimData, cutoff: f64) -> VerletLists {
+    if sim_data.is_empty() {
+        return VerletLists::from(Vec::new());
+    }
+
+    let (linked_cells, stencil) = bin_particles(sim_data, cutoff, CellSizingStrategy::MaxRadius);
+    let verlet_lists: Vec<(usize, Vec<usize>)> = (0..linked_cells.get_num_x())
+        .into_par_iter()
+        .flat_map_iter(|ix| {
+            let mut column_lists = Vec::new();
+            for iy in 0..linked_cells.get_num_y() {
+                cell_verlet_lists(&linked_cells, &stencil, ix, iy, sim_data, cutoff, &mut column_lists);
+            }
+            column_lists
+        })
+        .collect();
+
+    finish_verlet_lists(sim_data, cutoff, verlet_lists)
+}
+
+/// Check whether the box is too small for the minimum image convention: if it is narrower than twice the largest
+/// interaction distance in a periodic dimension, a particle can interact with several periodic images of the same
+/// neighbor, but verlet lists only ever contain the nearest one, so the other contacts are silently dropped.
+pub fn box_too_small_for_cutoff(sim_data: &SimData, cutoff: f64) -> bool {
+    let max_radius = sim_data.radii.iter().copied().fold(0.0, f64::max);
+    let max_interaction = 2.0 * max_radius + cutoff;
+    let (periodic_x, periodic_y) = sim_data.topology.periodic();
+    (periodic_x && sim_data.width() < 2.0 * max_interaction)
+        || (periodic_y && sim_data.height() < 2.0 * max_interaction)
+}
+
+/// Make verlet lists from the lists found by a search, recording whether the box was too small for them to hold every
+/// contact.
+fn finish_verlet_lists(sim_data: &SimData, cutoff: f64, verlet_lists: Vec<(usize, Vec<usize>)>) -> VerletLists {
+    let mut verlet_lists = VerletLists::from(verlet_lists);
+    verlet_lists.box_too_small = box_too_small_for_cutoff(sim_data, cutoff);
+    verlet_lists
+}
+
+/// Bin the particles into a linked cells structure, with cells sized by some strategy. Returns the linked cells and
+/// the half stencil of cell offsets that must be searched to find all pairs within the cutoff. If the linked cells
+/// have too few cells for their ghost cells to wrap, pairs across the periodic boundaries are not found with them.
+fn bin_particles(sim_data: &SimData, cutoff: f64, strategy: CellSizingStrategy) -> (LinkedCells, Vec<(i32, i32)>) {
     // NOTE: We need a characteristic radius to create bins. For systems with roughly equally sized
-    // particles, we can use the max particle radius.
+    // particles, we can use the max particle radius. The stencil must reach at least as far as the largest
+    // possible interaction distance.
     let max_radius = sim_data.radii.iter().copied().fold(f64::NAN, f64::max);
+    let max_interaction = 2.0 * max_radius + cutoff;
+    let target_size = match strategy {
+        CellSizingStrategy::MaxRadius => max_interaction,
+        CellSizingStrategy::MeanRadius => {
+            let mean_radius = sim_data.radii.iter().sum::<f64>() / sim_data.num_particles() as f64;
+            2.0 * mean_radius + cutoff
+        }
+        CellSizingStrategy::Fixed(size) => size,
+    };
+
+    // The number of cells the stencil has to reach in each direction. Cells are never smaller than the target size.
+    let reach = usize::max(1, f64::ceil(max_interaction / target_size) as usize);
 
-    // Bin particles in the linked cells structure.
-    let mut linked_cells = LinkedCells::new_for_simdata(&sim_data, max_radius);
+    // Bin particles in the linked cells structure. The ghost layers hold the periodic images of the particles
+    // on the opposite edge, so the stencil finds pairs that are close across the boundary.
+    let mut linked_cells = LinkedCells::new_with_ghost_layers(sim_data.bounds, target_size, reach);
     for id in 0..sim_data.num_particles() {
-        linked_cells.add_particle(&sim_data.positions.get(id).unwrap(), id);
+        linked_cells.add_particle(sim_data.positions.get(id).unwrap(), id);
     }
+    linked_cells.populate_sheared_ghost_cells(sim_data.topology.image_offset(&sim_data.bounds));
+    (linked_cells, half_stencil(reach as i32))
+}
 
-    // Create verlet lists from the linked cells.
-    for ix in 0..linked_cells.get_num_x() {
-        for iy in 0..linked_cells.get_num_y() {
-            let cell = linked_cells.get_cell(ix, iy).unwrap();
-
-            for i in 0..cell.particle_ids.len() {
-                let id1 = cell.particle_ids[i];
-
-                let mut neighbors = Vec::new();
-
-                // Top row.
-                if let Some(cell) = linked_cells.get_adjusted_cell(ix, iy, -1, 1) {
-                    check_neighbors(
-                        id1,
-                        cell.particle_ids.as_slice(),
-                        &sim_data,
-                        &mut neighbors,
-                        cutoff,
-                    );
-                }
-                if let Some(cell) = linked_cells.get_adjusted_cell(ix, iy, 0, 1) {
-                    check_neighbors(
-                        id1,
-                        cell.particle_ids.as_slice(),
-                        &sim_data,
-                        &mut neighbors,
-                        cutoff,
-                    );
-                }
-                if let Some(cell) = linked_cells.get_adjusted_cell(ix, iy, 1, 1) {
-                    check_neighbors(
-                        id1,
-                        cell.particle_ids.as_slice(),
-                        &sim_data,
-                        &mut neighbors,
-                        cutoff,
-                    );
-                }
-                if let Some(cell) = linked_cells.get_adjusted_cell(ix, iy, -1, 0) {
-                    check_neighbors(
-                        id1,
-                        cell.particle_ids.as_slice(),
-                        &sim_data,
-                        &mut neighbors,
-                        cutoff,
-                    );
-                }
-
-                // Same cells.
+/// The offsets of the cells in the half stencil that reaches `reach` cells in every direction. The same cell is
+/// not included. Every pair of distinct cells within reach of each other is covered exactly once, from one of the
+/// two cells.
+fn half_stencil(reach: i32) -> Vec<(i32, i32)> {
+    let mut stencil = Vec::new();
+    // Rows above.
+    for dy in 1..=reach {
+        for dx in -reach..=reach {
+            stencil.push((dx, dy));
+        }
+    }
+    // Cells to the left.
+    for dx in -reach..0 {
+        stencil.push((dx, 0));
+    }
+    stencil
+}
+
+/// Add the verlet lists of the particles in one cell, searching the cells in a half stencil around it.
+fn cell_verlet_lists(
+    linked_cells: &LinkedCells,
+    stencil: &[(i32, i32)],
+    ix: usize,
+    iy: usize,
+    sim_data: &SimData,
+    cutoff: f64,
+    verlet_lists: &mut Vec<(usize, Vec<usize>)>,
+) {
+    let cell = linked_cells.get_cell(ix, iy).unwrap();
+
+    for i in 0..cell.particle_ids.len() {
+        let id1 = cell.particle_ids[i];
+
+        let mut neighbors = Vec::new();
+
+        for &(dx, dy) in stencil {
+            if let Some(cell) = linked_cells.get_adjusted_cell(ix, iy, dx, dy) {
                 check_neighbors(
                     id1,
-                    &cell.particle_ids[i + 1..],
-                    &sim_data,
+                    cell.particle_ids.as_slice(),
+                    sim_data,
                     &mut neighbors,
                     cutoff,
                 );
-
-                // If any neighbors of id1 were found, add them to the verlet lists.
-                if 0 < neighbors.len() {
-                    verlet_lists.push((id1, neighbors));
-                }
             }
         }
+
+        // Same cells.
+        check_neighbors(
+            id1,
+            &cell.particle_ids[i + 1..],
+            sim_data,
+            &mut neighbors,
+            cutoff,
+        );
+        // With sheared ghost cells, a particle can be in two of the cells of the stencil.
+        neighbors.sort_unstable();
+        neighbors.dedup();
+
+        // If any neighbors of id1 were found, add them to the verlet lists.
+        if !neighbors.is_empty() {
+            verlet_lists.push((id1, neighbors));
+        }
     }
+}
 
-    VerletLists::from(verlet_lists)
+/// Check, using the O(N^2) all-pairs search, that no interacting pair of particles is missing from the verlet
+/// lists. Two particles interact if they are closer than the sum of their radii plus `cutoff`.
+///
+/// Panics if an interacting pair is missing. This is slow, and is meant for debugging and tests.
+pub fn debug_verify_verlet_lists(sim_data: &SimData, verlet_lists: &VerletLists, cutoff: f64) {
+    let pairs: HashSet<(usize, usize)> = verlet_lists
+        .into_iter()
+        .map(|(id1, id2)| (usize::min(id1, id2), usize::max(id1, id2)))
+        .collect();
+
+    for id1 in 0..sim_data.num_particles() {
+        for id2 in id1 + 1..sim_data.num_particles() {
+            let rsqr = sim_data.distance_sqr_between(id1, id2);
+            let rdiff = sim_data.radii[id1] + sim_data.radii[id2] + cutoff;
+            if rsqr < rdiff * rdiff && !pairs.contains(&(id1, id2)) {
+                panic!("interacting pair ({}, {}) is missing from the verlet lists", id1, id2);
+            }
+        }
+    }
 }
 
 // =================================================================================================
@@ -180,15 +403,130 @@ pub fn create_verlet_lists(sim_data: &SimData, cutoff: f64) -> VerletLists {
 
 #[cfg(test)]
 mod tests {
+    use crate::assert_close;
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::{Bounds, LeesEdwardsTopology, ReflectingTopology, ShearProtocol, Topology};
+    use crate::utils::rng::SimRng;
+
+    /// Two particles separated by a small gap, which are moved into contact after the verlet lists are built.
+    fn approaching_pair() -> SimData {
+        let particles = vec![
+            Particle::new().with_coords(5.0, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(6.2, 5.0).with_radius(0.5).to_owned(),
+        ];
+        SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles)
+    }
+
+    #[test]
+    fn test_box_too_small_for_cutoff() {
+        // Particles with radius 0.5 interact out to 1 + cutoff, so a box of width 2.5 only fits the minimum image
+        // convention without a cutoff.
+        let particles = vec![
+            Particle::new().with_coords(0.5, 0.5).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(1.5, 0.5).with_radius(0.5).to_owned(),
+        ];
+        let tiny = SimData::new_with_particles(Bounds::from((0.0, 2.5, 0.0, 10.0)), &particles);
+        assert!(!box_too_small_for_cutoff(&tiny, 0.0));
+        assert!(box_too_small_for_cutoff(&tiny, 0.5));
+        // Narrow in y instead of x.
+        let flat = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 1.5)), &particles);
+        assert!(box_too_small_for_cutoff(&flat, 0.0));
+
+        // The lists are still built, with the nearest image of each neighbor, and record that the box is too small.
+        for search in [NeighborSearch::CellList, NeighborSearch::KdTree] {
+            let verlet_lists = create_verlet_lists_with_search(&tiny, 0.5, search);
+            assert_eq!(verlet_lists.num_pairs(), 1);
+            assert!(verlet_lists.box_too_small());
+            assert!(!create_verlet_lists_with_search(&tiny, 0.0, search).box_too_small());
+        }
+        assert!(create_verlet_lists_parallel(&tiny, 0.5).box_too_small());
+
+        // Images do not matter if the box is not periodic.
+        let mut walled = tiny.clone();
+        walled.topology = Box::new(ReflectingTopology {});
+        assert!(!box_too_small_for_cutoff(&walled, 0.5));
+        assert!(!create_verlet_lists(&walled, 0.5).box_too_small());
+    }
+
+    #[test]
+    fn test_verlet_lists_find_periodic_neighbors_in_small_box() {
+        // Small cells need a stencil reaching three cells out, but the box only fits six cells across, too few for the
+        // ghost cells to wrap. The box is still wide enough for the minimum image convention.
+        let particles = vec![
+            Particle::new().with_coords(0.2, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(2.3, 5.0).with_radius(0.5).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 2.5, 0.0, 10.0)), &particles);
+        assert!(!box_too_small_for_cutoff(&sim_data, 0.1));
+
+        for strategy in [CellSizingStrategy::Fixed(0.4), CellSizingStrategy::MaxRadius] {
+            let verlet_lists = create_verlet_lists_with_cell_sizing(&sim_data, 0.1, strategy);
+            assert_eq!(verlet_lists.num_pairs(), 1);
+            debug_verify_verlet_lists(&sim_data, &verlet_lists, 0.1);
+        }
+    }
+
+    #[test]
+    fn test_verlet_lists_find_neighbors_across_sheared_boundary() {
+        // With a strain of 0.3, the images above the box are shifted by 3, much more than the cutoff, so the particle
+        // near the bottom of the box touches the particle near the top of the box three cells to its right.
+        let particles = vec![
+            Particle::new().with_coords(5.0, 9.8).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(2.0, 0.2).with_radius(0.5).to_owned(),
+        ];
+        let mut sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let mut topology = LeesEdwardsTopology::new(ShearProtocol::Steady { rate: 0.03 });
+        topology.advance(10.0);
+        sim_data.topology = Box::new(topology);
+        assert_close!(sim_data.displacement_between(0, 1).length(), 0.4, 1e-9);
+
+        for search in [NeighborSearch::CellList, NeighborSearch::KdTree] {
+            let verlet_lists = create_verlet_lists_with_search(&sim_data, 0.1, search);
+            assert_eq!(verlet_lists.num_pairs(), 1, "{:?}", search);
+            debug_verify_verlet_lists(&sim_data, &verlet_lists, 0.1);
+        }
+    }
+
+    #[test]
+    fn test_kd_tree_matches_cell_list() {
+        // A sparse, clustered configuration: most particles in one corner, and a few spread out, some near the edges.
+        let mut rng = SimRng::new(13);
+        let mut particles: Vec<Particle> = (0..60)
+            .map(|_| Particle::new()
+                .with_coords(rng.uniform(0.0, 6.0), rng.uniform(0.0, 6.0))
+                .with_radius(rng.uniform(0.1, 0.4))
+                .to_owned())
+            .collect();
+        particles.extend((0..20).map(|_| Particle::new()
+            .with_coords(rng.uniform(0.0, 100.0), rng.uniform(0.0, 100.0))
+            .with_radius(rng.uniform(0.1, 0.4))
+            .to_owned()));
+        particles.push(Particle::new().with_coords(99.9, 50.0).with_radius(0.3).to_owned());
+        particles.push(Particle::new().with_coords(0.2, 50.0).with_radius(0.3).to_owned());
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 100.0, 0.0, 100.0)), &particles);
+        assert_eq!(choose_neighbor_search(&sim_data, 0.1), NeighborSearch::KdTree);
+
+        let pair_set = |verlet_lists: &VerletLists| -> HashSet<(usize, usize)> {
+            verlet_lists.into_iter().map(|(id1, id2)| (usize::min(id1, id2), usize::max(id1, id2))).collect()
+        };
+        let cells = create_verlet_lists_with_search(&sim_data, 0.1, NeighborSearch::CellList);
+        let tree = create_verlet_lists_with_search(&sim_data, 0.1, NeighborSearch::KdTree);
+        assert_eq!(tree.num_pairs(), cells.num_pairs());
+        assert_eq!(pair_set(&tree), pair_set(&cells));
+        // The pair across the periodic boundary is found.
+        assert!(pair_set(&tree).contains(&(80, 81)));
+        debug_verify_verlet_lists(&sim_data, &tree, 0.1);
+    }
 
     #[test]
     fn test_verlet_list_construction() {
-        let mut v = Vec::new();
-        v.push((0usize, vec![1usize, 2usize, 3usize]));
-        v.push((1usize, vec![2usize, 4usize]));
-        v.push((5usize, vec![6usize, 7usize]));
+        let v = vec![
+            (0usize, vec![1usize, 2usize, 3usize]),
+            (1usize, vec![2usize, 4usize]),
+            (5usize, vec![6usize, 7usize]),
+        ];
 
         let vl = VerletLists::from(v);
         assert_eq!(vl.num_pairs, 7);
@@ -228,4 +566,103 @@ mod tests {
 
         assert_eq!(indices.len(), 0);
     }
+
+    #[test]
+    fn test_verlet_lists_find_periodic_neighbors() {
+        let particles = vec![
+            Particle::new().with_coords(0.1, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(9.6, 5.0).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.0, 9.9).with_radius(0.5).to_owned(),
+            Particle::new().with_coords(5.2, 0.2).with_radius(0.5).to_owned(),
+        ];
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 10.0, 0.0, 10.0)), &particles);
+        let verlet_lists = create_verlet_lists(&sim_data, 0.1);
+
+        let mut pairs: Vec<(usize, usize)> = verlet_lists
+            .into_iter()
+            .map(|(id1, id2)| (usize::min(id1, id2), usize::max(id1, id2)))
+            .collect();
+        pairs.sort();
+        assert_eq!(pairs, vec![(0, 1), (2, 3)]);
+        debug_verify_verlet_lists(&sim_data, &verlet_lists, 0.1);
+    }
+
+    #[test]
+    fn test_debug_verify_verlet_lists_with_correct_skin() {
+        let mut sim_data = approaching_pair();
+        let verlet_lists = create_verlet_lists(&sim_data, 0.3);
+        sim_data.positions[1].x = 5.9;
+        debug_verify_verlet_lists(&sim_data, &verlet_lists, 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "missing from the verlet lists")]
+    fn test_debug_verify_verlet_lists_with_too_small_skin() {
+        let mut sim_data = approaching_pair();
+        let verlet_lists = create_verlet_lists(&sim_data, 0.05);
+        sim_data.positions[1].x = 5.9;
+        debug_verify_verlet_lists(&sim_data, &verlet_lists, 0.0);
+    }
+
+    #[test]
+    fn test_parallel_verlet_lists_match_serial() {
+        // A fixed, disordered configuration, with particles near every edge of the box.
+        let mut rng = SimRng::new(7);
+        let particles: Vec<Particle> = (0..400)
+            .map(|_| Particle::new()
+                .with_coords(rng.uniform(0.0, 20.0), rng.uniform(0.0, 20.0))
+                .with_radius(rng.uniform(0.3, 0.5))
+                .to_owned())
+            .collect();
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 20.0, 0.0, 20.0)), &particles);
+
+        let pair_set = |verlet_lists: &VerletLists| -> HashSet<(usize, usize)> {
+            verlet_lists.into_iter().map(|(id1, id2)| (usize::min(id1, id2), usize::max(id1, id2))).collect()
+        };
+        let serial = create_verlet_lists(&sim_data, 0.1);
+        let parallel = create_verlet_lists_parallel(&sim_data, 0.1);
+
+        // No pair is counted twice, and the two lists contain the same pairs.
+        assert_eq!(parallel.num_pairs(), serial.num_pairs());
+        assert_eq!(pair_set(&parallel).len(), parallel.num_pairs());
+        assert_eq!(pair_set(&parallel), pair_set(&serial));
+        debug_verify_verlet_lists(&sim_data, &parallel, 0.1);
+    }
+
+    #[test]
+    fn test_cell_sizing_strategies_find_the_same_pairs() {
+        // A polydisperse configuration, with a few particles much larger than the rest.
+        let mut rng = SimRng::new(11);
+        let particles: Vec<Particle> = (0..300)
+            .map(|i| Particle::new()
+                .with_coords(rng.uniform(0.0, 20.0), rng.uniform(0.0, 20.0))
+                .with_radius(if i % 50 == 0 { 1.5 } else { rng.uniform(0.2, 0.5) })
+                .to_owned())
+            .collect();
+        let sim_data = SimData::new_with_particles(Bounds::from((0.0, 20.0, 0.0, 20.0)), &particles);
+
+        let overlaps = |strategy: CellSizingStrategy| -> HashSet<(usize, usize)> {
+            let verlet_lists = create_verlet_lists_with_cell_sizing(&sim_data, 0.1, strategy);
+            let pairs: HashSet<(usize, usize)> = verlet_lists
+                .into_iter()
+                .map(|(id1, id2)| (usize::min(id1, id2), usize::max(id1, id2)))
+                .collect();
+            // No pair is counted twice.
+            assert_eq!(pairs.len(), verlet_lists.num_pairs());
+            debug_verify_verlet_lists(&sim_data, &verlet_lists, 0.1);
+            pairs
+                .into_iter()
+                .filter(|&(id1, id2)| {
+                    let sum_radii = sim_data.radii[id1] + sim_data.radii[id2];
+                    sim_data.distance_sqr_between(id1, id2) < sum_radii * sum_radii
+                })
+                .collect()
+        };
+
+        let expected = overlaps(CellSizingStrategy::MaxRadius);
+        assert!(!expected.is_empty());
+        assert_eq!(overlaps(CellSizingStrategy::MeanRadius), expected);
+        assert_eq!(overlaps(CellSizingStrategy::Fixed(0.4)), expected);
+        assert_eq!(overlaps(CellSizingStrategy::Fixed(6.0)), expected);
+    }
 }
@@ -1,40 +1,22 @@
-use std::borrow::Borrow;
-use std::cell::RefCell;
-use std::rc::Rc;
-use plotly;
-use plotly::common::Mode;
-use plotly::{Layout, Plot, Scatter};
-
-use rand::Rng;
 use crate::core::particle::Particle;
-use crate::core::simdata::{Bounds, SimData};
-use crate::core::force::Force;
-use crate::core::monitor::{Monitor, PositionMonitor};
+use crate::core::simdata::Bounds;
+use crate::core::monitor::StateMonitor;
 use crate::core::universe::Universe;
 
+pub mod analysis;
 pub mod core;
 pub mod utils;
 
-use crate::core::vector::{Position, Vector, Velocity};
-use crate::core::verlet_lists::create_verlet_lists;
-
-use plotters::prelude::*;
-
-fn generate_particles(num_particles: i64, bounds: Bounds) -> Vec<Particle> {
-    let mut rng1 = rand::thread_rng();
-    let mut rng2 = rand::thread_rng();
-    let mut rng3 = rand::thread_rng();
-    let mut random_x = || rng1.gen_range(bounds.xlo..bounds.xhi);
-    let mut random_y = || rng2.gen_range(bounds.ylo..bounds.yhi);
-    let mut random_v = || rng3.gen_range(-1.0..1.0);
+use crate::core::vector::Vector;
+use crate::utils::render::Renderer;
+use crate::utils::rng::SimRng;
 
+fn generate_particles(num_particles: i64, bounds: Bounds, rng: &mut SimRng) -> Vec<Particle> {
     let mut particles = Vec::new();
     for _ in 0..num_particles {
         let p = Particle::new()
-            .with_position(Vector::new(random_x(), random_y()))
-            // TODO(Nate): this does not generate velocities with magnitudes that are uncorrelated with directions,
-            //  you need to use normal RVs to do this. I'll change it later.
-            .with_velocity(Vector::new(random_v(), random_v()))
+            .with_position(Vector::new(rng.uniform(bounds.xlo, bounds.xhi), rng.uniform(bounds.ylo, bounds.yhi)))
+            .with_velocity(Vector::new(rng.gaussian(0.0, 1.0), rng.gaussian(0.0, 1.0)))
             .with_radius(0.05)
             .with_density(1.0)
             .to_owned();
@@ -43,6 +25,7 @@ fn generate_particles(num_particles: i64, bounds: Bounds) -> Vec<Particle> {
     particles
 }
 
+#[allow(dead_code, clippy::vec_init_then_push)]
 fn specific_scenario() -> Vec<Particle> {
     let mut particles = Vec::new();
     particles.push(Particle::new().with_coords(6.446288539458056, 6.217110127096928).with_velocity(Vector::new(-4.407848524198707, 3.6995346746413134)).with_radius(0.05).to_owned());
@@ -64,7 +47,7 @@ fn main() {
     let mut universe = Universe::new(sim_bounds);
 
     // let particles = specific_scenario();
-    let particles = generate_particles(256, sim_bounds);
+    let particles = generate_particles(256, sim_bounds, universe.rng());
 
     // println!("let mut particles = Vec::new();");
     // for p in particles.iter() {
@@ -72,8 +55,8 @@ fn main() {
     //              p.position.x, p.position.y, p.velocity.x, p.velocity.y, p.radius);
     // }
 
-    let positions = Box::new(PositionMonitor::new(1.0 / 30.0));
-    universe.add_monitor("Positions", positions);
+    let states = Box::new(StateMonitor::new(1.0 / 30.0));
+    universe.add_monitor("States", states);
 
     universe.sim_data.add_particles(&particles);
 
@@ -81,49 +64,17 @@ fn main() {
 
     universe.run_until(2.0);
 
-    let positions = universe
-        .get_monitor("Positions")
+    let states = universe
+        .get_monitor("States")
         .unwrap()
         .as_any()
-        .downcast_ref::<PositionMonitor>()
+        .downcast_ref::<StateMonitor>()
         .unwrap();
 
-    let trajectories = &positions.positions;
-
-    let area = BitMapBackend::gif(
-        "/Users/nathaniel/animated.gif",
-        (256, 256),
-        1_000 / 30,
-    ).unwrap().into_drawing_area();
-
-    let r = 256.0 / 4.0 * 0.05;
-
-    for i in 0..trajectories.len() {
-        println!("Handling trajectory point {} of {}", i, trajectories.len());
-
-        area.fill(&WHITE).unwrap();
-
-        let sim_data = &universe.sim_data;
-        let mut ctx = ChartBuilder::on(&area)
-            // .set_label_area_size(LabelAreaPosition::Left, 40)
-            // .set_label_area_size(LabelAreaPosition::Bottom, 40)
-            // .caption("Scatter Demo", ("sans-serif", 40))
-
-            .build_cartesian_2d(sim_data.bounds.xlo..sim_data.bounds.xhi,
-                                sim_data.bounds.ylo..sim_data.bounds.yhi)
-            .unwrap();
-
-        ctx.configure_mesh().draw().unwrap();
-
-        let pos: Vec<(f64, f64)> = trajectories[i].iter().map(|p| (p.x, p.y)).collect();
-
-        ctx.draw_series(
-            pos.iter().map(|point| Circle::new(*point, r, &RED)),
-        ).unwrap();
-
-        area.present().unwrap();
-    }
-
+    Renderer::new((256, 256), 1_000 / 30)
+        .with_color_by_speed(0.0, 3.0)
+        .render_gif("/Users/nathaniel/animated.gif", &universe.sim_data, states)
+        .unwrap();
 
     // let mut x = Vec::new();
     // let mut y = Vec::new();
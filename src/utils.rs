@@ -1,2 +1,7 @@
+pub mod ensemble;
+pub mod init;
 pub mod macros;
+pub mod render;
+pub mod rng;
+pub mod testing;
 mod plot2d;
@@ -0,0 +1,58 @@
+use crate::core::universe::Universe;
+
+/// Run an ensemble of independent simulations, for averaging over. The setup closure builds each replica from its
+/// seed, which is the index of the replica, so that the replicas differ and the ensemble is reproducible. Every
+/// replica is run for `steps` iterations, and the finished universes are returned in order.
+///
+/// NOTE: The replicas are run one after the other, since a Universe holds boxed forces, integrators, and monitors that
+/// are not required to be `Send`, so they cannot be moved between threads.
+pub fn run_ensemble(n_replicas: usize, setup: impl Fn(u64) -> Universe, steps: i64) -> Vec<Universe> {
+    (0..n_replicas)
+        .map(|replica| {
+            let mut universe = setup(replica as u64);
+            universe.run_for_iterations(steps);
+            universe
+        })
+        .collect()
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::particle::Particle;
+    use crate::core::simdata::Bounds;
+
+    #[test]
+    fn test_run_ensemble() {
+        let setup = |seed: u64| {
+            let mut universe = Universe::new_seeded(Bounds::from((0.0, 5.0, 0.0, 5.0)), seed);
+            let particles: Vec<Particle> = (0..10)
+                .map(|_| {
+                    let (x, y) = (universe.rng().uniform(0.0, 5.0), universe.rng().uniform(0.0, 5.0));
+                    let (vx, vy) = (universe.rng().gaussian(0.0, 1.0), universe.rng().gaussian(0.0, 1.0));
+                    Particle::new().with_coords(x, y).with_radius(0.3).with_velocity_components(vx, vy).to_owned()
+                })
+                .collect();
+            universe.sim_data.add_particles(&particles);
+            universe
+        };
+        let replicas = run_ensemble(3, setup, 100);
+
+        assert_eq!(replicas.len(), 3);
+        for universe in replicas.iter() {
+            assert_eq!(universe.iterations, 100);
+        }
+        let configurations: Vec<Vec<(f64, f64)>> = replicas
+            .iter()
+            .map(|u| u.sim_data.positions.iter().map(|p| (p.x, p.y)).collect())
+            .collect();
+        assert_ne!(configurations[0], configurations[1]);
+        assert_ne!(configurations[0], configurations[2]);
+        assert_ne!(configurations[1], configurations[2]);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::core::particle::Particle;
+use crate::core::simdata::Bounds;
+use crate::core::vector::Position;
+use crate::utils::rng::SimRng;
+
+/// Create a square lattice of nx by ny particles, filling the bounds. Each particle sits at the center of its
+/// lattice cell.
+pub fn square_lattice(nx: usize, ny: usize, bounds: Bounds, radius: f64) -> Vec<Particle> {
+    let dx = bounds.width() / nx as f64;
+    let dy = bounds.height() / ny as f64;
+
+    let mut particles = Vec::new();
+    for iy in 0..ny {
+        for ix in 0..nx {
+            particles.push(Particle::new()
+                .with_coords(bounds.xlo + (ix as f64 + 0.5) * dx, bounds.ylo + (iy as f64 + 0.5) * dy)
+                .with_radius(radius)
+                .to_owned());
+        }
+    }
+    particles
+}
+
+/// Create a hexagonal (triangular) lattice of nx by ny particles, filling the bounds, with every other row shifted by
+/// half a lattice spacing. For the lattice to be periodic, ny must be even. For it to be regular, the height of the
+/// bounds must be sqrt(3) / 2 * ny / nx times its width.
+pub fn hexagonal_lattice(nx: usize, ny: usize, bounds: Bounds, radius: f64) -> Vec<Particle> {
+    let dx = bounds.width() / nx as f64;
+    let dy = bounds.height() / ny as f64;
+
+    let mut particles = Vec::new();
+    for iy in 0..ny {
+        let shift = if iy % 2 == 0 { 0.25 } else { 0.75 };
+        for ix in 0..nx {
+            particles.push(Particle::new()
+                .with_coords(bounds.xlo + (ix as f64 + shift) * dx, bounds.ylo + (iy as f64 + 0.5) * dy)
+                .with_radius(radius)
+                .to_owned());
+        }
+    }
+    particles
+}
+
+/// Create a square lattice of nx by ny particles, like `square_lattice`, with a fraction of the sites, chosen at
+/// random, left empty. The number of vacancies is the vacancy fraction of the sites, rounded to the nearest integer.
+/// The same seed always removes the same sites.
+pub fn lattice_with_vacancies(
+    nx: usize,
+    ny: usize,
+    bounds: Bounds,
+    radius: f64,
+    vacancy_fraction: f64,
+    seed: u64,
+) -> Vec<Particle> {
+    let particles = square_lattice(nx, ny, bounds, radius);
+    let num_vacancies = (vacancy_fraction * particles.len() as f64).round() as usize;
+
+    let mut sites: Vec<usize> = (0..particles.len()).collect();
+    SimRng::new(seed).shuffle(&mut sites);
+    let mut is_vacant = vec![false; particles.len()];
+    for &site in sites.iter().take(num_vacancies) {
+        is_vacant[site] = true;
+    }
+    particles.into_iter().zip(is_vacant).filter(|(_, vacant)| !vacant).map(|(p, _)| p).collect()
+}
+
+/// Create a cluster of n particles about a center, e.g. a droplet, whose number density at a distance r from the
+/// center is proportional to `profile(r)`, out to `max_radius`. Positions are drawn by rejection sampling: points are
+/// drawn uniformly in the disk, and accepted with probability profile(r) / max profile. The maximum of the profile is
+/// found by evaluating it at many radii. Particles may overlap. The same seed always gives the same cluster.
+pub fn radial_cluster(
+    n: usize,
+    center: Position,
+    max_radius: f64,
+    radius: f64,
+    profile: impl Fn(f64) -> f64,
+    seed: u64,
+) -> Vec<Particle> {
+    const PROFILE_SAMPLES: usize = 1000;
+
+    let max_profile = (0..=PROFILE_SAMPLES)
+        .map(|i| profile(max_radius * i as f64 / PROFILE_SAMPLES as f64))
+        .fold(0.0, f64::max);
+    if n == 0 || max_profile <= 0.0 {
+        return vec![];
+    }
+
+    let mut rng = SimRng::new(seed);
+    let mut particles = Vec::with_capacity(n);
+    while particles.len() < n {
+        let r = max_radius * rng.uniform(0.0, 1.0).sqrt();
+        if max_profile * rng.uniform(0.0, 1.0) < profile(r) {
+            let theta = rng.uniform(0.0, 2.0 * std::f64::consts::PI);
+            particles.push(Particle::new()
+                .with_coords(center.x + r * theta.cos(), center.y + r * theta.sin())
+                .with_radius(radius)
+                .to_owned());
+        }
+    }
+    particles
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn test_lattice_with_vacancies() {
+        let bounds = Bounds::from((0.0, 10.0, 0.0, 10.0));
+        let particles = lattice_with_vacancies(10, 10, bounds, 0.4, 0.15, 7);
+        assert_eq!(particles.len(), 85);
+
+        // The same seed removes the same sites, and a different seed removes different ones.
+        let coords = |particles: &[Particle]| -> Vec<(f64, f64)> {
+            particles.iter().map(|p| (p.position.x, p.position.y)).collect()
+        };
+        let again = lattice_with_vacancies(10, 10, bounds, 0.4, 0.15, 7);
+        assert_eq!(coords(&particles), coords(&again));
+        let other = lattice_with_vacancies(10, 10, bounds, 0.4, 0.15, 8);
+        assert_ne!(coords(&particles), coords(&other));
+
+        // The count is rounded.
+        assert_eq!(lattice_with_vacancies(3, 3, bounds, 0.4, 0.1, 7).len(), 8);
+    }
+
+    #[test]
+    fn test_radial_cluster_follows_profile() {
+        // A droplet whose density falls linearly to zero at its edge.
+        let center = Position::new(5.0, 5.0);
+        let profile = |r: f64| 1.0 - r / 4.0;
+        let particles = radial_cluster(20_000, center, 4.0, 0.01, profile, 3);
+        assert_eq!(particles.len(), 20_000);
+
+        // Bin the particles into rings, and compare the density in each ring to the profile at its middle.
+        let mut counts = [0usize; 8];
+        for p in particles.iter() {
+            let r = (p.position - center).length();
+            assert!(r <= 4.0);
+            counts[usize::min((r / 0.5) as usize, 7)] += 1;
+        }
+        // The normalization, from the integral of 2 pi r profile(r) over the disk, 16 pi / 3.
+        let scale = 20_000.0 / (16.0 * std::f64::consts::PI / 3.0);
+        for (bin, &count) in counts.iter().enumerate().take(6) {
+            let (r_in, r_out) = (0.5 * bin as f64, 0.5 * (bin + 1) as f64);
+            let density = count as f64 / (std::f64::consts::PI * (r_out * r_out - r_in * r_in));
+            let expected = scale * profile(0.5 * (r_in + r_out));
+            assert!((density - expected).abs() < 0.1 * expected, "ring {}: {} vs {}", bin, density, expected);
+        }
+
+        // The same seed gives the same cluster.
+        let again = radial_cluster(20_000, center, 4.0, 0.01, profile, 3);
+        assert!(particles.iter().zip(again.iter()).all(|(p, q)| p.position.x == q.position.x));
+    }
+}
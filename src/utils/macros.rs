@@ -2,6 +2,8 @@
 #[macro_export]
 macro_rules! assert_close {
     ($x:expr, $y:expr, $delta:expr) => {
-        if !($x - $y < $delta || $y - $x < $delta) { panic!(); }
+        if !($x - $y < $delta && $y - $x < $delta) {
+            panic!("assertion failed: {} is not within {} of {}", $x, $delta, $y);
+        }
     };
 }
\ No newline at end of file
@@ -0,0 +1,273 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use plotters::prelude::*;
+use crate::core::monitor::StateMonitor;
+use crate::core::simdata::{Bounds, SimData};
+use crate::core::vector::Velocity;
+
+/// Renders recorded trajectories as animated GIFs.
+pub struct Renderer {
+    /// The size of the image, in pixels.
+    pub size: (u32, u32),
+    /// The time between frames, in milliseconds.
+    pub frame_delay: u32,
+
+    /// Whether to color each particle by its speed, from blue for slow particles to red for fast ones.
+    pub color_by_speed: bool,
+    /// The (slowest, fastest) speeds of the colormap. Speeds outside of the range get the color at its ends.
+    pub speed_range: (f64, f64),
+
+    /// Which particles to highlight, at each frame, e.g. the `mobile` flags of a `MobilityMonitor` that took its
+    /// snapshots at the same times as the state monitor. Frames or particles without a flag are not highlighted.
+    pub highlight: Vec<Vec<bool>>,
+    /// The color of the highlighted particles.
+    pub highlight_color: RGBColor,
+}
+
+impl Renderer {
+    pub fn new(size: (u32, u32), frame_delay: u32) -> Renderer {
+        Renderer {
+            size,
+            frame_delay,
+            color_by_speed: false,
+            speed_range: (0.0, 1.0),
+            highlight: vec![],
+            highlight_color: GREEN,
+        }
+    }
+
+    /// Color particles by their speed, with a colormap going from blue at `min_speed` to red at `max_speed`. Allows
+    /// for chaining.
+    pub fn with_color_by_speed(&mut self, min_speed: f64, max_speed: f64) -> &mut Self {
+        self.color_by_speed = true;
+        self.speed_range = (min_speed, max_speed);
+        self
+    }
+
+    /// Draw the flagged particles of each frame in `color`, instead of their usual color. Allows for chaining.
+    pub fn with_highlight(&mut self, flags: &[Vec<bool>], color: RGBColor) -> &mut Self {
+        self.highlight = flags.to_vec();
+        self.highlight_color = color;
+        self
+    }
+
+    /// The color of each particle in a frame, given the particles' velocities, with the highlighted particles of the
+    /// frame in the highlight color.
+    pub fn frame_colors(&self, frame: usize, velocities: &[Velocity]) -> Vec<RGBColor> {
+        let mut colors = self.particle_colors(velocities);
+        if let Some(flags) = self.highlight.get(frame) {
+            for (color, _) in colors.iter_mut().zip(flags).filter(|(_, &flag)| flag) {
+                *color = self.highlight_color;
+            }
+        }
+        colors
+    }
+
+    /// The color of each particle, given the particles' velocities.
+    pub fn particle_colors(&self, velocities: &[Velocity]) -> Vec<RGBColor> {
+        if !self.color_by_speed {
+            return vec![RED; velocities.len()];
+        }
+        let (min_speed, max_speed) = self.speed_range;
+        velocities
+            .iter()
+            .map(|v| {
+                let t = f64::clamp((v.length() - min_speed) / (max_speed - min_speed), 0.0, 1.0);
+                RGBColor((255.0 * t).round() as u8, 0, (255.0 * (1.0 - t)).round() as u8)
+            })
+            .collect()
+    }
+
+    /// Render every frame recorded by a state monitor into an animated GIF, drawing the particles with the radii they
+    /// had in each frame, and the highlighted particles of each frame in the highlight color. The SimData supplies the
+    /// bounds.
+    pub fn render_gif(&self, path: &str, sim_data: &SimData, states: &StateMonitor) -> Result<(), Box<dyn Error>> {
+        let area = BitMapBackend::gif(path, self.size, self.frame_delay)?.into_drawing_area();
+        let pixels_per_unit = self.size.0 as f64 / sim_data.width();
+
+        let frames = states.positions.iter().zip(states.velocities.iter()).zip(states.radii.iter());
+        for (frame, ((positions, velocities), radii)) in frames.enumerate() {
+            area.fill(&WHITE)?;
+
+            let bounds = sim_data.bounds;
+            let mut ctx = ChartBuilder::on(&area).build_cartesian_2d(bounds.xlo..bounds.xhi, bounds.ylo..bounds.yhi)?;
+            ctx.configure_mesh().draw()?;
+
+            let colors = self.frame_colors(frame, velocities);
+            ctx.draw_series(positions.iter().enumerate().map(|(i, p)| {
+                let radius = (radii[i] * pixels_per_unit).round() as i32;
+                Circle::new((p.x, p.y), radius, colors[i].filled())
+            }))?;
+
+            area.present()?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the current configuration as an SVG drawing: the outline of the box, and every particle as a circle with
+/// its true radius. Lengths are in simulation units, and y points up, as in the simulation.
+pub fn write_svg(sim_data: &SimData, path: &str) -> io::Result<()> {
+    let b = sim_data.bounds;
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+        b.xlo, -b.yhi, sim_data.width(), sim_data.height()
+    ).unwrap();
+    // Flip the y axis, since SVG's y axis points down.
+    writeln!(svg, r#"<g transform="scale(1,-1)">"#).unwrap();
+    writeln!(
+        svg,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="black" stroke-width="{}"/>"#,
+        b.xlo, b.ylo, sim_data.width(), sim_data.height(), 0.002 * sim_data.width()
+    ).unwrap();
+    for i in 0..sim_data.num_particles() {
+        let p = sim_data.positions[i];
+        writeln!(svg, r#"<circle cx="{}" cy="{}" r="{}" fill="red"/>"#, p.x, p.y, sim_data.radii[i]).unwrap();
+    }
+    svg.push_str("</g>\n</svg>\n");
+    fs::write(path, svg)
+}
+
+/// Write a field on a grid over the bounds, indexed as `[ix][iy]` like `analysis::free_volume_map`, as an SVG drawing
+/// with a rectangle per grid cell. Cells are shaded from black at the smallest finite value to white at the largest.
+pub fn write_field_svg(field: &[Vec<f64>], bounds: Bounds, path: &str) -> io::Result<()> {
+    let finite = || field.iter().flatten().copied().filter(|v| v.is_finite());
+    let (min, max) = (finite().fold(f64::INFINITY, f64::min), finite().fold(f64::NEG_INFINITY, f64::max));
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+        bounds.xlo, -bounds.yhi, bounds.width(), bounds.height()
+    ).unwrap();
+    // Flip the y axis, since SVG's y axis points down.
+    writeln!(svg, r#"<g transform="scale(1,-1)">"#).unwrap();
+    for (ix, column) in field.iter().enumerate() {
+        let (dx, dy) = (bounds.width() / field.len() as f64, bounds.height() / column.len() as f64);
+        for (iy, &value) in column.iter().enumerate() {
+            let fraction = if min < max { ((value - min) / (max - min)).clamp(0.0, 1.0) } else { 1.0 };
+            let shade = (255.0 * fraction).round() as u8;
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="rgb({shade},{shade},{shade})"/>"#,
+                bounds.xlo + ix as f64 * dx, bounds.ylo + iy as f64 * dy, dx, dy
+            ).unwrap();
+        }
+    }
+    svg.push_str("</g>\n</svg>\n");
+    fs::write(path, svg)
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::monitor::mobility::MobilityMonitor;
+    use crate::core::particle::Particle;
+    use crate::core::universe::Universe;
+    use crate::core::vector::Vector;
+
+    #[test]
+    fn test_color_by_speed() {
+        let velocities = vec![Vector::new(0.1, 0.0), Vector::new(3.0, 4.0)];
+        let mut renderer = Renderer::new((64, 64), 33);
+        assert_eq!(renderer.particle_colors(&velocities), vec![RED, RED]);
+
+        renderer.with_color_by_speed(0.0, 5.0);
+        let colors = renderer.particle_colors(&velocities);
+        assert_ne!(colors[0], colors[1]);
+        // The slow particle is mostly blue, and the fast particle is red.
+        assert!(colors[0].0 < colors[0].2);
+        assert_eq!(colors[1], RGBColor(255, 0, 0));
+    }
+
+    #[test]
+    fn test_highlight() {
+        let velocities = vec![Vector::new(0.1, 0.0), Vector::new(3.0, 4.0), Vector::new(0.0, 1.0)];
+        let mut renderer = Renderer::new((64, 64), 33);
+        renderer.with_highlight(&[vec![false, true, false], vec![true]], BLUE);
+
+        assert_eq!(renderer.frame_colors(0, &velocities), vec![RED, BLUE, RED]);
+        // Particles without a flag are not highlighted.
+        assert_eq!(renderer.frame_colors(1, &velocities), vec![BLUE, RED, RED]);
+        // Neither are frames without flags.
+        assert_eq!(renderer.frame_colors(2, &velocities), vec![RED, RED, RED]);
+    }
+
+    #[test]
+    fn test_render_gif() {
+        let path = std::env::temp_dir().join("rust_md_test_render.gif");
+        let path = path.to_str().unwrap();
+
+        let mut universe = Universe::new(Bounds::from((0.0, 4.0, 0.0, 4.0)));
+        universe.sim_data.add_particles(&[
+            Particle::new().with_coords(1.0, 1.0).with_radius(0.2).with_velocity_components(2.0, 0.0).to_owned(),
+            Particle::new().with_coords(3.0, 3.0).with_radius(0.2).with_velocity_components(0.0, 0.1).to_owned(),
+        ]);
+        universe.add_monitor("States", Box::new(StateMonitor::new(0.01)));
+        universe.add_monitor("Mobility", Box::new(MobilityMonitor::new(0.01, 0.01)));
+        universe.run_until(0.05);
+        // Partway through, one particle grows and the other is removed.
+        universe.sim_data.radii[0] = 0.3;
+        universe.sim_data.remove_particle(1);
+        universe.run_until(0.1);
+
+        let states = universe.get_monitor("States").unwrap().as_any().downcast_ref::<StateMonitor>().unwrap();
+        assert_eq!(states.radii.first().unwrap(), &vec![0.2, 0.2]);
+        assert_eq!(states.radii.last().unwrap(), &vec![0.3]);
+        let mobility = universe.get_monitor("Mobility").unwrap().as_any().downcast_ref::<MobilityMonitor>().unwrap();
+        assert_eq!(mobility.mobile.len(), states.positions.len());
+        Renderer::new((64, 64), 33)
+            .with_color_by_speed(0.0, 2.0)
+            .with_highlight(&mobility.mobile, GREEN)
+            .render_gif(path, &universe.sim_data, states)
+            .unwrap();
+        assert!(0 < std::fs::metadata(path).unwrap().len());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_write_svg() {
+        let path = std::env::temp_dir().join("rust_md_test_render.svg");
+        let path = path.to_str().unwrap();
+
+        let sim_data = SimData::new_with_particles(
+            Bounds::from((0.0, 4.0, 0.0, 4.0)),
+            &[
+                Particle::new().with_coords(1.0, 1.0).with_radius(0.2).to_owned(),
+                Particle::new().with_coords(3.0, 2.0).with_radius(0.5).to_owned(),
+                Particle::new().with_coords(2.0, 3.0).with_radius(0.3).to_owned(),
+            ],
+        );
+        write_svg(&sim_data, path).unwrap();
+        let svg = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(!svg.is_empty());
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert!(svg.contains(r#"<circle cx="3" cy="2" r="0.5""#));
+    }
+
+    #[test]
+    fn test_write_field_svg() {
+        let path = std::env::temp_dir().join("rust_md_test_field.svg");
+        let path = path.to_str().unwrap();
+
+        let field = vec![vec![0.0, 1.0, 2.0], vec![3.0, 4.0, f64::INFINITY]];
+        write_field_svg(&field, Bounds::from((0.0, 4.0, 0.0, 3.0)), path).unwrap();
+        let svg = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(svg.matches("<rect").count(), 6);
+        assert!(svg.contains(r#"<rect x="0" y="0" width="2" height="1" fill="rgb(0,0,0)"/>"#));
+        assert!(svg.contains(r#"<rect x="2" y="1" width="2" height="1" fill="rgb(255,255,255)"/>"#));
+    }
+}
@@ -0,0 +1,92 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use crate::core::vector::Vector;
+
+/// A seedable random number generator, which is the single source of randomness for the simulation.
+pub struct SimRng {
+    rng: StdRng,
+}
+
+impl SimRng {
+    /// Create a new random number generator from a seed. Generators with the same seed produce the same sequence.
+    pub fn new(seed: u64) -> SimRng {
+        SimRng { rng: StdRng::seed_from_u64(seed) }
+    }
+
+    /// Create a new, randomly seeded, random number generator.
+    pub fn from_entropy() -> SimRng {
+        SimRng { rng: StdRng::from_entropy() }
+    }
+
+    /// A uniform random number in the range [lo, hi).
+    pub fn uniform(&mut self, lo: f64, hi: f64) -> f64 {
+        self.rng.gen_range(lo..hi)
+    }
+
+    /// A normally distributed random number, generated with the Box-Muller transform.
+    pub fn gaussian(&mut self, mean: f64, std: f64) -> f64 {
+        // Use 1 - u so the argument of the log is in (0, 1].
+        let u1: f64 = 1.0 - self.rng.gen::<f64>();
+        let u2: f64 = self.rng.gen();
+        let z = f64::sqrt(-2.0 * f64::ln(u1)) * f64::cos(2.0 * std::f64::consts::PI * u2);
+        mean + std * z
+    }
+
+    /// A seed for another random number generator, so stochastic components can derive their own generators
+    /// deterministically from a single master generator.
+    pub fn next_seed(&mut self) -> u64 {
+        self.rng.gen()
+    }
+
+    /// Randomly permute a slice in place.
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        values.shuffle(&mut self.rng);
+    }
+
+    /// A vector of length one, pointing in a uniformly random direction.
+    pub fn unit_vector(&mut self) -> Vector {
+        let theta = self.uniform(0.0, 2.0 * std::f64::consts::PI);
+        Vector::new(f64::cos(theta), f64::sin(theta))
+    }
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_close;
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn test_seeded_rng_is_deterministic() {
+        let mut rng1 = SimRng::new(17);
+        let mut rng2 = SimRng::new(17);
+        for _ in 0..10 {
+            assert_eq!(rng1.uniform(-1.0, 2.0), rng2.uniform(-1.0, 2.0));
+            assert_eq!(rng1.gaussian(0.5, 2.0), rng2.gaussian(0.5, 2.0));
+        }
+    }
+
+    #[test]
+    fn test_unit_vector() {
+        let mut rng = SimRng::new(3);
+        for _ in 0..100 {
+            assert_close!(rng.unit_vector().length(), 1.0, 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_gaussian_moments() {
+        let mut rng = SimRng::new(5);
+        let n = 20000;
+        let samples: Vec<f64> = (0..n).map(|_| rng.gaussian(1.0, 2.0)).collect();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let variance = samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / n as f64;
+        assert_close!(mean, 1.0, 0.05);
+        assert_close!(variance, 4.0, 0.15);
+    }
+}
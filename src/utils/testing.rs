@@ -0,0 +1,66 @@
+use crate::core::force::Force;
+use crate::core::particle::Particle;
+use crate::core::simdata::{Bounds, SimData};
+use crate::core::vector::Vector;
+
+/// Check a force's `pair_force` against its `pair_potential`. Two particles with unit radius and mass are placed at a
+/// separation `r` along x, and the analytic force on the second particle is compared to minus the derivative of the
+/// pair potential with respect to its position, found by a central finite difference with step `eps`. Returns the
+/// magnitude of the difference, which should be small for a correctly implemented force. Sign and factor errors show
+/// up as a discrepancy on the order of the force itself.
+pub fn check_force_matches_potential(force: &dyn Force, r: f64, eps: f64) -> f64 {
+    // Make the box large enough that periodic images of the particles never interact.
+    let half_width = f64::max(2.0 * (r + eps), 5.0);
+    let bounds = Bounds::from((-half_width, half_width, -half_width, half_width));
+    let mut sim_data = SimData::new_with_particles(bounds, &[Particle::new(), Particle::new()]);
+
+    sim_data.positions[1].x = r + eps;
+    let forward = force.pair_potential(&sim_data, 0, 1);
+    sim_data.positions[1].x = r - eps;
+    let backward = force.pair_potential(&sim_data, 0, 1);
+    sim_data.positions[1].x = r;
+    let analytic = force.pair_force(&sim_data, 0, 1);
+
+    let numerical = Vector::new(-(forward - backward) / (2.0 * eps), 0.0);
+    (analytic - numerical).length()
+}
+
+// =================================================================================================
+//  Unit Tests.
+// =================================================================================================
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::core::force::{HardSphereForce, ScaledForce};
+
+    /// A force whose pair force has the wrong sign.
+    struct BackwardsForce {
+        inner: HardSphereForce,
+    }
+
+    impl Force for BackwardsForce {
+        fn pair_force(&self, sim_data: &SimData, id1: usize, id2: usize) -> Vector {
+            Vector::zero() - self.inner.pair_force(sim_data, id1, id2)
+        }
+
+        fn pair_potential(&self, sim_data: &SimData, id1: usize, id2: usize) -> f64 {
+            self.inner.pair_potential(sim_data, id1, id2)
+        }
+    }
+
+    #[test]
+    fn test_check_force_matches_potential() {
+        let force = HardSphereForce::new(100.0);
+        for r in [0.5, 1.2, 1.9, 2.5] {
+            assert!(check_force_matches_potential(&force, r, 1.0e-6) < 1.0e-5);
+        }
+        let scaled = ScaledForce { inner: &force, scale: 0.3 };
+        assert!(check_force_matches_potential(&scaled, 1.5, 1.0e-6) < 1.0e-5);
+
+        // A sign error is caught.
+        let backwards = BackwardsForce { inner: HardSphereForce::new(100.0) };
+        assert!(1.0 < check_force_matches_potential(&backwards, 1.5, 1.0e-6));
+    }
+}